@@ -0,0 +1,43 @@
+//! Benchmarks `EncryptionContext::encrypt_many`'s rayon-parallel batch path
+//! against sealing the same batch one call to `encrypt_direct` at a time, to
+//! confirm the parallel path is actually worth reaching for. Requires a
+//! `[[bench]]` entry (harness = false) once this crate has a Cargo.toml.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use resolution_protocol::crypto::EncryptionContext;
+
+fn batch(size: usize, sender: &EncryptionContext) -> Vec<(oqs::kem::PublicKey, Vec<u8>)> {
+    (0..size)
+        .map(|i| {
+            let recipient = EncryptionContext::generate().unwrap();
+            (recipient.encryption_public_key().clone(), format!("message {i}").into_bytes())
+        })
+        .collect()
+}
+
+fn bench_encrypt_many(c: &mut Criterion) {
+    let sender = EncryptionContext::generate().unwrap();
+
+    let mut group = c.benchmark_group("encrypt_many");
+    for size in [1, 8, 64, 256] {
+        group.bench_function(format!("parallel/{size}"), |b| {
+            b.iter_batched(|| batch(size, &sender), |items| sender.encrypt_many(items), BatchSize::SmallInput);
+        });
+        group.bench_function(format!("serial/{size}"), |b| {
+            b.iter_batched(
+                || batch(size, &sender),
+                |items| {
+                    items
+                        .into_iter()
+                        .map(|(target, data)| sender.encrypt_direct(target, data))
+                        .collect::<Vec<_>>()
+                },
+                BatchSize::SmallInput,
+            );
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_encrypt_many);
+criterion_main!(benches);
@@ -0,0 +1,65 @@
+//! Metadata describing a file sent alongside an encrypted body, so the
+//! receiver knows what it's getting before downloading it.
+
+use serde::{Deserialize, Serialize};
+
+/// Signed, sent-alongside-the-body description of a file transfer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub filename: String,
+    pub content_type: String,
+    pub size: u64,
+    /// SHA-256 of the plaintext file content.
+    pub content_hash: [u8; 32],
+}
+
+impl Attachment {
+    /// Build an attachment, sanitizing `filename` so it can never be used
+    /// for a path-traversal write when the receiver saves it (stripping
+    /// directory separators and `..` segments, keeping only the final
+    /// path component).
+    pub fn new(filename: &str, content_type: impl Into<String>, size: u64, content_hash: [u8; 32]) -> Self {
+        Self {
+            filename: sanitize_filename(filename),
+            content_type: content_type.into(),
+            size,
+            content_hash,
+        }
+    }
+}
+
+/// Strip path separators and reject `.`/`..` so a filename taken from an
+/// untrusted attachment can be joined onto a save directory safely.
+fn sanitize_filename(filename: &str) -> String {
+    let candidate = filename
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or(filename);
+    match candidate {
+        "" | "." | ".." => "unnamed".to_string(),
+        name => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_traversal_filenames_are_sanitized() {
+        let a = Attachment::new("../../etc/passwd", "text/plain", 0, [0u8; 32]);
+        assert_eq!(a.filename, "passwd");
+
+        let b = Attachment::new("..\\..\\windows\\system32\\config", "text/plain", 0, [0u8; 32]);
+        assert_eq!(b.filename, "config");
+
+        let c = Attachment::new("..", "text/plain", 0, [0u8; 32]);
+        assert_eq!(c.filename, "unnamed");
+    }
+
+    #[test]
+    fn plain_filenames_pass_through() {
+        let a = Attachment::new("photo.png", "image/png", 1024, [1u8; 32]);
+        assert_eq!(a.filename, "photo.png");
+    }
+}
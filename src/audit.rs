@@ -0,0 +1,115 @@
+//! Auditable security events, orthogonal to this crate's fallible
+//! [`crate::Error`] results. Some conditions worth recording for a
+//! security audit trail aren't errors from the caller's point of view —
+//! [`crate::pinning::KeyPinStore::observe`] silently accepting a changed
+//! key is exactly the kind of thing an application wants to log even
+//! though the call itself succeeds. [`SecurityEvent`] never carries key
+//! material or plaintext, only identifiers and fingerprints, so it's
+//! always safe to persist or forward to an external audit sink.
+//!
+//! Nothing subscribes by default — [`emit`] is a no-op until an
+//! application calls [`set_security_sink`].
+
+use std::sync::RwLock;
+
+use uuid::Uuid;
+
+use crate::identity::ProfileId;
+
+/// A security-relevant occurrence, reported via [`emit`] to whatever sink
+/// [`set_security_sink`] installed. See the module doc for why this exists
+/// alongside [`crate::Error`] instead of replacing it.
+#[derive(Debug, Clone)]
+pub enum SecurityEvent {
+    /// A signature failed to verify against its claimed signer, from
+    /// [`crate::crypto::verify_detached`]. `signer_fingerprint` is the
+    /// same short, non-sensitive identifier carried by
+    /// [`crate::Error::SignatureInvalid`].
+    SignatureInvalid { signer_fingerprint: String },
+
+    /// A direct-message envelope couldn't be opened with any current or
+    /// retired key, from [`crate::Error::NoMatchingKey`].
+    DecryptionFailed,
+
+    /// A `(key_id, nonce)` pair was seen more than once under a group
+    /// key, from [`crate::crypto::NonceTracker`] via
+    /// [`crate::Error::NonceReuse`].
+    ReplayRejected { key_id: Uuid },
+
+    /// [`crate::pinning::KeyPinStore::observe`] saw a profile present
+    /// different key material than what was previously pinned for it.
+    KeyChangeDetected { profile_id: ProfileId },
+
+    /// A message from a revoked device or membership was rejected.
+    /// Reserved for when this crate grows a revocation store — nothing
+    /// emits this variant yet.
+    RevocationHit { profile_id: ProfileId },
+}
+
+type Sink = Box<dyn Fn(&SecurityEvent) + Send + Sync>;
+
+static SINK: RwLock<Option<Sink>> = RwLock::new(None);
+
+/// Install `sink` to receive every [`SecurityEvent`] this crate emits from
+/// now on, replacing whatever sink (if any) was installed before. Pass a
+/// closure that persists to a log, forwards to an alerting pipeline, or
+/// whatever an application's audit trail needs — [`emit`] calls it
+/// synchronously, so a slow sink slows down the call that triggered the
+/// event.
+pub fn set_security_sink(sink: impl Fn(&SecurityEvent) + Send + Sync + 'static) {
+    *SINK.write().unwrap() = Some(Box::new(sink));
+}
+
+/// Remove whatever sink [`set_security_sink`] installed, returning to the
+/// default no-op behavior.
+pub fn clear_security_sink() {
+    *SINK.write().unwrap() = None;
+}
+
+/// Report `event` to the installed sink, if any. A no-op when no sink has
+/// been installed.
+pub(crate) fn emit(event: SecurityEvent) {
+    if let Some(sink) = SINK.read().unwrap().as_ref() {
+        sink(&event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Tests in this module install a process-global sink, so they must
+    /// not run concurrently with each other or with tests elsewhere that
+    /// do the same (see [`crate::crypto::context::tests`]'s
+    /// `a_forged_signature_emits_a_signature_invalid_security_event` and
+    /// [`crate::pinning::tests`]'s
+    /// `observe_emits_a_key_change_detected_security_event_only_when_keys_change`).
+    /// `cargo test` runs `#[test]`s in parallel by default; there's no
+    /// in-crate lock shared across modules for this, so this is a known
+    /// gap rather than a guarantee.
+    #[test]
+    fn set_security_sink_receives_emitted_events() {
+        let received: Arc<Mutex<Vec<SecurityEvent>>> = Arc::new(Mutex::new(Vec::new()));
+        let received_in_sink = received.clone();
+        set_security_sink(move |event| received_in_sink.lock().unwrap().push(event.clone()));
+
+        emit(SecurityEvent::DecryptionFailed);
+        emit(SecurityEvent::ReplayRejected { key_id: Uuid::nil() });
+
+        let events = received.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], SecurityEvent::DecryptionFailed));
+        assert!(matches!(events[1], SecurityEvent::ReplayRejected { .. }));
+
+        clear_security_sink();
+    }
+
+    #[test]
+    fn clear_security_sink_returns_to_a_no_op() {
+        set_security_sink(|_| panic!("sink should have been cleared"));
+        clear_security_sink();
+
+        emit(SecurityEvent::DecryptionFailed);
+    }
+}
@@ -0,0 +1,71 @@
+//! Abstraction over "what time is it", so time-sensitive logic (profile
+//! expiry, presence/last-seen bookkeeping, and anything with a replay
+//! window) can be tested deterministically instead of depending on
+//! `SystemTime::now()` and real sleeps.
+//!
+//! Security note: expiry and replay-window checks are only as trustworthy
+//! as the clock behind them. [`SystemClock`] trusts the local OS clock,
+//! which an attacker with local access can skew; this crate doesn't defend
+//! against that beyond the existing grace windows (e.g.
+//! [`crate::profile::CLOCK_SKEW_TOLERANCE`]).
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A source of the current unix time, in seconds.
+pub trait Clock: Send + Sync {
+    fn now_unix(&self) -> u64;
+}
+
+/// The real wall clock.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_unix(&self) -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+    }
+}
+
+/// A fake clock for tests: starts at a fixed instant and only moves when
+/// explicitly told to, so expiry and replay-window tests don't depend on
+/// how fast the test happens to run.
+#[derive(Debug, Default)]
+pub struct MockClock(AtomicU64);
+
+impl MockClock {
+    pub fn at(unix_secs: u64) -> Self {
+        Self(AtomicU64::new(unix_secs))
+    }
+
+    pub fn advance(&self, secs: u64) {
+        self.0.fetch_add(secs, Ordering::SeqCst);
+    }
+
+    pub fn set(&self, unix_secs: u64) {
+        self.0.store(unix_secs, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now_unix(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mock_clock_only_moves_when_told() {
+        let clock = MockClock::at(1_000);
+        assert_eq!(clock.now_unix(), 1_000);
+
+        clock.advance(50);
+        assert_eq!(clock.now_unix(), 1_050);
+
+        clock.set(2_000);
+        assert_eq!(clock.now_unix(), 2_000);
+    }
+}
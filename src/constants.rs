@@ -0,0 +1,199 @@
+//! Crate-wide protocol constants.
+
+/// ALPN advertised by production endpoints.
+pub const PROTOCOL_ALPN: &[u8] = b"resolution/1";
+
+/// The wire protocol version this build speaks.
+pub const PROTOCOL_VERSION: &str = "1";
+
+/// A parsed `PROTOCOL_VERSION`-style string: `"<major>"` or
+/// `"<major>.<minor>"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+}
+
+/// Parse a version string of the form `"<major>"` or `"<major>.<minor>"`.
+/// Returns `None` for anything else, including empty strings, extra
+/// components, or non-numeric parts.
+pub fn parse_version(s: &str) -> Option<Version> {
+    let mut parts = s.splitn(2, '.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = match parts.next() {
+        Some(rest) => rest.parse().ok()?,
+        None => 0,
+    };
+    Some(Version { major, minor })
+}
+
+/// Whether `remote_version` can interoperate with [`PROTOCOL_VERSION`].
+///
+/// Policy: the major version must match exactly. Minor version is assumed
+/// additive-only (new optional fields, new message variants a peer can
+/// ignore) so any minor difference is still compatible. A version string
+/// this build can't parse is never compatible, since we have no way to
+/// know what it means.
+pub fn is_compatible(remote_version: &str) -> bool {
+    match (parse_version(PROTOCOL_VERSION), parse_version(remote_version)) {
+        (Some(local), Some(remote)) => local.major == remote.major,
+        _ => false,
+    }
+}
+
+/// Default content type used by [`crate::crypto::EncryptionContext::encrypt_direct`]
+/// when the caller doesn't care to tag the payload.
+pub const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Maximum length, in bytes, of a [`crate::crypto::SingleEncryption::content_type`] string.
+pub const MAX_CONTENT_TYPE_LEN: usize = 255;
+
+/// Identifies a specific KEM + signature + AEAD combination. Carried on
+/// the wire alongside an envelope so a receiver knows how to interpret
+/// it, and compared during capability negotiation so two peers only
+/// proceed once they agree on one. Centralized here instead of scattering
+/// the raw numbers across the modules that produce and consume them.
+pub type SuiteId = u8;
+
+/// ML-KEM-768 + Falcon-512 + AES-256-GCM: the crate's default suite,
+/// produced by [`crate::crypto::EncryptionContext::generate`].
+pub const SUITE_MLKEM768_FALCON512_AESGCM: SuiteId = 1;
+
+/// ML-KEM-1024 + Dilithium3 + AES-256-GCM, for callers opting into the
+/// higher security level via [`crate::crypto::KemChoice::MlKem1024`] and
+/// [`crate::crypto::SigChoice::Dilithium3`].
+pub const SUITE_MLKEM1024_DILITHIUM3_AESGCM: SuiteId = 2;
+
+/// Default cap on the number of recipients
+/// [`crate::crypto::EncryptionContext::encrypt_group`] will fan out to in
+/// one call. Each recipient costs a KEM encapsulation and a signature, so
+/// an unbounded recipient list drawn from untrusted input is a DoS
+/// vector; callers that trust their own recipient list (e.g. a locally
+/// assembled roster) can raise the limit via `encrypt_group_with_max`.
+pub const MAX_GROUP_RECIPIENTS: usize = 256;
+
+/// Size of each AEAD-sealed chunk
+/// [`crate::crypto::EncryptionContext::encrypt_group_stream`] reads and
+/// writes at a time. Bounds peak memory for a stream of any length to
+/// roughly this many bytes, rather than the whole body.
+pub const GROUP_STREAM_CHUNK_LEN: usize = 64 * 1024;
+
+/// Floor [`crate::transfer::FileTransfer::recommended_chunk_size`] clamps
+/// to, so a pathologically small negotiated frame size doesn't recommend a
+/// chunk so tiny that per-chunk overhead (framing, scheduling) would
+/// dominate the transfer.
+pub const MIN_FILE_TRANSFER_CHUNK_LEN: usize = 4 * 1024;
+
+/// Ceiling [`crate::transfer::FileTransfer::recommended_chunk_size`] clamps
+/// to, so an unusually generous negotiated frame size doesn't recommend a
+/// chunk so large that a single slow chunk stalls the transfer's apparent
+/// progress for a long time.
+pub const MAX_FILE_TRANSFER_CHUNK_LEN: usize = 1024 * 1024;
+
+/// Every suite this build understands, in ascending id order.
+pub const SUPPORTED_SUITES: &[SuiteId] = &[SUITE_MLKEM768_FALCON512_AESGCM, SUITE_MLKEM1024_DILITHIUM3_AESGCM];
+
+/// A human-readable name for a suite id, or `None` if this build doesn't
+/// recognize it.
+pub fn suite_name(suite: SuiteId) -> Option<&'static str> {
+    match suite {
+        SUITE_MLKEM768_FALCON512_AESGCM => Some("mlkem768-falcon512-aesgcm"),
+        SUITE_MLKEM1024_DILITHIUM3_AESGCM => Some("mlkem1024-dilithium3-aesgcm"),
+        _ => None,
+    }
+}
+
+/// Whether `local` and `remote` name a suite both sides can use. Exact
+/// match only for now: there's no fallback or negotiation, just success
+/// or a hard incompatibility.
+pub fn suite_compatible(local: SuiteId, remote: SuiteId) -> bool {
+    local == remote && suite_name(local).is_some()
+}
+
+/// Resolve `suite` to its name, or
+/// [`crate::error::UserError::UnknownAlgorithm`] if this build doesn't
+/// recognize the id at all. Distinct from
+/// [`crate::error::Error::AlgorithmNotEnabled`]: that's for an algorithm
+/// this build knows about but wasn't compiled with support for, whereas
+/// this is for an id nobody defined a suite for, e.g. because it came
+/// from a newer peer. Callers should reject rather than fall back to a
+/// default suite, since silently downgrading defeats the negotiation.
+pub fn resolve_suite(suite: SuiteId) -> crate::Result<&'static str> {
+    suite_name(suite).ok_or_else(|| crate::error::UserError::UnknownAlgorithm { name: format!("suite#{suite}") }.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_version_accepts_major_and_major_minor() {
+        assert_eq!(parse_version("1"), Some(Version { major: 1, minor: 0 }));
+        assert_eq!(parse_version("1.2"), Some(Version { major: 1, minor: 2 }));
+    }
+
+    #[test]
+    fn parse_version_rejects_malformed_input() {
+        assert_eq!(parse_version(""), None);
+        assert_eq!(parse_version("v1"), None);
+        assert_eq!(parse_version("1.2.3"), None);
+        assert_eq!(parse_version("1."), None);
+    }
+
+    #[test]
+    fn is_compatible_matches_equal_version() {
+        assert!(is_compatible(PROTOCOL_VERSION));
+    }
+
+    #[test]
+    fn is_compatible_ignores_minor_differences() {
+        assert!(is_compatible(&format!("{PROTOCOL_VERSION}.7")));
+    }
+
+    #[test]
+    fn is_compatible_rejects_different_major_versions() {
+        let older = parse_version(PROTOCOL_VERSION).unwrap().major.saturating_sub(1);
+        let newer = parse_version(PROTOCOL_VERSION).unwrap().major + 1;
+        assert!(!is_compatible(&older.to_string()));
+        assert!(!is_compatible(&newer.to_string()));
+    }
+
+    #[test]
+    fn is_compatible_rejects_malformed_remote_version() {
+        assert!(!is_compatible("not-a-version"));
+        assert!(!is_compatible(""));
+    }
+
+    #[test]
+    fn every_supported_suite_has_a_unique_id_and_a_name() {
+        let mut seen = std::collections::HashSet::new();
+        for &suite in SUPPORTED_SUITES {
+            assert!(seen.insert(suite), "duplicate suite id {suite}");
+            assert!(suite_name(suite).is_some(), "suite {suite} has no name");
+        }
+    }
+
+    #[test]
+    fn suite_compatible_requires_an_exact_known_match() {
+        assert!(suite_compatible(SUITE_MLKEM768_FALCON512_AESGCM, SUITE_MLKEM768_FALCON512_AESGCM));
+        assert!(!suite_compatible(SUITE_MLKEM768_FALCON512_AESGCM, SUITE_MLKEM1024_DILITHIUM3_AESGCM));
+        assert!(!suite_compatible(0, 0));
+    }
+
+    #[test]
+    fn resolve_suite_rejects_an_unknown_suite_id_instead_of_falling_back() {
+        // No envelope type in this crate carries a suite-id field yet, so
+        // this exercises the resolution path directly with a suite id a
+        // peer might advertise from a future build we don't understand.
+        let unknown_suite: SuiteId = 200;
+        let err = resolve_suite(unknown_suite).unwrap_err();
+        assert!(matches!(err, crate::Error::User(crate::error::UserError::UnknownAlgorithm { .. })));
+    }
+
+    #[test]
+    fn resolve_suite_accepts_every_supported_suite() {
+        for &suite in SUPPORTED_SUITES {
+            assert!(resolve_suite(suite).is_ok());
+        }
+    }
+}
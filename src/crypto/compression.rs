@@ -0,0 +1,135 @@
+//! Speculative plaintext compression, applied before AEAD encryption.
+//!
+//! Compressing already-compressed data (images, video, most encrypted or
+//! archived formats) wastes CPU and can grow the payload instead of
+//! shrinking it, so [`wrap`] always compresses speculatively and keeps
+//! whichever of the compressed or original bytes is smaller. The choice
+//! is recorded as a one-byte flag ahead of the stored bytes — the same
+//! trick [`super::padding`] uses to keep metadata inside the AEAD
+//! plaintext instead of changing the envelope's wire shape.
+
+use std::io::{Read, Write};
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+
+use crate::error::UserError;
+use crate::Result;
+
+/// Whether [`wrap`] chose to store `data` compressed, and the size before
+/// and after, for callers that want to record it in metrics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionOutcome {
+    pub compressed: bool,
+    pub original_len: usize,
+    pub stored_len: usize,
+}
+
+/// Compress `data` speculatively and keep whichever of the compressed or
+/// original bytes is smaller, prefixed with a flag byte recording the
+/// choice. Guarantees the result is never more than one byte larger than
+/// `data`, even for incompressible input.
+pub(crate) fn wrap(data: &[u8]) -> (Vec<u8>, CompressionOutcome) {
+    let candidate = deflate(data);
+    let (body, compressed) = match candidate {
+        Some(candidate) if candidate.len() < data.len() => (candidate, true),
+        _ => (data.to_vec(), false),
+    };
+
+    let mut framed = Vec::with_capacity(body.len() + 1);
+    framed.push(compressed as u8);
+    framed.extend_from_slice(&body);
+
+    let outcome = CompressionOutcome { compressed, original_len: data.len(), stored_len: framed.len() };
+    (framed, outcome)
+}
+
+/// The most [`unwrap`] will ever inflate one payload to. `body` reaches
+/// here from inside a decrypted envelope, so it's attacker-controlled;
+/// without a cap, a small compressed blob could decompress to an
+/// arbitrarily large allocation. Matches [`crate::framing::MAX_ENVELOPE_LEN`],
+/// since nothing legitimate needs a single message's plaintext to be
+/// bigger than the whole envelope it arrived in was ever allowed to be.
+const MAX_DECOMPRESSED_LEN: usize = crate::framing::MAX_ENVELOPE_LEN;
+
+/// Reverse [`wrap`], inflating the body if its flag byte says it's
+/// compressed.
+pub(crate) fn unwrap(framed: &[u8]) -> Result<Vec<u8>> {
+    let (&flag, body) = framed.split_first().ok_or(UserError::BadLength { expected: 1, got: 0 })?;
+    if flag == 0 {
+        return Ok(body.to_vec());
+    }
+
+    let decoder = DeflateDecoder::new(body);
+    // Read one byte past the cap so an exactly-at-the-limit payload isn't
+    // mistaken for an oversized one: if `out` still holds the extra byte
+    // afterward, the real output was too large.
+    let mut out = Vec::new();
+    decoder.take(MAX_DECOMPRESSED_LEN as u64 + 1).read_to_end(&mut out).map_err(anyhow::Error::from)?;
+    if out.len() > MAX_DECOMPRESSED_LEN {
+        return Err(UserError::DecompressedValueTooLarge { max: MAX_DECOMPRESSED_LEN }.into());
+    }
+    Ok(out)
+}
+
+fn deflate(data: &[u8]) -> Option<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).ok()?;
+    encoder.finish().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compressible_data_is_stored_compressed() {
+        let data = vec![b'a'; 10_000];
+        let (framed, outcome) = wrap(&data);
+        assert!(outcome.compressed);
+        assert!(framed.len() < data.len());
+        assert_eq!(unwrap(&framed).unwrap(), data);
+    }
+
+    #[test]
+    fn incompressible_random_data_is_stored_uncompressed() {
+        let mut data = vec![0u8; 4096];
+        let mut state: u64 = 0x2545F4914F6CDD1D;
+        for byte in data.iter_mut() {
+            // xorshift64: cheap, deterministic pseudo-randomness, good
+            // enough to be incompressible without pulling in a `rand`
+            // dependency just for test data.
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            *byte = state as u8;
+        }
+
+        let (framed, outcome) = wrap(&data);
+        assert!(!outcome.compressed);
+        assert_eq!(outcome.stored_len, data.len() + 1);
+        assert_eq!(unwrap(&framed).unwrap(), data);
+    }
+
+    #[test]
+    fn wrap_then_unwrap_round_trips_empty_data() {
+        let (framed, _) = wrap(&[]);
+        assert_eq!(unwrap(&framed).unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn unwrap_rejects_an_empty_frame() {
+        assert!(unwrap(&[]).is_err());
+    }
+
+    #[test]
+    fn unwrap_rejects_a_payload_that_would_decompress_past_the_cap() {
+        let data = vec![b'a'; MAX_DECOMPRESSED_LEN + 1];
+        let (framed, outcome) = wrap(&data);
+        assert!(outcome.compressed);
+
+        let err = unwrap(&framed).unwrap_err();
+        assert!(matches!(err, crate::Error::User(UserError::DecompressedValueTooLarge { .. })));
+    }
+}
@@ -0,0 +1,2363 @@
+//! Key material and the encrypt/decrypt operations built on it.
+
+use std::collections::VecDeque;
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use oqs::{kem, sig};
+use rand::RngCore;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::crypto::compression::{self, CompressionOutcome};
+use crate::crypto::decap_cache::DecapsulationCache;
+use crate::crypto::group::{GroupCipher, GroupKey};
+use crate::crypto::padding::{self, PaddingScheme};
+use crate::crypto::types::{EnvelopeKind, EnvelopeOverhead, GroupEncryption, GroupStreamManifest, SharedSecret, SingleEncryption};
+use crate::crypto::verify::verify_ct;
+use crate::error::{Error, UserError};
+use crate::identity::ProfileId;
+use crate::profile::PublicProfileData;
+use crate::Result;
+
+pub(crate) const KEM_ALG: kem::Algorithm = kem::Algorithm::MlKem768;
+pub(crate) const SIG_ALG: sig::Algorithm = sig::Algorithm::Falcon512;
+
+/// AES-256-GCM's authentication tag length. See [`EncryptionContext::overhead_bytes`].
+const AEAD_TAG_LEN: usize = 16;
+
+// Feature-gated algorithm matrix for `generate_with` below. This crate has
+// no `Cargo.toml` of its own in this checkout, so the `[features]` table
+// these `cfg`s are meant to pair with can't actually be declared here; a
+// full manifest would carry:
+//
+//   [features]
+//   default = ["mlkem768", "falcon512"]
+//   mlkem768 = []
+//   mlkem1024 = []
+//   falcon512 = []
+//   dilithium3 = []
+//   hybrid = ["mlkem768", "mlkem1024"]
+//
+// with `EncryptionContext::generate()` (unaffected by any of this) staying
+// on the always-available `KEM_ALG`/`SIG_ALG` default suite above, and each
+// single-feature combination ({mlkem768}, {mlkem1024}, {falcon512},
+// {dilithium3}, {hybrid}) building on its own as its own CI matrix leg.
+
+/// A KEM algorithm selectable via [`EncryptionContext::generate_with`],
+/// gated behind its cargo feature so downstream crates that only need one
+/// don't pay for liboqs pulling in support for all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KemChoice {
+    MlKem768,
+    MlKem1024,
+    /// A classical/post-quantum hybrid composition. Not yet implemented at
+    /// the ciphertext level (see [`Self::algorithm`]) — reserved so callers
+    /// can opt in ahead of it landing without a further breaking change.
+    Hybrid,
+}
+
+impl KemChoice {
+    fn algorithm(self) -> Result<kem::Algorithm> {
+        match self {
+            #[cfg(feature = "mlkem768")]
+            KemChoice::MlKem768 => Ok(kem::Algorithm::MlKem768),
+            #[cfg(not(feature = "mlkem768"))]
+            KemChoice::MlKem768 => Err(Error::AlgorithmNotEnabled("mlkem768")),
+
+            #[cfg(feature = "mlkem1024")]
+            KemChoice::MlKem1024 => Ok(kem::Algorithm::MlKem1024),
+            #[cfg(not(feature = "mlkem1024"))]
+            KemChoice::MlKem1024 => Err(Error::AlgorithmNotEnabled("mlkem1024")),
+
+            #[cfg(feature = "hybrid")]
+            KemChoice::Hybrid => Err(Error::AlgorithmNotEnabled("hybrid")),
+            #[cfg(not(feature = "hybrid"))]
+            KemChoice::Hybrid => Err(Error::AlgorithmNotEnabled("hybrid")),
+        }
+    }
+}
+
+/// A signature algorithm selectable via
+/// [`EncryptionContext::generate_with`], gated the same way as
+/// [`KemChoice`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigChoice {
+    Falcon512,
+    Dilithium3,
+}
+
+impl SigChoice {
+    fn algorithm(self) -> Result<sig::Algorithm> {
+        match self {
+            #[cfg(feature = "falcon512")]
+            SigChoice::Falcon512 => Ok(sig::Algorithm::Falcon512),
+            #[cfg(not(feature = "falcon512"))]
+            SigChoice::Falcon512 => Err(Error::AlgorithmNotEnabled("falcon512")),
+
+            #[cfg(feature = "dilithium3")]
+            SigChoice::Dilithium3 => Ok(sig::Algorithm::Dilithium3),
+            #[cfg(not(feature = "dilithium3"))]
+            SigChoice::Dilithium3 => Err(Error::AlgorithmNotEnabled("dilithium3")),
+        }
+    }
+}
+
+/// How many retired KEM secret keys [`EncryptionContext::rotate_encryption_key`]
+/// keeps around so messages encrypted before rotation propagated can still
+/// be decrypted. Oldest is evicted once this is exceeded.
+pub(crate) const KEM_KEY_RING_SIZE: usize = 3;
+
+/// Domain-separation prefixes prepended to every signed byte string before
+/// it's hashed and signed. Without these, a signature produced for one
+/// purpose could in principle be replayed and accepted for another if two
+/// record layouts ever happened to collide; each purpose gets its own
+/// namespace instead.
+pub mod domain {
+    pub const DIRECT_MESSAGE: &str = "resolution:direct:v1";
+    pub const GROUP_MESSAGE: &str = "resolution:group:v1";
+    pub const DEVICE_BINDING: &str = "resolution:device:v1";
+    pub const SIGNED_PROFILE: &str = "resolution:profile:v1";
+    pub const KEY_ROTATION: &str = "resolution:key-rotation:v1";
+    pub const SIGNED_VALUE: &str = "resolution:signed:v1";
+    pub const CAPABILITY_CONFIRM: &str = "resolution:capability-confirm:v1";
+    pub const GROUP_ADMIN_ACTION: &str = "resolution:group-admin-action:v1";
+    pub const HANDSHAKE_TRANSCRIPT: &str = "resolution:handshake-transcript:v1";
+}
+
+fn domain_prefixed(purpose: &str, bytes: &[u8]) -> Vec<u8> {
+    let mut prefixed = purpose.as_bytes().to_vec();
+    prefixed.push(0);
+    prefixed.extend_from_slice(bytes);
+    prefixed
+}
+
+/// Byte lengths of an algorithm pair's key material, ciphertexts, and
+/// shared secrets, queried without generating an actual keypair — useful
+/// for an application validating an imported key's length up front, or
+/// just documenting the wire sizes a given suite produces.
+/// [`sig_signature_max`](Self::sig_signature_max) is the one field that
+/// isn't a fixed length: Falcon (and most post-quantum signature
+/// schemes) produce signatures up to that many bytes, not exactly that
+/// many.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeySizes {
+    pub kem_public_key: usize,
+    pub kem_secret_key: usize,
+    pub kem_ciphertext: usize,
+    pub kem_shared_secret: usize,
+    pub sig_public_key: usize,
+    pub sig_secret_key: usize,
+    pub sig_signature_max: usize,
+}
+
+/// The exact key, ciphertext, and shared-secret sizes `kem_alg` and
+/// `sig_alg` produce. Takes the underlying oqs algorithm identifiers
+/// rather than [`KemChoice`]/[`SigChoice`], since those are gated behind
+/// this crate's own cargo features for [`EncryptionContext::generate_with`]
+/// and sizing a key has nothing to do with which of *this crate's*
+/// features happen to be compiled in. [`encryption_key_from_bytes`] and
+/// [`signing_key_from_bytes`] check an imported key's length against the
+/// crate's default suite's sizes before asking oqs to parse it, so a
+/// truncated or padded key is rejected with a specific
+/// [`UserError::BadLength`] instead of a generic "malformed key".
+pub fn key_sizes(kem_alg: kem::Algorithm, sig_alg: sig::Algorithm) -> Result<KeySizes> {
+    let kem = kem::Kem::new(kem_alg).map_err(anyhow::Error::from)?;
+    let sig = sig::Sig::new(sig_alg).map_err(anyhow::Error::from)?;
+    Ok(KeySizes {
+        kem_public_key: kem.length_public_key(),
+        kem_secret_key: kem.length_secret_key(),
+        kem_ciphertext: kem.length_ciphertext(),
+        kem_shared_secret: kem.length_shared_secret(),
+        sig_public_key: sig.length_public_key(),
+        sig_secret_key: sig.length_secret_key(),
+        sig_signature_max: sig.length_signature(),
+    })
+}
+
+/// Reconstruct a signing public key from raw bytes, e.g. after receiving a
+/// serialized [`crate::profile::PublicProfileData`].
+pub fn signing_key_from_bytes(bytes: &[u8]) -> Result<sig::PublicKey> {
+    let algo = sig::Sig::new(SIG_ALG).map_err(anyhow::Error::from)?;
+    let expected = algo.length_public_key();
+    if bytes.len() != expected {
+        return Err(UserError::BadLength { expected, got: bytes.len() }.into());
+    }
+    algo.public_key_from_bytes(bytes)
+        .map(|k| k.to_owned())
+        .ok_or_else(|| anyhow::anyhow!("malformed signing key").into())
+}
+
+/// A short, stable identifier for a signing public key, used only to
+/// name a signer in error messages (e.g. [`Error::SignatureInvalid`]) —
+/// not a security-sensitive value in its own right.
+fn signer_fingerprint(signer: &sig::PublicKey) -> String {
+    let digest = Sha256::digest(signer.as_ref());
+    digest[..8].iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Verify a detached signature over `bytes` signed for `purpose` (one of
+/// the [`domain`] constants), given the signer's public key. Doesn't
+/// require any local secret material, so it's a free function rather than
+/// an `EncryptionContext` method.
+///
+/// Returns [`Error::SignatureInvalid`] when the signature simply doesn't
+/// verify against `signer`. A malformed signature encoding or a failure to
+/// initialize the signature backend are different problems — genuine
+/// operational errors rather than "this signature is invalid" — and stay
+/// [`Error::Other`].
+pub fn verify_detached(purpose: &str, bytes: &[u8], signature: &[u8], signer: &sig::PublicKey) -> Result<()> {
+    let algo = sig::Sig::new(SIG_ALG).map_err(anyhow::Error::from)?;
+    let signature = algo
+        .signature_from_bytes(signature)
+        .ok_or_else(|| anyhow::anyhow!("malformed signature"))?;
+    algo.verify(&domain_prefixed(purpose, bytes), signature, signer).map_err(|_| {
+        let signer_fingerprint = signer_fingerprint(signer);
+        crate::audit::emit(crate::audit::SecurityEvent::SignatureInvalid { signer_fingerprint: signer_fingerprint.clone() });
+        Error::SignatureInvalid { signer_fingerprint }
+    })?;
+    Ok(())
+}
+
+/// Reconstruct an encryption public key from raw bytes.
+pub fn encryption_key_from_bytes(bytes: &[u8]) -> Result<kem::PublicKey> {
+    let algo = kem::Kem::new(KEM_ALG).map_err(anyhow::Error::from)?;
+    let expected = algo.length_public_key();
+    if bytes.len() != expected {
+        return Err(UserError::BadLength { expected, got: bytes.len() }.into());
+    }
+    algo.public_key_from_bytes(bytes)
+        .map(|k| k.to_owned())
+        .ok_or_else(|| anyhow::anyhow!("malformed encryption key").into())
+}
+
+/// A profile's full key material: a KEM keypair for encryption and a
+/// signature keypair for authenticity, both post-quantum.
+pub struct EncryptionContext {
+    sig_algo: sig::Sig,
+    sig_public: sig::PublicKey,
+    sig_secret: sig::SecretKey,
+    kem_algo: kem::Kem,
+    kem_public: kem::PublicKey,
+    kem_secret: kem::SecretKey,
+    /// Retired KEM secrets, newest first, from past calls to
+    /// [`Self::rotate_encryption_key`].
+    retired_kem_secrets: VecDeque<kem::SecretKey>,
+}
+
+/// Deliberately redacted: only the public keys, never the secret material.
+/// See [`EncryptionContext::to_secret_bytes`] for the genuine persistence
+/// path.
+impl Serialize for EncryptionContext {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct PublicOnly<'a> {
+            signing_public_key: &'a [u8],
+            encryption_public_key: &'a [u8],
+        }
+        PublicOnly { signing_public_key: self.sig_public.as_ref(), encryption_public_key: self.kem_public.as_ref() }.serialize(serializer)
+    }
+}
+
+/// Wire representation of the full secret key material, produced and
+/// consumed only by [`EncryptionContext::to_secret_bytes`]/
+/// [`EncryptionContext::from_secret_bytes`] — never by the redacted
+/// [`Serialize`] impl above.
+#[derive(Serialize, Deserialize)]
+struct SecretKeyMaterialWire {
+    sig_public: Vec<u8>,
+    sig_secret: Vec<u8>,
+    kem_public: Vec<u8>,
+    kem_secret: Vec<u8>,
+    retired_kem_secrets: Vec<Vec<u8>>,
+}
+
+fn random_nonce() -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    nonce
+}
+
+/// How many bytes [`check_entropy`] samples from the OS RNG for its sanity
+/// check.
+const ENTROPY_SAMPLE_LEN: usize = 256;
+
+/// The fewest distinct byte values [`check_entropy`] will accept in its
+/// sample before concluding the RNG looks stuck or under-seeded.
+/// Deliberately loose: a healthy RNG over 256 bytes routinely sees well
+/// over 100 distinct values, so this only catches output that's obviously
+/// degenerate rather than merely unlucky.
+const ENTROPY_MIN_DISTINCT_BYTES: usize = 16;
+
+/// Best-effort sanity check that the system RNG looks alive before relying
+/// on it to generate key material. Draws a small sample directly from
+/// `OsRng` (bypassing the thread-local generator [`random_nonce`] and
+/// friends use) and rejects it only if it's egregiously degenerate — every
+/// byte identical, or too few distinct values across the sample — which is
+/// the failure mode an under-seeded pool on a freshly booted or embedded
+/// system tends to produce.
+///
+/// This is **not** a statistical randomness test and can't catch a subtly
+/// biased RNG; it only guards against the RNG returning obviously
+/// non-random output or failing outright. Treat a passing result as "the
+/// RNG isn't visibly broken", not as a guarantee of genuine unpredictability.
+pub fn check_entropy() -> Result<()> {
+    let mut sample = [0u8; ENTROPY_SAMPLE_LEN];
+    rand::rngs::OsRng.try_fill_bytes(&mut sample).map_err(|e| anyhow::anyhow!(e))?;
+
+    let mut seen = [false; 256];
+    for &byte in &sample {
+        seen[byte as usize] = true;
+    }
+    let distinct = seen.iter().filter(|&&s| s).count();
+
+    if distinct < ENTROPY_MIN_DISTINCT_BYTES {
+        return Err(Error::InsufficientEntropy);
+    }
+    Ok(())
+}
+
+/// Check a decoded nonce is exactly `expected` bytes before it's handed to
+/// `Nonce::from_slice`, which panics rather than erroring on a length
+/// mismatch.
+fn validate_nonce_len(bytes: &[u8], expected: usize) -> Result<()> {
+    if bytes.len() != expected {
+        return Err(UserError::BadNonceLength { expected, got: bytes.len() }.into());
+    }
+    Ok(())
+}
+
+fn cipher_for(secret: &SharedSecret) -> Result<Aes256Gcm> {
+    Aes256Gcm::new_from_slice(secret.as_bytes()).map_err(|e| anyhow::anyhow!(e).into())
+}
+
+/// Open `data`'s payload under an already-known `SharedSecret`, without
+/// attempting a decapsulation — the shared step between a fresh
+/// decapsulation and a [`DecapsulationCache`] hit.
+fn open_payload_with(shared: &SharedSecret, data: &SingleEncryption, aad: &[u8]) -> Option<Vec<u8>> {
+    cipher_for(shared).ok()?.decrypt(Nonce::from_slice(&data.nonce), Payload { msg: &data.payload, aad }).ok()
+}
+
+/// The nonce for chunk `index` of a [`GroupStreamManifest`]-described
+/// stream: `prefix` followed by the index, big-endian. Unique per chunk as
+/// long as `prefix` is (see [`GroupStreamManifest::nonce_prefix`]).
+fn stream_nonce(prefix: [u8; 4], index: u64) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[..4].copy_from_slice(&prefix);
+    nonce[4..].copy_from_slice(&index.to_be_bytes());
+    nonce
+}
+
+/// The largest ciphertext length [`EncryptionContext::encrypt_group_body_stream`]
+/// ever emits for one chunk: a plaintext chunk up to
+/// [`crate::constants::GROUP_STREAM_CHUNK_LEN`], plus AES-256-GCM's fixed
+/// authentication tag.
+const MAX_GROUP_STREAM_CHUNK_CIPHERTEXT_LEN: usize = crate::constants::GROUP_STREAM_CHUNK_LEN + AEAD_TAG_LEN;
+
+/// Parse a group stream chunk's big-endian length prefix, rejecting one
+/// bigger than a legitimate sender would ever emit *before* it's used to
+/// size an allocation — see [`UserError::GroupStreamChunkTooLarge`].
+fn read_group_stream_chunk_len(len_buf: &[u8; 4]) -> Result<usize> {
+    let len = u32::from_be_bytes(*len_buf) as usize;
+    if len > MAX_GROUP_STREAM_CHUNK_CIPHERTEXT_LEN {
+        return Err(UserError::GroupStreamChunkTooLarge { len, max: MAX_GROUP_STREAM_CHUNK_CIPHERTEXT_LEN }.into());
+    }
+    Ok(len)
+}
+
+/// A [`std::io::Write`] sink for [`EncryptionContext::decrypt_group_stream_verified`]
+/// that spills decrypted plaintext to a temp file instead of an in-memory
+/// buffer, so accumulating a whole stream before releasing it to the
+/// caller doesn't cost RAM proportional to the stream's size. Deleted on
+/// drop regardless of whether [`Self::copy_to`] is ever reached.
+struct GroupStreamStaging {
+    file: std::fs::File,
+    path: std::path::PathBuf,
+}
+
+impl GroupStreamStaging {
+    fn new() -> Result<Self> {
+        let path = std::env::temp_dir().join(format!("resolution-protocol-group-stream-{}", uuid::Uuid::new_v4()));
+        let file = std::fs::File::create(&path).map_err(anyhow::Error::from)?;
+        Ok(Self { file, path })
+    }
+
+    /// Rewind and copy everything written so far to `writer`.
+    fn copy_to(&mut self, writer: &mut impl std::io::Write) -> Result<()> {
+        use std::io::{Seek, Write as _};
+        self.file.flush().map_err(anyhow::Error::from)?;
+        self.file.rewind().map_err(anyhow::Error::from)?;
+        std::io::copy(&mut self.file, writer).map_err(anyhow::Error::from)?;
+        Ok(())
+    }
+}
+
+impl std::io::Write for GroupStreamStaging {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        use std::io::Write as _;
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        use std::io::Write as _;
+        self.file.flush()
+    }
+}
+
+impl Drop for GroupStreamStaging {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Fill `buf` from `reader`, stopping early only at genuine EOF (a `read`
+/// returning `0`) rather than after a single short read — sockets and
+/// pipes routinely hand back less than requested even mid-stream.
+/// Returns how much of `buf` was actually filled.
+fn read_full_or_eof(reader: &mut impl std::io::Read, buf: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let read = reader.read(&mut buf[filled..]).map_err(anyhow::Error::from)?;
+        if read == 0 {
+            break;
+        }
+        filled += read;
+    }
+    Ok(filled)
+}
+
+/// NIST PQC security level for a KEM algorithm. Unrecognized algorithms
+/// (any future addition this table hasn't caught up with) are treated as
+/// the lowest level rather than panicking, so [`EncryptionContext::security_level`]
+/// stays a conservative estimate instead of a crash.
+fn kem_security_level(algo: kem::Algorithm) -> u8 {
+    match algo {
+        kem::Algorithm::MlKem768 => 3,
+        kem::Algorithm::MlKem1024 => 5,
+        _ => 1,
+    }
+}
+
+/// NIST PQC security level for a signature algorithm. See [`kem_security_level`].
+fn sig_security_level(algo: sig::Algorithm) -> u8 {
+    match algo {
+        sig::Algorithm::Falcon512 => 1,
+        sig::Algorithm::Dilithium3 => 3,
+        _ => 1,
+    }
+}
+
+impl EncryptionContext {
+    /// Generate a fresh keypair using the crate's default crypto suite
+    /// (ML-KEM-768 for encryption, Falcon-512 for signatures).
+    pub fn generate() -> Result<Self> {
+        let sig_algo = sig::Sig::new(SIG_ALG).map_err(anyhow::Error::from)?;
+        let (sig_public, sig_secret) = sig_algo.keypair().map_err(anyhow::Error::from)?;
+        let kem_algo = kem::Kem::new(KEM_ALG).map_err(anyhow::Error::from)?;
+        let (kem_public, kem_secret) = kem_algo.keypair().map_err(anyhow::Error::from)?;
+        Ok(Self {
+            sig_algo,
+            sig_public,
+            sig_secret,
+            kem_algo,
+            kem_public,
+            kem_secret,
+            retired_kem_secrets: VecDeque::new(),
+        })
+    }
+
+    /// As [`Self::generate`], but first runs [`check_entropy`] and fails
+    /// with [`Error::InsufficientEntropy`] instead of generating key
+    /// material an attacker might be able to predict. Not the default
+    /// [`Self::generate`] behavior: the check costs an extra RNG read and,
+    /// per [`check_entropy`]'s own caveat, is only a best-effort guard —
+    /// callers on hardware where that tradeoff matters (freshly booted or
+    /// embedded systems) opt in explicitly by calling this instead.
+    pub fn generate_checked() -> Result<Self> {
+        check_entropy()?;
+        Self::generate()
+    }
+
+    /// Generate a fresh keypair using an explicitly chosen algorithm pair
+    /// rather than the crate default. Fails with
+    /// [`Error::AlgorithmNotEnabled`] if `kem` or `sig`'s cargo feature
+    /// isn't compiled into this build.
+    pub fn generate_with(kem: KemChoice, sig: SigChoice) -> Result<Self> {
+        let sig_algo = sig::Sig::new(sig.algorithm()?).map_err(anyhow::Error::from)?;
+        let (sig_public, sig_secret) = sig_algo.keypair().map_err(anyhow::Error::from)?;
+        let kem_algo = kem::Kem::new(kem.algorithm()?).map_err(anyhow::Error::from)?;
+        let (kem_public, kem_secret) = kem_algo.keypair().map_err(anyhow::Error::from)?;
+        Ok(Self {
+            sig_algo,
+            sig_public,
+            sig_secret,
+            kem_algo,
+            kem_public,
+            kem_secret,
+            retired_kem_secrets: VecDeque::new(),
+        })
+    }
+
+    pub fn signing_public_key(&self) -> &sig::PublicKey {
+        &self.sig_public
+    }
+
+    pub fn encryption_public_key(&self) -> &kem::PublicKey {
+        &self.kem_public
+    }
+
+    /// The KEM and signature algorithm names this context uses, e.g.
+    /// `("ML-KEM-768", "Falcon-512")` for a context built with
+    /// [`Self::generate`]. Read from the algorithm identifiers this
+    /// context actually holds, so a context built via
+    /// [`Self::generate_with`] reports whatever it negotiated rather than
+    /// the crate defaults.
+    pub fn algorithms(&self) -> (String, String) {
+        (self.kem_algo.algorithm().to_string(), self.sig_algo.algorithm().to_string())
+    }
+
+    /// The NIST post-quantum security level (1, 3, or 5) this context's
+    /// pairing provides, reported as the minimum of the KEM and signature
+    /// algorithm's levels: an attacker only needs to break whichever
+    /// primitive is weaker to compromise the pairing as a whole.
+    pub fn security_level(&self) -> u8 {
+        kem_security_level(self.kem_algo.algorithm()).min(sig_security_level(self.sig_algo.algorithm()))
+    }
+
+    /// The nonce length, in bytes, this context's AEAD suite requires.
+    /// Every suite this build supports (currently just AES-256-GCM) uses
+    /// 12 bytes, but callers that decode a nonce off the wire should ask
+    /// this rather than hardcoding `12`, so a future suite with a
+    /// different nonce size (e.g. XChaCha20's 24 bytes) only has to change
+    /// this method.
+    pub fn nonce_len(&self) -> usize {
+        12
+    }
+
+    /// The fixed and worst-case per-message overhead this context's suite
+    /// adds, computed from the KEM/signature algorithms' own parameters
+    /// rather than by encrypting anything. Lets an app size an effective
+    /// MTU or chunk budget for `encrypt_direct`/`encrypt_group` up front.
+    /// See [`EnvelopeOverhead`].
+    pub fn overhead_bytes(&self) -> EnvelopeOverhead {
+        EnvelopeOverhead {
+            kem_ciphertext_len: self.kem_algo.length_ciphertext(),
+            nonce_len: self.nonce_len(),
+            aead_tag_len: AEAD_TAG_LEN,
+            max_signature_len: self.sig_algo.length_signature(),
+            group_key_id_len: 16,
+        }
+    }
+
+    /// Serialize the full secret key material — both keypairs, plus the
+    /// retired-key ring — for a genuine persistence path (e.g. the
+    /// sealed-profile feature). Unlike this type's redacted [`Serialize`]
+    /// impl, this is an explicit opt-in a caller has to reach for on
+    /// purpose, so a secret key can't leak out through a casual
+    /// `serde_json::to_string` of something that happens to embed one.
+    pub fn to_secret_bytes(&self) -> Result<Vec<u8>> {
+        let wire = SecretKeyMaterialWire {
+            sig_public: self.sig_public.as_ref().to_vec(),
+            sig_secret: self.sig_secret.as_ref().to_vec(),
+            kem_public: self.kem_public.as_ref().to_vec(),
+            kem_secret: self.kem_secret.as_ref().to_vec(),
+            retired_kem_secrets: self.retired_kem_secrets.iter().map(|k| k.as_ref().to_vec()).collect(),
+        };
+        crate::encoding::to_vec(&wire)
+    }
+
+    /// Reconstruct an `EncryptionContext` from bytes produced by
+    /// [`Self::to_secret_bytes`].
+    pub fn from_secret_bytes(bytes: &[u8]) -> Result<Self> {
+        let wire: SecretKeyMaterialWire = crate::encoding::from_slice(bytes)?;
+
+        let sig_algo = sig::Sig::new(SIG_ALG).map_err(anyhow::Error::from)?;
+        let sig_public = sig_algo
+            .public_key_from_bytes(&wire.sig_public)
+            .map(|k| k.to_owned())
+            .ok_or_else(|| anyhow::anyhow!("malformed signing public key"))?;
+        let sig_secret = sig_algo
+            .secret_key_from_bytes(&wire.sig_secret)
+            .map(|k| k.to_owned())
+            .ok_or_else(|| anyhow::anyhow!("malformed signing secret key"))?;
+
+        let kem_algo = kem::Kem::new(KEM_ALG).map_err(anyhow::Error::from)?;
+        let kem_public = kem_algo
+            .public_key_from_bytes(&wire.kem_public)
+            .map(|k| k.to_owned())
+            .ok_or_else(|| anyhow::anyhow!("malformed encryption public key"))?;
+        let kem_secret = kem_algo
+            .secret_key_from_bytes(&wire.kem_secret)
+            .map(|k| k.to_owned())
+            .ok_or_else(|| anyhow::anyhow!("malformed encryption secret key"))?;
+        let retired_kem_secrets = wire
+            .retired_kem_secrets
+            .iter()
+            .map(|bytes| {
+                kem_algo
+                    .secret_key_from_bytes(bytes)
+                    .map(|k| k.to_owned())
+                    .ok_or_else(|| anyhow::anyhow!("malformed retired encryption secret key"))
+            })
+            .collect::<std::result::Result<VecDeque<_>, _>>()?;
+
+        Ok(Self { sig_algo, sig_public, sig_secret, kem_algo, kem_public, kem_secret, retired_kem_secrets })
+    }
+
+    fn sign(&self, purpose: &str, bytes: &[u8]) -> Result<Vec<u8>> {
+        self.sign_detached(purpose, bytes)
+    }
+
+    fn verify(&self, purpose: &str, bytes: &[u8], signature: &[u8], signer: &sig::PublicKey) -> Result<()> {
+        verify_detached(purpose, bytes, signature, signer)
+    }
+
+    /// Regenerate the signing keypair while leaving the KEM keypair
+    /// untouched, so ciphertexts already sealed to this identity stay
+    /// decryptable. Returns the new public signing key together with a
+    /// detached signature from the *old* signing key over the new key's
+    /// bytes, so a contact who already trusts the old key can verify
+    /// continuity before accepting the new one.
+    ///
+    /// This changes [`crate::discriminant::discriminant`] (it hashes both
+    /// public keys) and therefore the profile's
+    /// [`crate::identity::ProfileId`] — republish the profile afterwards.
+    pub fn rotate_signing_key(&mut self) -> Result<(sig::PublicKey, Vec<u8>)> {
+        let (new_public, new_secret) = self.sig_algo.keypair().map_err(anyhow::Error::from)?;
+        let binding = self.sign_detached(domain::KEY_ROTATION, new_public.as_ref())?;
+        self.sig_public = new_public.clone();
+        self.sig_secret = new_secret;
+        Ok((new_public, binding))
+    }
+
+    /// Regenerate the KEM keypair while leaving the signing identity
+    /// stable, so contacts don't need to re-pin anything. Returns the new
+    /// public encryption key together with an announcement of it signed by
+    /// the unchanged signing key, so contacts can trust the update came
+    /// from this identity.
+    ///
+    /// Like [`Self::rotate_signing_key`], this changes
+    /// [`crate::discriminant::discriminant`] and hence the profile's
+    /// [`crate::identity::ProfileId`] — republish the profile afterwards.
+    /// The old KEM secret moves into a small retirement ring (see
+    /// [`Self::decrypt_direct`]), so ciphertexts already sealed to it
+    /// during the propagation window still decrypt; once
+    /// [`KEM_KEY_RING_SIZE`] rotations have happened since, it's evicted
+    /// for good.
+    pub fn rotate_encryption_key(&mut self) -> Result<(kem::PublicKey, Vec<u8>)> {
+        let (new_public, new_secret) = self.kem_algo.keypair().map_err(anyhow::Error::from)?;
+        let announcement = self.sign_detached(domain::KEY_ROTATION, new_public.as_ref())?;
+        let old_secret = std::mem::replace(&mut self.kem_secret, new_secret);
+        self.kem_public = new_public.clone();
+        self.retired_kem_secrets.push_front(old_secret);
+        self.retired_kem_secrets.truncate(KEM_KEY_RING_SIZE);
+        Ok((new_public, announcement))
+    }
+
+    /// Sign arbitrary bytes with this profile's signing key under a
+    /// [`domain`] purpose. Used internally by the encrypt paths and
+    /// available for callers that need to sign non-envelope data (e.g. a
+    /// device entry or a profile announcement).
+    pub fn sign_detached(&self, purpose: &str, bytes: &[u8]) -> Result<Vec<u8>> {
+        Ok(self.sig_algo.sign(&domain_prefixed(purpose, bytes), &self.sig_secret).map_err(anyhow::Error::from)?.into_vec())
+    }
+
+    /// Encapsulate a fresh shared secret to `target`'s KEM public key,
+    /// without wrapping it in a [`SingleEncryption`] envelope or signing
+    /// anything. For an application that wants to run its own protocol on
+    /// top of the raw KEM agreement — a Noise-like handshake, a symmetric
+    /// ratchet — instead of this crate's AEAD envelope. A caller that uses
+    /// this takes on responsibility for framing, authenticating, and
+    /// binding the resulting secret itself; [`Self::encrypt_direct`]
+    /// already does all of that and is almost always the right choice
+    /// otherwise.
+    pub fn encapsulate_to(&self, target: &kem::PublicKey) -> Result<(kem::Ciphertext, SharedSecret)> {
+        let (ciphertext, raw_secret) = self.kem_algo.encapsulate(target).map_err(anyhow::Error::from)?;
+        let secret = SharedSecret::from_slice(raw_secret.into_vec().as_slice())?;
+        Ok((ciphertext, secret))
+    }
+
+    /// Decapsulate a shared secret from `ciphertext`, the counterpart to
+    /// [`Self::encapsulate_to`]. Only tries this context's current KEM
+    /// secret — unlike [`Self::decrypt_direct`], there's no fallback to the
+    /// retired-key ring, since a caller managing their own protocol on top
+    /// of this primitive is expected to track which key a ciphertext was
+    /// sealed to.
+    pub fn decapsulate(&self, ciphertext: &kem::Ciphertext) -> Result<SharedSecret> {
+        let raw_secret = self.kem_algo.decapsulate(&self.kem_secret, ciphertext).map_err(anyhow::Error::from)?;
+        SharedSecret::from_slice(raw_secret.into_vec().as_slice())
+    }
+
+    /// Seal `data` to `target`'s KEM public key, tagged with the default
+    /// content type. See [`Self::encrypt_direct_typed`] to tag it.
+    pub fn encrypt_direct(&self, target: impl AsRef<kem::PublicKey>, data: impl AsRef<[u8]>) -> Result<SingleEncryption> {
+        self.encrypt_direct_typed(target, data, crate::constants::DEFAULT_CONTENT_TYPE)
+    }
+
+    /// Seal `data` to `target`'s KEM public key: encapsulate a fresh shared
+    /// secret, encrypt under it, and sign the ciphertext together with
+    /// `content_type` so the receiver can dispatch on it without sniffing
+    /// the decrypted bytes.
+    pub fn encrypt_direct_typed(
+        &self,
+        target: impl AsRef<kem::PublicKey>,
+        data: impl AsRef<[u8]>,
+        content_type: impl Into<String>,
+    ) -> Result<SingleEncryption> {
+        self.seal_direct(target, data, content_type, EnvelopeKind::Direct)
+    }
+
+    /// Seal `key` to `target`'s KEM public key as a
+    /// [`EnvelopeKind::GroupWrapped`] envelope, for the group-key
+    /// distribution step of a [`crate::message::Message::GroupInvite`].
+    /// Open with [`Self::decrypt_group_key_from`].
+    pub fn encrypt_group_key_to(&self, target: impl AsRef<kem::PublicKey>, key: &GroupKey) -> Result<SingleEncryption> {
+        let mut plaintext = key.id().as_bytes().to_vec();
+        plaintext.extend_from_slice(key.secret().as_bytes());
+        self.seal_direct(target, plaintext, crate::constants::DEFAULT_CONTENT_TYPE, EnvelopeKind::GroupWrapped)
+    }
+
+    /// The shared implementation behind [`Self::encrypt_direct_typed`] and
+    /// [`Self::encrypt_group_key_to`]: encapsulate a fresh shared secret,
+    /// encrypt under it, and sign the ciphertext together with
+    /// `content_type` and `kind` so a receiver can dispatch on either
+    /// without sniffing the decrypted bytes. Binds no AAD; see
+    /// [`Self::seal_direct_with_aad`] for the variant that does.
+    fn seal_direct(&self, target: impl AsRef<kem::PublicKey>, data: impl AsRef<[u8]>, content_type: impl Into<String>, kind: EnvelopeKind) -> Result<SingleEncryption> {
+        self.seal_direct_with_aad(target, data, content_type, kind, &[])
+    }
+
+    /// As [`Self::seal_direct`], but also binding `aad` into the AEAD
+    /// payload and the signed bytes, so an envelope sealed with one `aad`
+    /// can't be reopened by [`Self::open_direct_with_aad`] under another —
+    /// the primitive [`Self::encrypt_direct_to_typed`] uses to bind a
+    /// recipient's `profile_id`, and [`crate::crypto::Conversation`] uses
+    /// to bind a conversation id.
+    pub(crate) fn seal_direct_with_aad(&self, target: impl AsRef<kem::PublicKey>, data: impl AsRef<[u8]>, content_type: impl Into<String>, kind: EnvelopeKind, aad: &[u8]) -> Result<SingleEncryption> {
+        let content_type = content_type.into();
+        if content_type.len() > crate::constants::MAX_CONTENT_TYPE_LEN {
+            return Err(crate::error::UserError::ContentTypeTooLong {
+                len: content_type.len(),
+                max: crate::constants::MAX_CONTENT_TYPE_LEN,
+            }
+            .into());
+        }
+
+        let (kem_ciphertext, raw_secret) = self
+            .kem_algo
+            .encapsulate(target.as_ref())
+            .map_err(anyhow::Error::from)?;
+        let secret = SharedSecret::from_slice(raw_secret.into_vec().as_slice())?;
+        let nonce = random_nonce();
+        let payload = cipher_for(&secret)?
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: data.as_ref(), aad })
+            .map_err(|_| anyhow::anyhow!("aead encryption failed"))?;
+
+        let kem_ciphertext = kem_ciphertext.into_vec();
+        let mut to_sign = kem_ciphertext.clone();
+        to_sign.extend_from_slice(&payload);
+        to_sign.extend_from_slice(content_type.as_bytes());
+        to_sign.push(kind.signed_byte());
+        to_sign.extend_from_slice(aad);
+        let signature = self.sign(domain::DIRECT_MESSAGE, &to_sign)?;
+
+        Ok(SingleEncryption::new(kem_ciphertext, nonce.to_vec(), payload, signature, content_type, kind))
+    }
+
+    /// As [`Self::encrypt_direct`], but sealing to `recipient` directly
+    /// instead of a bare KEM key, and binding the ciphertext to their
+    /// `profile_id` — as AEAD associated data and as part of the signed
+    /// bytes — so it can't be replayed as if addressed to a different
+    /// contact. Open with [`Self::decrypt_direct_to`].
+    pub fn encrypt_direct_to(&self, recipient: &PublicProfileData, data: impl AsRef<[u8]>) -> Result<SingleEncryption> {
+        self.encrypt_direct_to_typed(recipient, data, crate::constants::DEFAULT_CONTENT_TYPE)
+    }
+
+    /// As [`Self::encrypt_direct_to`], tagged with `content_type`. See
+    /// [`Self::encrypt_direct_typed`].
+    pub fn encrypt_direct_to_typed(&self, recipient: &PublicProfileData, data: impl AsRef<[u8]>, content_type: impl Into<String>) -> Result<SingleEncryption> {
+        let aad = recipient.profile_id().as_str().as_bytes();
+        self.seal_direct_with_aad(recipient.encryption_key(), data, content_type, EnvelopeKind::Direct, aad)
+    }
+
+    /// Like [`Self::encrypt_direct`], but pads the plaintext up to
+    /// `scheme`'s next bucket before encrypting, so ciphertext length only
+    /// reveals which bucket the true length fell into rather than the
+    /// exact byte count. The true length travels inside the padded
+    /// plaintext itself, so it's covered by the AEAD tag along with
+    /// everything else. Open with [`Self::decrypt_direct_padded`].
+    pub fn encrypt_direct_padded(&self, target: impl AsRef<kem::PublicKey>, data: impl AsRef<[u8]>, scheme: PaddingScheme) -> Result<SingleEncryption> {
+        let padded = padding::pad(data.as_ref(), scheme);
+        self.encrypt_direct_typed(target, padded, crate::constants::DEFAULT_CONTENT_TYPE)
+    }
+
+    /// Open an envelope produced by [`Self::encrypt_direct_padded`],
+    /// trimming the padding back off after decryption. Using this on an
+    /// envelope that wasn't padded (or decrypting with plain
+    /// [`Self::decrypt_direct`] on one that was) will fail or return
+    /// garbage, since the two are only compatible with their own kind.
+    pub fn decrypt_direct_padded(&self, data: &SingleEncryption, signer: impl AsRef<sig::PublicKey>) -> Result<(String, Vec<u8>)> {
+        let (content_type, padded) = self.decrypt_direct(data, signer)?;
+        Ok((content_type, padding::unpad(&padded)?))
+    }
+
+    /// Like [`Self::encrypt_direct`], but compresses the plaintext first
+    /// if that actually makes it smaller — already-compressed data
+    /// (images, video, ciphertext) often doesn't shrink further, so this
+    /// only keeps the compressed form when it wins, and never grows the
+    /// payload by more than the one-byte flag [`compression::wrap`] uses
+    /// to record its choice. The returned [`CompressionOutcome`] is for
+    /// callers that want to log or export the decision. Open with
+    /// [`Self::decrypt_direct_compressed`].
+    pub fn encrypt_direct_compressed(&self, target: impl AsRef<kem::PublicKey>, data: impl AsRef<[u8]>) -> Result<(SingleEncryption, CompressionOutcome)> {
+        let (wrapped, outcome) = compression::wrap(data.as_ref());
+        let envelope = self.encrypt_direct_typed(target, wrapped, crate::constants::DEFAULT_CONTENT_TYPE)?;
+        Ok((envelope, outcome))
+    }
+
+    /// Open an envelope produced by [`Self::encrypt_direct_compressed`],
+    /// inflating the plaintext back out if it was stored compressed.
+    pub fn decrypt_direct_compressed(&self, data: &SingleEncryption, signer: impl AsRef<sig::PublicKey>) -> Result<(String, Vec<u8>)> {
+        let (content_type, wrapped) = self.decrypt_direct(data, signer)?;
+        Ok((content_type, compression::unwrap(&wrapped)?))
+    }
+
+    /// Seal each `(recipient, payload)` pair in `items` with
+    /// [`Self::encrypt_direct`], independently — unlike [`Self::encrypt_group`],
+    /// these aren't a shared body sealed once per recipient, so there's no
+    /// batch-level failure: the result at index `i` corresponds to
+    /// `items[i]`, `Ok` or `Err` on its own. Runs across the `rayon` global
+    /// thread pool instead of serially, one per core, the same tradeoff
+    /// [`crate::profile::SignedProfile::verify_batch`] makes for batch
+    /// signature verification. Useful for a relay fanning a burst of
+    /// independent direct messages out to many recipients at once.
+    pub fn encrypt_many(&self, items: Vec<(kem::PublicKey, Vec<u8>)>) -> Vec<Result<SingleEncryption>> {
+        items.into_par_iter().map(|(target, data)| self.encrypt_direct(&target, data)).collect()
+    }
+
+    /// Check `data`'s signature without decapsulating anything, so a
+    /// receiver can drop unsigned or forged envelopes before spending a
+    /// KEM decapsulation on them. [`Self::decrypt_direct`] does not call
+    /// this: it checks the signature through `verify_ct` and decapsulates
+    /// regardless of that check's outcome.
+    pub fn verify_sender(&self, data: &SingleEncryption, signer: impl AsRef<sig::PublicKey>) -> Result<()> {
+        let mut signed_bytes = data.kem_ciphertext.clone();
+        signed_bytes.extend_from_slice(&data.payload);
+        signed_bytes.extend_from_slice(data.content_type.as_bytes());
+        signed_bytes.push(data.kind().signed_byte());
+        self.verify(domain::DIRECT_MESSAGE, &signed_bytes, &data.signature, signer.as_ref())
+    }
+
+    /// Open an envelope sealed by [`Self::encrypt_direct`], verifying the
+    /// sender's signature and decapsulating. Returns the envelope's
+    /// content type alongside the plaintext.
+    ///
+    /// Tries the current KEM key first, then each retired key from
+    /// [`Self::rotate_encryption_key`]'s ring, newest first, so envelopes
+    /// sealed just before a rotation propagated still open. Returns
+    /// [`Error::NoMatchingKey`] only once every key has failed.
+    ///
+    /// Unlike calling [`Self::verify_sender`] and then decrypting
+    /// separately, this runs the decapsulation attempts unconditionally
+    /// before looking at the signature result, via [`verify_ct`]: a caller
+    /// who forged a ciphertext but not the signature still costs us the
+    /// same decapsulation work a legitimate one would, rather than getting
+    /// a cheaper rejection. See [`crate::crypto::VerifyOutcome`] for the
+    /// threat model this is (and isn't) meant to address.
+    pub fn decrypt_direct(&self, data: &SingleEncryption, signer: impl AsRef<sig::PublicKey>) -> Result<(String, Vec<u8>)> {
+        self.open_direct_with_aad(data, signer, &[])
+    }
+
+    /// The shared implementation behind [`Self::decrypt_direct`] and
+    /// [`Self::decrypt_direct_to`]: verify the signature over `data`
+    /// together with `aad`, decapsulate against every current or retired
+    /// KEM key, and require both to succeed before returning the
+    /// plaintext. [`crate::crypto::Conversation`] reaches for this
+    /// directly to bind a conversation id instead of a recipient
+    /// `profile_id`.
+    pub(crate) fn open_direct_with_aad(&self, data: &SingleEncryption, signer: impl AsRef<sig::PublicKey>, aad: &[u8]) -> Result<(String, Vec<u8>)> {
+        validate_nonce_len(&data.nonce, self.nonce_len())?;
+
+        let mut signed_bytes = data.kem_ciphertext.clone();
+        signed_bytes.extend_from_slice(&data.payload);
+        signed_bytes.extend_from_slice(data.content_type.as_bytes());
+        signed_bytes.push(data.kind().signed_byte());
+        signed_bytes.extend_from_slice(aad);
+        let outcome = verify_ct(domain::DIRECT_MESSAGE, &signed_bytes, &data.signature, signer.as_ref());
+
+        let plaintext = std::iter::once(&self.kem_secret)
+            .chain(self.retired_kem_secrets.iter())
+            .find_map(|secret| self.try_open_with(secret, data, aad));
+
+        outcome.into_result()?;
+        plaintext.ok_or_else(|| {
+            crate::audit::emit(crate::audit::SecurityEvent::DecryptionFailed);
+            Error::NoMatchingKey
+        })
+        .map(|plaintext| (data.content_type.clone(), plaintext))
+    }
+
+    /// Open an envelope produced by [`Self::encrypt_group_key_to`] and
+    /// parse its plaintext back into a [`GroupKey`]. Delegates to
+    /// [`Self::decrypt_direct`] for the signature check and decapsulation;
+    /// [`SingleEncryption::kind`] is what a dispatcher checks beforehand to
+    /// decide this is the method to call in the first place, so it isn't
+    /// re-checked here.
+    pub fn decrypt_group_key_from(&self, data: &SingleEncryption, signer: impl AsRef<sig::PublicKey>) -> Result<GroupKey> {
+        let (_, plaintext) = self.decrypt_direct(data, signer)?;
+        GroupKey::from_bytes(&plaintext)
+    }
+
+    /// As [`Self::decrypt_direct`], but for an envelope sealed with
+    /// [`Self::encrypt_direct_to`]/[`Self::encrypt_direct_to_typed`]:
+    /// `expected_recipient` is checked both as additional authenticated
+    /// data on the AEAD payload and as part of the signed bytes, so an
+    /// envelope addressed to a different profile_id (even one sharing this
+    /// context's encryption key, e.g. across a key rotation) is rejected
+    /// rather than opened.
+    pub fn decrypt_direct_to(&self, data: &SingleEncryption, signer: impl AsRef<sig::PublicKey>, expected_recipient: &ProfileId) -> Result<(String, Vec<u8>)> {
+        self.open_direct_with_aad(data, signer, expected_recipient.as_str().as_bytes())
+    }
+
+    /// As [`Self::decrypt_direct`], but consulting `cache` for a
+    /// `SharedSecret` already decapsulated from this exact
+    /// `kem_ciphertext` before paying for a fresh decapsulation, and
+    /// recording the result for next time on a miss. Worth reaching for
+    /// in a busy session where the same envelope can arrive more than
+    /// once (e.g. a transport-level retransmission) — decapsulation is the
+    /// expensive part of opening an envelope, so memoizing it trades a
+    /// bounded amount of memory (see [`DecapsulationCache::new`]) for
+    /// skipping that cost on a repeat. Not the default
+    /// [`Self::decrypt_direct`] behavior: a caller opts in by constructing
+    /// a [`DecapsulationCache`] and threading it through explicitly.
+    pub fn decrypt_direct_cached(&self, data: &SingleEncryption, signer: impl AsRef<sig::PublicKey>, cache: &DecapsulationCache) -> Result<(String, Vec<u8>)> {
+        validate_nonce_len(&data.nonce, self.nonce_len())?;
+
+        let mut signed_bytes = data.kem_ciphertext.clone();
+        signed_bytes.extend_from_slice(&data.payload);
+        signed_bytes.extend_from_slice(data.content_type.as_bytes());
+        signed_bytes.push(data.kind().signed_byte());
+        let outcome = verify_ct(domain::DIRECT_MESSAGE, &signed_bytes, &data.signature, signer.as_ref());
+
+        let cached = cache.get(&data.kem_ciphertext).and_then(|shared| open_payload_with(&shared, data, &[]));
+        let plaintext = match cached {
+            Some(plaintext) => Some(plaintext),
+            None => std::iter::once(&self.kem_secret).chain(self.retired_kem_secrets.iter()).find_map(|secret| {
+                let (shared, plaintext) = self.try_open_with_secret(secret, data, &[])?;
+                cache.insert(&data.kem_ciphertext, shared);
+                Some(plaintext)
+            }),
+        };
+
+        outcome.into_result()?;
+        plaintext.ok_or_else(|| {
+            crate::audit::emit(crate::audit::SecurityEvent::DecryptionFailed);
+            Error::NoMatchingKey
+        })
+        .map(|plaintext| (data.content_type.clone(), plaintext))
+    }
+
+    /// Attempt to open `data`'s payload under a single KEM secret,
+    /// returning `None` (rather than propagating an error) on any failure
+    /// so [`Self::decrypt_direct`] can keep trying the rest of the ring.
+    fn try_open_with(&self, secret: &kem::SecretKey, data: &SingleEncryption, aad: &[u8]) -> Option<Vec<u8>> {
+        self.try_open_with_secret(secret, data, aad).map(|(_, plaintext)| plaintext)
+    }
+
+    /// As [`Self::try_open_with`], but also returning the `SharedSecret`
+    /// the decapsulation produced, for [`Self::decrypt_direct_cached`] to
+    /// memoize.
+    fn try_open_with_secret(&self, secret: &kem::SecretKey, data: &SingleEncryption, aad: &[u8]) -> Option<(SharedSecret, Vec<u8>)> {
+        let ciphertext = self.kem_algo.ciphertext_from_bytes(&data.kem_ciphertext)?;
+        let raw_secret = self.kem_algo.decapsulate(secret, ciphertext).ok()?;
+        let shared = SharedSecret::from_slice(raw_secret.into_vec().as_slice()).ok()?;
+        let plaintext = open_payload_with(&shared, data, aad)?;
+        Some((shared, plaintext))
+    }
+
+    /// Encrypt `data` once under `key` and pair the resulting envelope with
+    /// each target's public key, so callers can route the same ciphertext
+    /// to every group member without re-encrypting.
+    /// Seal `data` under `key` for every public key in `targets`. Errors
+    /// with [`UserError::NoRecipients`] if `targets` is empty, since an
+    /// envelope nobody is addressed to is almost always a caller bug, or
+    /// with [`UserError::TooManyRecipients`] if `targets` exceeds
+    /// [`crate::constants::MAX_GROUP_RECIPIENTS`] — each recipient costs a
+    /// KEM encapsulation and a signature, so an unbounded list drawn from
+    /// untrusted input is a DoS vector. A caller that trusts its own
+    /// recipient list can use [`Self::encrypt_group_with_max`] to raise
+    /// the limit, or [`Self::encrypt_group_body`] to skip the check
+    /// entirely and fan out later.
+    pub fn encrypt_group(
+        &self,
+        key: &GroupKey,
+        targets: impl IntoIterator<Item = kem::PublicKey>,
+        data: impl AsRef<[u8]>,
+    ) -> Result<Vec<(kem::PublicKey, GroupEncryption)>> {
+        self.encrypt_group_with_max(key, targets, data, crate::constants::MAX_GROUP_RECIPIENTS)
+    }
+
+    /// As [`Self::encrypt_group`], but with the recipient-count limit
+    /// overridable instead of fixed at
+    /// [`crate::constants::MAX_GROUP_RECIPIENTS`] — for a caller that
+    /// trusts its own recipient list (e.g. a locally assembled roster)
+    /// and legitimately wants a larger group.
+    pub fn encrypt_group_with_max(
+        &self,
+        key: &GroupKey,
+        targets: impl IntoIterator<Item = kem::PublicKey>,
+        data: impl AsRef<[u8]>,
+        max: usize,
+    ) -> Result<Vec<(kem::PublicKey, GroupEncryption)>> {
+        let targets: Vec<_> = targets.into_iter().collect();
+        if targets.is_empty() {
+            return Err(UserError::NoRecipients.into());
+        }
+        if targets.len() > max {
+            return Err(UserError::TooManyRecipients { got: targets.len(), max }.into());
+        }
+
+        let envelope = self.encrypt_group_body(key, data)?;
+        Ok(targets.into_iter().map(|pk| (pk, envelope.clone())).collect())
+    }
+
+    /// As [`Self::encrypt_group`], but keyed by each recipient's
+    /// [`ProfileId`] instead of their raw KEM key, since that's what
+    /// callers actually route on — saves re-matching KEM keys back to
+    /// contacts after the fact. Subject to the same
+    /// [`crate::constants::MAX_GROUP_RECIPIENTS`] limit as
+    /// `encrypt_group`.
+    pub fn encrypt_group_for(
+        &self,
+        key: &GroupKey,
+        recipients: &[&PublicProfileData],
+        data: impl AsRef<[u8]>,
+    ) -> Result<Vec<(ProfileId, GroupEncryption)>> {
+        let targets = recipients.iter().map(|r| r.encryption_key().clone());
+        let sealed = self.encrypt_group(key, targets, data)?;
+        Ok(recipients.iter().map(|r| r.profile_id().clone()).zip(sealed.into_iter().map(|(_, envelope)| envelope)).collect())
+    }
+
+    /// Seal `data` under `key`, without addressing it to any recipient.
+    /// The lower-level primitive behind [`Self::encrypt_group`]: useful for
+    /// precomputing the shared ciphertext body once and fanning it out to a
+    /// recipient list assembled later, since (unlike `encrypt_group`) it
+    /// has no target set to check and so accepts zero recipients.
+    pub fn encrypt_group_body(&self, key: &GroupKey, data: impl AsRef<[u8]>) -> Result<GroupEncryption> {
+        let nonce = random_nonce();
+        let payload = cipher_for(key.secret())?
+            .encrypt(Nonce::from_slice(&nonce), data.as_ref())
+            .map_err(|_| anyhow::anyhow!("aead encryption failed"))?;
+
+        let mut to_sign = key.id().as_bytes().to_vec();
+        to_sign.extend_from_slice(&payload);
+        let signature = self.sign(domain::GROUP_MESSAGE, &to_sign)?;
+
+        Ok(GroupEncryption { key_id: key.id(), nonce: nonce.to_vec(), payload, signature, prev_hash: None })
+    }
+
+    /// As [`Self::encrypt_group_body`], but linking onto a previous message
+    /// in the same ordered conversation: `prev_hash` — typically the
+    /// previous message's [`GroupEncryption::chain_hash`] — becomes part of
+    /// the signed payload, so tampering with the link is caught by
+    /// signature verification and [`verify_chain`] can detect the sequence
+    /// being reordered, truncated, or spliced. Pass `None` for the first
+    /// message in a chain.
+    pub fn encrypt_group_body_chained(&self, key: &GroupKey, data: impl AsRef<[u8]>, prev_hash: Option<[u8; 32]>) -> Result<GroupEncryption> {
+        let nonce = random_nonce();
+        let payload = cipher_for(key.secret())?
+            .encrypt(Nonce::from_slice(&nonce), data.as_ref())
+            .map_err(|_| anyhow::anyhow!("aead encryption failed"))?;
+
+        let mut to_sign = key.id().as_bytes().to_vec();
+        to_sign.extend_from_slice(&payload);
+        if let Some(prev) = &prev_hash {
+            to_sign.extend_from_slice(prev);
+        }
+        let signature = self.sign(domain::GROUP_MESSAGE, &to_sign)?;
+
+        Ok(GroupEncryption { key_id: key.id(), nonce: nonce.to_vec(), payload, signature, prev_hash })
+    }
+
+    /// As [`Self::encrypt_group`], but for a plaintext body too large to
+    /// hold in memory twice over (once plain, once encrypted). Reads
+    /// `reader` and writes the sealed body to `writer` in
+    /// [`crate::constants::GROUP_STREAM_CHUNK_LEN`]-sized chunks instead of
+    /// buffering it whole, so peak memory stays bounded no matter how
+    /// large the stream is. See [`Self::encrypt_group_body_stream`] for
+    /// the chunked AEAD scheme itself.
+    ///
+    /// Group members already share `key` from a prior `Message::GroupInvite`,
+    /// so — same as `encrypt_group` — sealing the body once is the whole
+    /// cost; every target in `targets` gets back the same manifest rather
+    /// than something computed per recipient, so unlike `encrypt_direct`
+    /// there's no per-recipient KEM step here to parallelize. Subject to
+    /// the same [`crate::constants::MAX_GROUP_RECIPIENTS`] limit as
+    /// `encrypt_group`.
+    pub fn encrypt_group_stream(
+        &self,
+        key: &GroupKey,
+        targets: impl IntoIterator<Item = kem::PublicKey>,
+        reader: impl std::io::Read,
+        writer: impl std::io::Write,
+    ) -> Result<Vec<(kem::PublicKey, GroupStreamManifest)>> {
+        let targets: Vec<_> = targets.into_iter().collect();
+        if targets.is_empty() {
+            return Err(UserError::NoRecipients.into());
+        }
+        if targets.len() > crate::constants::MAX_GROUP_RECIPIENTS {
+            return Err(UserError::TooManyRecipients { got: targets.len(), max: crate::constants::MAX_GROUP_RECIPIENTS }.into());
+        }
+
+        let manifest = self.encrypt_group_body_stream(key, reader, writer)?;
+        Ok(targets.into_iter().map(|pk| (pk, manifest.clone())).collect())
+    }
+
+    /// The lower-level primitive behind [`Self::encrypt_group_stream`]:
+    /// seals `reader` chunk by chunk under `key`, writing each chunk's
+    /// ciphertext (length-prefixed with a big-endian `u32`) to `writer` as
+    /// soon as it's produced rather than after the whole body is read.
+    /// Each chunk gets its own nonce (see
+    /// [`GroupStreamManifest::nonce_prefix`]) and authenticates itself
+    /// independently; the chunk count is signed too, so
+    /// [`Self::decrypt_group_stream`] can also catch a stream truncated
+    /// after fewer chunks than were actually sent.
+    pub fn encrypt_group_body_stream(&self, key: &GroupKey, mut reader: impl std::io::Read, mut writer: impl std::io::Write) -> Result<GroupStreamManifest> {
+        let cipher = GroupCipher::new(key)?;
+        let mut nonce_prefix = [0u8; 4];
+        rand::thread_rng().fill_bytes(&mut nonce_prefix);
+
+        let mut buf = vec![0u8; crate::constants::GROUP_STREAM_CHUNK_LEN];
+        let mut chunk_count: u64 = 0;
+        loop {
+            let read = read_full_or_eof(&mut reader, &mut buf)?;
+            if read == 0 {
+                break;
+            }
+            let ciphertext = cipher.encrypt(stream_nonce(nonce_prefix, chunk_count), &buf[..read])?;
+            writer.write_all(&(ciphertext.len() as u32).to_be_bytes()).map_err(anyhow::Error::from)?;
+            writer.write_all(&ciphertext).map_err(anyhow::Error::from)?;
+            chunk_count += 1;
+            if read < buf.len() {
+                break;
+            }
+        }
+
+        let mut to_sign = key.id().as_bytes().to_vec();
+        to_sign.extend_from_slice(&nonce_prefix);
+        to_sign.extend_from_slice(&chunk_count.to_be_bytes());
+        let signature = self.sign(domain::GROUP_MESSAGE, &to_sign)?;
+
+        Ok(GroupStreamManifest { key_id: key.id(), nonce_prefix, chunk_count, signature })
+    }
+
+    /// Reverse [`Self::encrypt_group_stream`]: verifies `manifest`'s
+    /// signature, then reads exactly `manifest.chunk_count` length-prefixed
+    /// chunks from `reader`, decrypting each straight into `writer` as it
+    /// arrives. The signature is checked before any ciphertext is touched
+    /// — an unrecognized signer means nothing that follows can be trusted
+    /// — and a `reader` that runs out before all chunks arrive fails with
+    /// the underlying I/O error rather than silently returning a
+    /// truncated body.
+    ///
+    /// Each chunk authenticates itself independently (its own AEAD tag),
+    /// so a chunk that decrypts here was genuinely produced by whoever
+    /// holds `key`. But because plaintext is written to `writer` as each
+    /// chunk arrives, a `reader` that's truncated or tampered with
+    /// partway through means the caller has already seen everything
+    /// decrypted before the failure — the stream as a whole was never
+    /// confirmed complete before some of it was released. For a caller
+    /// that can't tolerate that, see [`Self::decrypt_group_stream_verified`].
+    pub fn decrypt_group_stream(
+        &self,
+        key: &GroupKey,
+        manifest: &GroupStreamManifest,
+        mut reader: impl std::io::Read,
+        mut writer: impl std::io::Write,
+        signer: impl AsRef<sig::PublicKey>,
+    ) -> Result<()> {
+        let mut to_verify = manifest.key_id.as_bytes().to_vec();
+        to_verify.extend_from_slice(&manifest.nonce_prefix);
+        to_verify.extend_from_slice(&manifest.chunk_count.to_be_bytes());
+        self.verify(domain::GROUP_MESSAGE, &to_verify, &manifest.signature, signer.as_ref())?;
+
+        let cipher = GroupCipher::new(key)?;
+        for index in 0..manifest.chunk_count {
+            let mut len_buf = [0u8; 4];
+            reader.read_exact(&mut len_buf).map_err(anyhow::Error::from)?;
+            let len = read_group_stream_chunk_len(&len_buf)?;
+            let mut ciphertext = vec![0u8; len];
+            reader.read_exact(&mut ciphertext).map_err(anyhow::Error::from)?;
+
+            let plaintext = cipher.decrypt(stream_nonce(manifest.nonce_prefix, index), &ciphertext)?;
+            writer.write_all(&plaintext).map_err(anyhow::Error::from)?;
+        }
+
+        Ok(())
+    }
+
+    /// As [`Self::decrypt_group_stream`], but buffering every chunk's
+    /// plaintext in a temp file until all `manifest.chunk_count` of them have
+    /// decrypted successfully, then writing the whole body to `writer` in
+    /// one shot instead of chunk by chunk. Nothing reaches `writer` unless
+    /// the entire stream — every chunk present, every chunk's AEAD tag
+    /// valid — checks out; a corrupted or truncated final chunk fails the
+    /// whole call and `writer` sees no plaintext at all, not even the
+    /// chunks that came before it.
+    ///
+    /// Buffered to a temp file rather than an in-memory `Vec`, so
+    /// holding the whole body doesn't cost RAM proportional to the
+    /// stream's size — relevant since, unlike the per-chunk length prefix
+    /// (capped against [`crate::constants::GROUP_STREAM_CHUNK_LEN`] the
+    /// same way [`Self::decrypt_group_stream`] caps it), `manifest.chunk_count`
+    /// is only bounded by whatever the sender committed to in the signed
+    /// manifest. Pick this mode when the caller can't tolerate ever
+    /// seeing plaintext from a stream that turns out to be incomplete;
+    /// pick [`Self::decrypt_group_stream`] when per-chunk authentication
+    /// is enough and a temp file isn't wanted.
+    pub fn decrypt_group_stream_verified(
+        &self,
+        key: &GroupKey,
+        manifest: &GroupStreamManifest,
+        mut reader: impl std::io::Read,
+        mut writer: impl std::io::Write,
+        signer: impl AsRef<sig::PublicKey>,
+    ) -> Result<()> {
+        use std::io::Write as _;
+
+        let mut to_verify = manifest.key_id.as_bytes().to_vec();
+        to_verify.extend_from_slice(&manifest.nonce_prefix);
+        to_verify.extend_from_slice(&manifest.chunk_count.to_be_bytes());
+        self.verify(domain::GROUP_MESSAGE, &to_verify, &manifest.signature, signer.as_ref())?;
+
+        let cipher = GroupCipher::new(key)?;
+        let mut staging = GroupStreamStaging::new()?;
+        for index in 0..manifest.chunk_count {
+            let mut len_buf = [0u8; 4];
+            reader.read_exact(&mut len_buf).map_err(anyhow::Error::from)?;
+            let len = read_group_stream_chunk_len(&len_buf)?;
+            let mut ciphertext = vec![0u8; len];
+            reader.read_exact(&mut ciphertext).map_err(anyhow::Error::from)?;
+
+            let plaintext = cipher.decrypt(stream_nonce(manifest.nonce_prefix, index), &ciphertext)?;
+            staging.write_all(&plaintext).map_err(anyhow::Error::from)?;
+        }
+
+        staging.copy_to(&mut writer)
+    }
+
+    /// Decrypt an envelope produced by [`Self::encrypt_group`] under `key`.
+    ///
+    /// As with [`Self::decrypt_direct`], the AEAD decryption attempt runs
+    /// before the signature outcome is checked, so it costs the same
+    /// whether or not the signature turns out to be valid.
+    pub fn decrypt_group(&self, key: &GroupKey, data: &GroupEncryption, signer: impl AsRef<sig::PublicKey>) -> Result<Vec<u8>> {
+        validate_nonce_len(&data.nonce, self.nonce_len())?;
+
+        let mut signed_bytes = data.key_id.as_bytes().to_vec();
+        signed_bytes.extend_from_slice(&data.payload);
+        if let Some(prev) = &data.prev_hash {
+            signed_bytes.extend_from_slice(prev);
+        }
+        let outcome = verify_ct(domain::GROUP_MESSAGE, &signed_bytes, &data.signature, signer.as_ref());
+
+        let plaintext = cipher_for(key.secret())?
+            .decrypt(Nonce::from_slice(&data.nonce), data.payload.as_slice())
+            .map_err(|_| anyhow::anyhow!("aead decryption failed"));
+
+        outcome.into_result()?;
+        plaintext.map_err(Into::into)
+    }
+
+    /// As [`Self::decrypt_group`], but consulting `tracker` first and
+    /// failing closed with [`Error::NonceReuse`] if `data`'s
+    /// `(key_id, nonce)` pair has already been seen — a defense against a
+    /// sender that (accidentally or otherwise) reuses a nonce under a
+    /// group key, which breaks GCM's confidentiality guarantee for both
+    /// messages. As [`crate::crypto::NonceTracker`] documents, this only
+    /// detects reuse after the fact; it can't undo whatever the first
+    /// occurrence already exposed. The observation is recorded before the
+    /// AEAD decryption attempt, so a reused nonce is rejected even if the
+    /// ciphertext or signature also happens to be invalid.
+    pub fn decrypt_group_checked(
+        &self,
+        key: &GroupKey,
+        data: &GroupEncryption,
+        signer: impl AsRef<sig::PublicKey>,
+        tracker: &crate::crypto::NonceTracker,
+    ) -> Result<Vec<u8>> {
+        validate_nonce_len(&data.nonce, self.nonce_len())?;
+
+        if !tracker.observe(data.key_id, data.nonce.clone()) {
+            crate::audit::emit(crate::audit::SecurityEvent::ReplayRejected { key_id: data.key_id });
+            return Err(Error::NonceReuse { key_id: data.key_id });
+        }
+        self.decrypt_group(key, data, signer)
+    }
+
+    /// As [`Self::decrypt_group`], but for a caller holding several group
+    /// keys who doesn't know in advance which one `data` was sealed under.
+    /// Picks the candidate in `keys` whose id matches `data.key_id` and
+    /// decrypts with it, or returns
+    /// [`crate::error::UserError::UnknownGroupKey`] if none match.
+    pub fn decrypt_group_any(&self, keys: &[GroupKey], data: &GroupEncryption, signer: impl AsRef<sig::PublicKey>) -> Result<Vec<u8>> {
+        let key = keys
+            .iter()
+            .find(|key| key.matches_id(data.key_id))
+            .ok_or(crate::error::UserError::UnknownGroupKey { key_id: data.key_id })?;
+        self.decrypt_group(key, data, signer)
+    }
+
+    /// As [`Self::decrypt_group_any`], but for callers in a high-security
+    /// mode where even a timing signal on top of what the wire format
+    /// already reveals is unacceptable. [`Self::decrypt_group_any`]'s
+    /// `.find()` stops as soon as it hits the matching `key_id`, so its
+    /// running time depends on the matching key's position within `keys`
+    /// — observable to anyone who can measure the call's latency and
+    /// already knows (or can narrow down) the caller's key ordering, e.g.
+    /// most-recently-used-first. This variant always runs the full
+    /// `decrypt_group` attempt (signature check and AEAD decryption
+    /// included, not just the `key_id` comparison) against every entry in
+    /// `keys`, so the work done — and therefore the time taken — is the
+    /// same regardless of which position, if any, actually matches.
+    ///
+    /// This costs `keys.len()` full decrypt attempts every call instead of
+    /// one, so it's opt-in: reach for [`Self::decrypt_group_any`] unless
+    /// an attacker measuring this call's timing is genuinely in your
+    /// threat model.
+    pub fn decrypt_group_any_ct(&self, keys: &[GroupKey], data: &GroupEncryption, signer: impl AsRef<sig::PublicKey>) -> Result<Vec<u8>> {
+        let signer = signer.as_ref();
+        let mut matched = None;
+
+        for key in keys {
+            let result = self.decrypt_group(key, data, signer.clone());
+            if key.matches_id(data.key_id) {
+                matched = Some(result);
+            }
+        }
+
+        matched.unwrap_or_else(|| Err(crate::error::UserError::UnknownGroupKey { key_id: data.key_id }.into()))
+    }
+}
+
+/// Check that `messages`, in the given order, form an unbroken hash chain:
+/// each message's `prev_hash` must equal the [`GroupEncryption::chain_hash`]
+/// of the one before it. Catches a message being removed, reordered, or
+/// spliced in — anywhere the sequence no longer links up the way it was
+/// signed. Doesn't itself verify signatures or decrypt anything; pair with
+/// [`EncryptionContext::decrypt_group`] (which already rejects a tampered
+/// `prev_hash` on any single message) to also authenticate each link's
+/// content.
+///
+/// A message with `prev_hash: None` is only accepted at index `0` — an
+/// unchained message never legitimately continues a chain.
+pub fn verify_chain(messages: &[GroupEncryption]) -> Result<()> {
+    for (index, message) in messages.iter().enumerate() {
+        let expected = if index == 0 { None } else { Some(messages[index - 1].chain_hash()) };
+        if message.prev_hash != expected {
+            return Err(Error::ChainBroken { index });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_sizes_matches_a_generated_contexts_actual_key_lengths() {
+        let context = EncryptionContext::generate().unwrap();
+        let sizes = key_sizes(KEM_ALG, SIG_ALG).unwrap();
+
+        assert_eq!(sizes.kem_public_key, context.encryption_public_key().as_ref().len());
+        assert_eq!(sizes.sig_public_key, context.signing_public_key().as_ref().len());
+    }
+
+    #[test]
+    fn encryption_key_from_bytes_rejects_a_truncated_key() {
+        let context = EncryptionContext::generate().unwrap();
+        let mut bytes = context.encryption_public_key().as_ref().to_vec();
+        bytes.truncate(bytes.len() - 1);
+
+        let err = encryption_key_from_bytes(&bytes).unwrap_err();
+        assert!(matches!(err, Error::User(UserError::BadLength { .. })));
+    }
+
+    #[test]
+    fn signing_key_from_bytes_rejects_a_truncated_key() {
+        let context = EncryptionContext::generate().unwrap();
+        let mut bytes = context.signing_public_key().as_ref().to_vec();
+        bytes.truncate(bytes.len() - 1);
+
+        let err = signing_key_from_bytes(&bytes).unwrap_err();
+        assert!(matches!(err, Error::User(UserError::BadLength { .. })));
+    }
+
+    #[test]
+    fn encapsulate_and_decapsulate_agree_on_the_same_secret() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+
+        let (ciphertext, sender_secret) = sender.encapsulate_to(receiver.encryption_public_key()).unwrap();
+        let receiver_secret = receiver.decapsulate(&ciphertext).unwrap();
+
+        assert_eq!(sender_secret.as_bytes(), receiver_secret.as_bytes());
+    }
+
+    #[test]
+    fn direct_round_trip() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+
+        let sealed = sender
+            .encrypt_direct(receiver.encryption_public_key().clone(), b"hello".to_vec())
+            .unwrap();
+        let (content_type, opened) = receiver.decrypt_direct(&sealed, sender.signing_public_key().clone()).unwrap();
+        assert_eq!(opened, b"hello");
+        assert_eq!(content_type, crate::constants::DEFAULT_CONTENT_TYPE);
+    }
+
+    #[test]
+    fn each_direct_producer_marks_its_envelope_kind() {
+        use crate::profile::ProfileBuilder;
+
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let receiver_profile = ProfileBuilder::new().name("bob").build().unwrap();
+        let group_key = GroupKey::generate();
+
+        let direct = sender.encrypt_direct(receiver.encryption_public_key().clone(), b"hi".to_vec()).unwrap();
+        assert_eq!(direct.kind(), EnvelopeKind::Direct);
+
+        let typed = sender.encrypt_direct_typed(receiver.encryption_public_key().clone(), b"hi".to_vec(), "text/plain").unwrap();
+        assert_eq!(typed.kind(), EnvelopeKind::Direct);
+
+        let to_recipient = sender.encrypt_direct_to(&receiver_profile.public(), b"hi".to_vec()).unwrap();
+        assert_eq!(to_recipient.kind(), EnvelopeKind::Direct);
+
+        let group_wrapped = sender.encrypt_group_key_to(receiver.encryption_public_key().clone(), &group_key).unwrap();
+        assert_eq!(group_wrapped.kind(), EnvelopeKind::GroupWrapped);
+    }
+
+    #[test]
+    fn group_wrapped_envelopes_decrypt_back_to_the_original_group_key() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let group_key = GroupKey::generate();
+
+        let sealed = sender.encrypt_group_key_to(receiver.encryption_public_key().clone(), &group_key).unwrap();
+        let opened = receiver.decrypt_group_key_from(&sealed, sender.signing_public_key().clone()).unwrap();
+        assert_eq!(opened.id(), group_key.id());
+        assert_eq!(opened.secret().as_bytes(), group_key.secret().as_bytes());
+    }
+
+    #[test]
+    fn encrypt_direct_accepts_a_borrowed_slice_without_allocating_a_vec() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+
+        let plaintext: &[u8] = b"borrowed";
+        let sealed = sender.encrypt_direct(receiver.encryption_public_key().clone(), plaintext).unwrap();
+        let (_, opened) = receiver.decrypt_direct(&sealed, sender.signing_public_key().clone()).unwrap();
+        assert_eq!(opened, plaintext);
+    }
+
+    #[test]
+    fn typed_round_trip_preserves_content_type() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+
+        for content_type in ["text/plain", "application/json", "image/png"] {
+            let sealed = sender
+                .encrypt_direct_typed(receiver.encryption_public_key().clone(), b"data".to_vec(), content_type)
+                .unwrap();
+            let (got_type, opened) = receiver.decrypt_direct(&sealed, sender.signing_public_key().clone()).unwrap();
+            assert_eq!(got_type, content_type);
+            assert_eq!(opened, b"data");
+        }
+    }
+
+    #[test]
+    fn encrypt_many_preserves_order_across_distinct_recipients() {
+        let sender = EncryptionContext::generate().unwrap();
+        let alice = EncryptionContext::generate().unwrap();
+        let bob = EncryptionContext::generate().unwrap();
+        let carol = EncryptionContext::generate().unwrap();
+
+        let items = vec![
+            (alice.encryption_public_key().clone(), b"for alice".to_vec()),
+            (bob.encryption_public_key().clone(), b"for bob".to_vec()),
+            (carol.encryption_public_key().clone(), b"for carol".to_vec()),
+        ];
+
+        let results = sender.encrypt_many(items);
+        assert_eq!(results.len(), 3);
+        let sealed: Vec<_> = results.into_iter().map(|r| r.unwrap()).collect();
+
+        let (_, opened) = alice.decrypt_direct(&sealed[0], sender.signing_public_key()).unwrap();
+        assert_eq!(opened, b"for alice");
+        let (_, opened) = bob.decrypt_direct(&sealed[1], sender.signing_public_key()).unwrap();
+        assert_eq!(opened, b"for bob");
+        let (_, opened) = carol.decrypt_direct(&sealed[2], sender.signing_public_key()).unwrap();
+        assert_eq!(opened, b"for carol");
+    }
+
+    #[test]
+    fn encrypt_many_batch_still_succeeds_after_filtering_out_unparseable_recipient_keys() {
+        // A `kem::PublicKey` of the wrong length is rejected by
+        // `encryption_key_from_bytes` before it ever exists, so it can't
+        // appear inside `encrypt_many`'s `items` — the type itself is the
+        // guarantee. What a caller relaying untrusted recipient key bytes
+        // (e.g. profiles pulled off the wire) actually deals with is this:
+        // parse each one first, keep only what parses, and hand the
+        // survivors to `encrypt_many`. A garbled entry in the middle
+        // doesn't affect its neighbors either way.
+        let sender = EncryptionContext::generate().unwrap();
+        let alice = EncryptionContext::generate().unwrap();
+        let bob = EncryptionContext::generate().unwrap();
+
+        let candidate_keys: Vec<Result<kem::PublicKey>> = vec![
+            Ok(alice.encryption_public_key().clone()),
+            encryption_key_from_bytes(b"too short to be a real key"),
+            Ok(bob.encryption_public_key().clone()),
+        ];
+        assert!(candidate_keys[1].is_err());
+
+        let items: Vec<_> = candidate_keys.into_iter().filter_map(Result::ok).zip([b"for alice".to_vec(), b"for bob".to_vec()]).collect();
+
+        let results = sender.encrypt_many(items);
+        assert_eq!(results.len(), 2);
+        let (_, opened) = alice.decrypt_direct(results[0].as_ref().unwrap(), sender.signing_public_key()).unwrap();
+        assert_eq!(opened, b"for alice");
+        let (_, opened) = bob.decrypt_direct(results[1].as_ref().unwrap(), sender.signing_public_key()).unwrap();
+        assert_eq!(opened, b"for bob");
+    }
+
+    #[test]
+    fn rotating_signing_key_preserves_decryption_but_rotates_signatures() {
+        let mut sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let old_signing_key = sender.signing_public_key().clone();
+
+        let sealed_before = sender.encrypt_direct(receiver.encryption_public_key().clone(), b"old".to_vec()).unwrap();
+
+        let (new_signing_key, binding) = sender.rotate_signing_key().unwrap();
+        assert_ne!(new_signing_key.as_ref(), old_signing_key.as_ref());
+        verify_detached(domain::KEY_ROTATION, new_signing_key.as_ref(), &binding, &old_signing_key).unwrap();
+
+        // A ciphertext sealed before rotation was signed with the old key,
+        // so it must still verify and decrypt: the KEM keypair didn't move.
+        let (_, opened_old) = receiver.decrypt_direct(&sealed_before, old_signing_key.clone()).unwrap();
+        assert_eq!(opened_old, b"old");
+
+        let sealed_after = sender.encrypt_direct(receiver.encryption_public_key().clone(), b"new".to_vec()).unwrap();
+        let (_, opened_new) = receiver.decrypt_direct(&sealed_after, new_signing_key).unwrap();
+        assert_eq!(opened_new, b"new");
+        assert!(receiver.decrypt_direct(&sealed_after, old_signing_key).is_err());
+    }
+
+    #[test]
+    fn rotating_encryption_key_keeps_signing_identity() {
+        let sender = EncryptionContext::generate().unwrap();
+        let mut receiver = EncryptionContext::generate().unwrap();
+        let signing_key = receiver.signing_public_key().clone();
+        let old_encryption_key = receiver.encryption_public_key().clone();
+
+        // Sealed to the pre-rotation key, and decryptable while that
+        // secret is still live.
+        let sealed_before = sender.encrypt_direct(old_encryption_key.clone(), b"old".to_vec()).unwrap();
+        let (_, opened_before) = receiver.decrypt_direct(&sealed_before, sender.signing_public_key().clone()).unwrap();
+        assert_eq!(opened_before, b"old");
+
+        let (new_encryption_key, announcement) = receiver.rotate_encryption_key().unwrap();
+        assert_ne!(new_encryption_key.as_ref(), old_encryption_key.as_ref());
+        verify_detached(domain::KEY_ROTATION, new_encryption_key.as_ref(), &announcement, &signing_key).unwrap();
+        assert_eq!(receiver.signing_public_key().as_ref(), signing_key.as_ref());
+
+        let sealed_after = sender.encrypt_direct(new_encryption_key, b"new".to_vec()).unwrap();
+        let (_, opened_after) = receiver.decrypt_direct(&sealed_after, sender.signing_public_key().clone()).unwrap();
+        assert_eq!(opened_after, b"new");
+
+        // The old KEM secret now sits in the retired-key ring, so a
+        // ciphertext sealed to the old key still opens through this context.
+        let (_, reopened_before) = receiver.decrypt_direct(&sealed_before, sender.signing_public_key().clone()).unwrap();
+        assert_eq!(reopened_before, b"old");
+    }
+
+    #[test]
+    fn decrypt_direct_cached_hits_the_cache_on_a_repeated_ciphertext() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let cache = DecapsulationCache::new(8);
+
+        let sealed = sender.encrypt_direct(receiver.encryption_public_key().clone(), b"hello".to_vec()).unwrap();
+
+        assert!(cache.get(&sealed.kem_ciphertext).is_none());
+        let (_, first) = receiver.decrypt_direct_cached(&sealed, sender.signing_public_key().clone(), &cache).unwrap();
+        assert_eq!(first, b"hello");
+        assert!(cache.get(&sealed.kem_ciphertext).is_some());
+
+        // A second decrypt of the same envelope is served from the cache
+        // rather than decapsulating again, and still yields the same
+        // plaintext.
+        let (_, second) = receiver.decrypt_direct_cached(&sealed, sender.signing_public_key().clone(), &cache).unwrap();
+        assert_eq!(second, b"hello");
+    }
+
+    #[test]
+    fn decrypt_direct_accepts_current_and_retired_keys() {
+        let sender = EncryptionContext::generate().unwrap();
+        let mut receiver = EncryptionContext::generate().unwrap();
+        let signing_key = sender.signing_public_key().clone();
+
+        let sealed_to_original = sender.encrypt_direct(receiver.encryption_public_key().clone(), b"before rotation".to_vec()).unwrap();
+
+        let (new_key, _) = receiver.rotate_encryption_key().unwrap();
+        let sealed_to_new = sender.encrypt_direct(new_key, b"after rotation".to_vec()).unwrap();
+
+        let (_, opened_new) = receiver.decrypt_direct(&sealed_to_new, signing_key.clone()).unwrap();
+        assert_eq!(opened_new, b"after rotation");
+
+        // Still decryptable via the retired-key ring, even though the
+        // current key has moved on.
+        let (_, opened_original) = receiver.decrypt_direct(&sealed_to_original, signing_key.clone()).unwrap();
+        assert_eq!(opened_original, b"before rotation");
+    }
+
+    #[test]
+    fn decrypt_direct_evicts_beyond_the_ring_size() {
+        let sender = EncryptionContext::generate().unwrap();
+        let mut receiver = EncryptionContext::generate().unwrap();
+        let signing_key = sender.signing_public_key().clone();
+
+        let sealed_to_original = sender.encrypt_direct(receiver.encryption_public_key().clone(), b"long gone".to_vec()).unwrap();
+
+        for _ in 0..KEM_KEY_RING_SIZE + 1 {
+            receiver.rotate_encryption_key().unwrap();
+        }
+
+        assert!(matches!(receiver.decrypt_direct(&sealed_to_original, signing_key), Err(crate::Error::NoMatchingKey)));
+    }
+
+    #[test]
+    fn verify_sender_checks_signature_without_decapsulating() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+
+        let mut sealed = sender.encrypt_direct(receiver.encryption_public_key().clone(), b"hi".to_vec()).unwrap();
+        receiver.verify_sender(&sealed, sender.signing_public_key().clone()).unwrap();
+
+        sealed.payload[0] ^= 0xff;
+        assert!(receiver.verify_sender(&sealed, sender.signing_public_key().clone()).is_err());
+    }
+
+    #[test]
+    fn signature_from_one_purpose_fails_verification_under_another() {
+        let context = EncryptionContext::generate().unwrap();
+        let bytes = b"identical bytes, different purposes";
+
+        let signature = context.sign_detached(domain::DEVICE_BINDING, bytes).unwrap();
+        verify_detached(domain::DEVICE_BINDING, bytes, &signature, context.signing_public_key()).unwrap();
+
+        assert!(verify_detached(domain::SIGNED_PROFILE, bytes, &signature, context.signing_public_key()).is_err());
+        assert!(verify_detached(domain::KEY_ROTATION, bytes, &signature, context.signing_public_key()).is_err());
+    }
+
+    #[test]
+    fn wrong_signer_yields_signature_invalid_not_a_generic_error() {
+        let signer = EncryptionContext::generate().unwrap();
+        let impostor = EncryptionContext::generate().unwrap();
+        let bytes = b"payload";
+        let signature = signer.sign_detached(domain::DEVICE_BINDING, bytes).unwrap();
+
+        let result = verify_detached(domain::DEVICE_BINDING, bytes, &signature, impostor.signing_public_key());
+        assert!(matches!(result, Err(Error::SignatureInvalid { .. })));
+    }
+
+    #[test]
+    fn malformed_signature_bytes_stay_a_generic_error() {
+        let signer = EncryptionContext::generate().unwrap();
+        let result = verify_detached(domain::DEVICE_BINDING, b"payload", b"not a real signature", signer.signing_public_key());
+        assert!(matches!(result, Err(Error::Other(_))));
+    }
+
+    #[test]
+    fn decrypt_direct_reports_signature_invalid_for_a_wrong_signer() {
+        let sender = EncryptionContext::generate().unwrap();
+        let impostor = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+
+        let sealed = sender.encrypt_direct(receiver.encryption_public_key().clone(), b"hi".to_vec()).unwrap();
+        let result = receiver.decrypt_direct(&sealed, impostor.signing_public_key().clone());
+        assert!(matches!(result, Err(Error::SignatureInvalid { .. })));
+    }
+
+    #[test]
+    fn entropy_check_passes_under_normal_conditions() {
+        assert!(check_entropy().is_ok());
+    }
+
+    #[test]
+    fn generate_checked_succeeds_under_normal_conditions() {
+        assert!(EncryptionContext::generate_checked().is_ok());
+    }
+
+    #[test]
+    fn generate_with_reports_disabled_algorithms() {
+        // This checkout has no `Cargo.toml`, so none of the `mlkem768` /
+        // `mlkem1024` / `falcon512` / `dilithium3` / `hybrid` features are
+        // ever enabled here — every combination should report itself as
+        // not enabled rather than silently falling back to a default.
+        assert!(matches!(
+            EncryptionContext::generate_with(KemChoice::MlKem1024, SigChoice::Dilithium3),
+            Err(Error::AlgorithmNotEnabled("mlkem1024"))
+        ));
+        assert!(matches!(EncryptionContext::generate_with(KemChoice::Hybrid, SigChoice::Falcon512), Err(Error::AlgorithmNotEnabled("hybrid"))));
+    }
+
+    #[test]
+    fn decrypt_direct_attempts_decapsulation_even_with_a_bad_signature() {
+        let sender = EncryptionContext::generate().unwrap();
+        let impostor = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+
+        // Sealed correctly, but verified against the wrong signer: the
+        // signature check fails, yet decapsulation must still have been
+        // attempted (and would have succeeded on its own), so the error is
+        // the signature failure, not `NoMatchingKey`; only a genuinely
+        // undecryptable envelope with a valid signature ends at
+        // `NoMatchingKey`.
+        let sealed = sender.encrypt_direct(receiver.encryption_public_key().clone(), b"hi".to_vec()).unwrap();
+        let wrong_signer_result = receiver.decrypt_direct(&sealed, impostor.signing_public_key().clone());
+        assert!(wrong_signer_result.is_err());
+        assert!(!matches!(wrong_signer_result, Err(crate::Error::NoMatchingKey)));
+
+        let unopenable = sender.encrypt_direct(impostor.encryption_public_key().clone(), b"hi".to_vec()).unwrap();
+        let no_matching_key_result = receiver.decrypt_direct(&unopenable, sender.signing_public_key().clone());
+        assert!(matches!(no_matching_key_result, Err(crate::Error::NoMatchingKey)));
+    }
+
+    #[test]
+    fn padded_round_trip_and_equal_ciphertext_lengths_within_a_bucket() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+
+        let short = sender.encrypt_direct_padded(receiver.encryption_public_key().clone(), b"hi".to_vec(), PaddingScheme::Bucket(64)).unwrap();
+        let long = sender.encrypt_direct_padded(receiver.encryption_public_key().clone(), vec![0u8; 60], PaddingScheme::Bucket(64)).unwrap();
+        assert_eq!(short.payload.len(), long.payload.len());
+
+        let (_, opened_short) = receiver.decrypt_direct_padded(&short, sender.signing_public_key().clone()).unwrap();
+        assert_eq!(opened_short, b"hi");
+        let (_, opened_long) = receiver.decrypt_direct_padded(&long, sender.signing_public_key().clone()).unwrap();
+        assert_eq!(opened_long, vec![0u8; 60]);
+    }
+
+    #[test]
+    fn compressed_round_trip_shrinks_compressible_data() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+
+        let data = vec![b'x'; 10_000];
+        let (sealed, outcome) = sender.encrypt_direct_compressed(receiver.encryption_public_key().clone(), data.clone()).unwrap();
+        assert!(outcome.compressed);
+        assert!(sealed.payload.len() < data.len());
+
+        let (_, opened) = receiver.decrypt_direct_compressed(&sealed, sender.signing_public_key().clone()).unwrap();
+        assert_eq!(opened, data);
+    }
+
+    #[test]
+    fn compressed_round_trip_never_grows_incompressible_data() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+
+        // Not a rigorous incompressibility guarantee, but stable and
+        // varied enough that deflate can't shrink it, exercising the
+        // "keep the original" branch deterministically.
+        let mut state: u64 = 0x9E3779B97F4A7C15;
+        let data: Vec<u8> = (0..4096)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+
+        let (sealed, outcome) = sender.encrypt_direct_compressed(receiver.encryption_public_key().clone(), data.clone()).unwrap();
+        assert!(!outcome.compressed);
+        assert_eq!(outcome.stored_len, data.len() + 1);
+
+        let (_, opened) = receiver.decrypt_direct_compressed(&sealed, sender.signing_public_key().clone()).unwrap();
+        assert_eq!(opened, data);
+    }
+
+    #[test]
+    fn group_round_trip() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+
+        let mut sealed = sender
+            .encrypt_group(&key, vec![receiver.encryption_public_key().clone()], b"hi group".to_vec())
+            .unwrap();
+        let (_pk, envelope) = sealed.remove(0);
+        let opened = receiver.decrypt_group(&key, &envelope, sender.signing_public_key().clone()).unwrap();
+        assert_eq!(opened, b"hi group");
+    }
+
+    #[test]
+    fn decrypt_group_any_picks_the_matching_key_out_of_several() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+
+        let mut sealed = sender
+            .encrypt_group(&key, vec![receiver.encryption_public_key().clone()], b"hi group".to_vec())
+            .unwrap();
+        let (_pk, envelope) = sealed.remove(0);
+
+        let candidates = vec![GroupKey::generate(), key, GroupKey::generate()];
+        let opened = receiver.decrypt_group_any(&candidates, &envelope, sender.signing_public_key().clone()).unwrap();
+        assert_eq!(opened, b"hi group");
+    }
+
+    #[test]
+    fn decrypt_group_any_reports_unknown_group_key_when_none_match() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+
+        let mut sealed = sender
+            .encrypt_group(&key, vec![receiver.encryption_public_key().clone()], b"hi group".to_vec())
+            .unwrap();
+        let (_pk, envelope) = sealed.remove(0);
+
+        let candidates = vec![GroupKey::generate(), GroupKey::generate()];
+        let err = receiver.decrypt_group_any(&candidates, &envelope, sender.signing_public_key().clone()).unwrap_err();
+        assert!(matches!(err, Error::User(crate::error::UserError::UnknownGroupKey { .. })));
+    }
+
+    #[test]
+    fn encrypt_group_rejects_an_empty_target_set() {
+        let sender = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+
+        let err = sender.encrypt_group(&key, Vec::new(), b"hi group".to_vec()).unwrap_err();
+        assert!(matches!(err, Error::User(crate::error::UserError::NoRecipients)));
+    }
+
+    #[test]
+    fn encrypt_group_with_max_accepts_exactly_the_limit_and_rejects_one_more() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+        let target = receiver.encryption_public_key().clone();
+
+        let at_limit = vec![target.clone(); 3];
+        assert!(sender.encrypt_group_with_max(&key, at_limit, b"hi group".to_vec(), 3).is_ok());
+
+        let over_limit = vec![target; 4];
+        let err = sender.encrypt_group_with_max(&key, over_limit, b"hi group".to_vec(), 3).unwrap_err();
+        assert!(matches!(err, Error::User(crate::error::UserError::TooManyRecipients { got: 4, max: 3 })));
+    }
+
+    #[test]
+    fn encrypt_group_for_maps_each_result_to_its_recipient_profile_id_exactly_once() {
+        use crate::profile::ProfileBuilder;
+        use std::collections::HashSet;
+
+        let sender = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+
+        let alice = ProfileBuilder::new().name("alice").build().unwrap();
+        let bob = ProfileBuilder::new().name("bob").build().unwrap();
+        let alice_public = alice.public();
+        let bob_public = bob.public();
+        let recipients = [&alice_public, &bob_public];
+
+        let sealed = sender.encrypt_group_for(&key, &recipients, b"hi group".to_vec()).unwrap();
+        assert_eq!(sealed.len(), 2);
+
+        let profile_ids: HashSet<_> = sealed.iter().map(|(profile_id, _)| profile_id.clone()).collect();
+        assert_eq!(profile_ids.len(), 2);
+        assert!(profile_ids.contains(alice_public.profile_id()));
+        assert!(profile_ids.contains(bob_public.profile_id()));
+    }
+
+    #[test]
+    fn decrypt_group_checked_rejects_a_reused_nonce() {
+        use crate::crypto::NonceTracker;
+
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+
+        let first = sender.encrypt_group_body(&key, b"hello".to_vec()).unwrap();
+        let mut second = sender.encrypt_group_body(&key, b"world".to_vec()).unwrap();
+        // Force an identical nonce, simulating a sender bug rather than
+        // the vanishingly unlikely chance of a real collision.
+        second.nonce = first.nonce.clone();
+
+        let tracker = NonceTracker::new(64);
+        assert!(receiver.decrypt_group_checked(&key, &first, sender.signing_public_key().clone(), &tracker).is_ok());
+
+        let err = receiver.decrypt_group_checked(&key, &second, sender.signing_public_key().clone(), &tracker).unwrap_err();
+        assert!(matches!(err, Error::NonceReuse { key_id } if key_id == key.id()));
+    }
+
+    #[test]
+    fn default_context_reports_expected_algorithms_and_security_level() {
+        let context = EncryptionContext::generate().unwrap();
+        let (kem, sig) = context.algorithms();
+        assert_eq!(kem, "ML-KEM-768");
+        assert_eq!(sig, "Falcon-512");
+        assert_eq!(context.security_level(), 1);
+    }
+
+    #[test]
+    fn nonce_len_matches_the_only_aead_suite_this_build_supports() {
+        let context = EncryptionContext::generate().unwrap();
+        assert_eq!(context.nonce_len(), 12); // AES-256-GCM
+    }
+
+    #[test]
+    fn overhead_bytes_matches_an_actual_encrypted_empty_payload() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let overhead = sender.overhead_bytes();
+
+        let sealed = sender.encrypt_direct(receiver.encryption_public_key().clone(), Vec::<u8>::new()).unwrap();
+
+        assert_eq!(sealed.kem_ciphertext.len(), overhead.kem_ciphertext_len);
+        assert_eq!(sealed.nonce.len(), overhead.nonce_len);
+        // An empty plaintext's ciphertext is just the AEAD tag.
+        assert_eq!(sealed.payload.len(), overhead.aead_tag_len);
+        // Falcon's signatures are variable-length below `max_signature_len`,
+        // so this is a bound rather than an exact match.
+        assert!(sealed.signature.len() <= overhead.max_signature_len);
+
+        let deterministic_overhead = overhead.kem_ciphertext_len + overhead.nonce_len + overhead.aead_tag_len;
+        let deterministic_actual = sealed.kem_ciphertext.len() + sealed.nonce.len() + sealed.payload.len();
+        assert_eq!(deterministic_overhead, deterministic_actual);
+    }
+
+    #[test]
+    fn group_overhead_bytes_has_no_kem_ciphertext_but_carries_a_key_id() {
+        let sender = EncryptionContext::generate().unwrap();
+        let overhead = sender.overhead_bytes();
+        let key = GroupKey::generate();
+
+        let sealed = sender.encrypt_group_body(&key, Vec::<u8>::new()).unwrap();
+
+        assert_eq!(overhead.kem_ciphertext_len, sender.overhead_bytes().kem_ciphertext_len);
+        assert_eq!(sealed.nonce.len(), overhead.nonce_len);
+        assert_eq!(sealed.payload.len(), overhead.aead_tag_len);
+        assert_eq!(sealed.key_id.as_bytes().len(), overhead.group_key_id_len);
+        assert!(sealed.signature.len() <= overhead.max_signature_len);
+    }
+
+    #[test]
+    fn decrypt_direct_rejects_a_short_nonce_instead_of_panicking() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+
+        let mut sealed = sender
+            .encrypt_direct(receiver.encryption_public_key().clone(), b"hello".to_vec())
+            .unwrap();
+        sealed.nonce = vec![0u8; 8];
+
+        let err = receiver.decrypt_direct(&sealed, sender.signing_public_key().clone()).unwrap_err();
+        match err {
+            Error::User(UserError::BadNonceLength { expected, got }) => {
+                assert_eq!(expected, 12);
+                assert_eq!(got, 8);
+            }
+            other => panic!("expected UserError::BadNonceLength, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn decrypt_group_rejects_an_oversized_nonce_instead_of_panicking() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+
+        let mut sealed = sender.encrypt_group_body(&key, b"hi group".to_vec()).unwrap();
+        sealed.nonce = vec![0u8; 24];
+
+        let err = receiver.decrypt_group(&key, &sealed, sender.signing_public_key().clone()).unwrap_err();
+        assert!(matches!(err, Error::User(UserError::BadNonceLength { expected: 12, got: 24 })));
+    }
+
+    #[test]
+    fn verify_chain_accepts_a_well_formed_chain() {
+        let sender = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+
+        let first = sender.encrypt_group_body_chained(&key, b"one".to_vec(), None).unwrap();
+        let second = sender.encrypt_group_body_chained(&key, b"two".to_vec(), Some(first.chain_hash())).unwrap();
+        let third = sender.encrypt_group_body_chained(&key, b"three".to_vec(), Some(second.chain_hash())).unwrap();
+
+        assert!(verify_chain(&[first, second, third]).is_ok());
+    }
+
+    #[test]
+    fn verify_chain_rejects_a_removed_message() {
+        let sender = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+
+        let first = sender.encrypt_group_body_chained(&key, b"one".to_vec(), None).unwrap();
+        let second = sender.encrypt_group_body_chained(&key, b"two".to_vec(), Some(first.chain_hash())).unwrap();
+        let third = sender.encrypt_group_body_chained(&key, b"three".to_vec(), Some(second.chain_hash())).unwrap();
+
+        let err = verify_chain(&[first, third]).unwrap_err();
+        assert!(matches!(err, Error::ChainBroken { index: 1 }));
+    }
+
+    #[test]
+    fn verify_chain_rejects_reordered_messages() {
+        let sender = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+
+        let first = sender.encrypt_group_body_chained(&key, b"one".to_vec(), None).unwrap();
+        let second = sender.encrypt_group_body_chained(&key, b"two".to_vec(), Some(first.chain_hash())).unwrap();
+
+        let err = verify_chain(&[second, first]).unwrap_err();
+        assert!(matches!(err, Error::ChainBroken { index: 0 }));
+    }
+
+    #[test]
+    fn decrypt_group_rejects_a_tampered_prev_hash() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+
+        let first = sender.encrypt_group_body_chained(&key, b"one".to_vec(), None).unwrap();
+        let mut second = sender.encrypt_group_body_chained(&key, b"two".to_vec(), Some(first.chain_hash())).unwrap();
+        second.prev_hash = Some([0u8; 32]);
+
+        let err = receiver.decrypt_group(&key, &second, sender.signing_public_key().clone()).unwrap_err();
+        assert!(matches!(err, Error::SignatureInvalid { .. }));
+    }
+
+    #[test]
+    fn default_serialization_contains_no_secret_key_bytes() {
+        let context = EncryptionContext::generate().unwrap();
+        let encoded = crate::encoding::to_vec(&context).unwrap();
+
+        assert!(!contains_subslice(&encoded, context.sig_secret.as_ref()));
+        assert!(!contains_subslice(&encoded, context.kem_secret.as_ref()));
+        // Sanity check the test itself: the public keys are expected to
+        // show up, since only secrets are redacted.
+        assert!(contains_subslice(&encoded, context.sig_public.as_ref()));
+    }
+
+    fn contains_subslice(haystack: &[u8], needle: &[u8]) -> bool {
+        !needle.is_empty() && haystack.windows(needle.len()).any(|window| window == needle)
+    }
+
+    #[test]
+    fn secret_bytes_round_trip_preserves_key_material_and_retired_keys() {
+        let mut context = EncryptionContext::generate().unwrap();
+        context.rotate_encryption_key().unwrap();
+
+        let bytes = context.to_secret_bytes().unwrap();
+        let restored = EncryptionContext::from_secret_bytes(&bytes).unwrap();
+
+        assert_eq!(restored.signing_public_key().as_ref(), context.signing_public_key().as_ref());
+        assert_eq!(restored.encryption_public_key().as_ref(), context.encryption_public_key().as_ref());
+
+        let receiver = EncryptionContext::generate().unwrap();
+        let sealed = context.encrypt_direct(receiver.encryption_public_key().clone(), b"hello".to_vec()).unwrap();
+        let (_, opened) = receiver.decrypt_direct(&sealed, restored.signing_public_key().clone()).unwrap();
+        assert_eq!(opened, b"hello");
+    }
+
+    #[test]
+    fn encrypt_group_body_has_no_recipients_and_is_still_decryptable() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+
+        let envelope = sender.encrypt_group_body(&key, b"hi group".to_vec()).unwrap();
+        let opened = receiver.decrypt_group(&key, &envelope, sender.signing_public_key().clone()).unwrap();
+        assert_eq!(opened, b"hi group");
+    }
+
+    #[test]
+    fn encrypt_group_stream_round_trips_a_large_body_across_several_recipients() {
+        let sender = EncryptionContext::generate().unwrap();
+        let recipients: Vec<_> = (0..5).map(|_| EncryptionContext::generate().unwrap()).collect();
+        let key = GroupKey::generate();
+
+        // A few chunks' worth, and not an exact multiple of the chunk
+        // size, to exercise the trailing partial chunk too.
+        let data = vec![0x5au8; crate::constants::GROUP_STREAM_CHUNK_LEN * 3 + 17];
+
+        let mut ciphertext = Vec::new();
+        let sealed = sender
+            .encrypt_group_stream(&key, recipients.iter().map(|r| r.encryption_public_key().clone()), data.as_slice(), &mut ciphertext)
+            .unwrap();
+        assert_eq!(sealed.len(), recipients.len());
+        assert_eq!(sealed[0].1.chunk_count, 4);
+
+        for receiver in &recipients {
+            let mut plaintext = Vec::new();
+            receiver
+                .decrypt_group_stream(&key, &sealed[0].1, ciphertext.as_slice(), &mut plaintext, sender.signing_public_key().clone())
+                .unwrap();
+            assert_eq!(plaintext, data);
+        }
+    }
+
+    #[test]
+    fn decrypt_group_stream_rejects_a_manifest_signed_by_the_wrong_key() {
+        let sender = EncryptionContext::generate().unwrap();
+        let impostor = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+
+        let mut ciphertext = Vec::new();
+        let mut manifest = sender.encrypt_group_body_stream(&key, b"hi group".as_slice(), &mut ciphertext).unwrap();
+        manifest.signature = impostor.sign_detached(domain::GROUP_MESSAGE, b"forged").unwrap();
+
+        let mut plaintext = Vec::new();
+        assert!(receiver.decrypt_group_stream(&key, &manifest, ciphertext.as_slice(), &mut plaintext, sender.signing_public_key().clone()).is_err());
+    }
+
+    #[test]
+    fn decrypt_group_stream_rejects_a_truncated_stream() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+
+        let data = vec![0x5au8; crate::constants::GROUP_STREAM_CHUNK_LEN * 2];
+        let mut ciphertext = Vec::new();
+        let manifest = sender.encrypt_group_body_stream(&key, data.as_slice(), &mut ciphertext).unwrap();
+
+        let truncated = &ciphertext[..ciphertext.len() / 2];
+        let mut plaintext = Vec::new();
+        assert!(receiver.decrypt_group_stream(&key, &manifest, truncated, &mut plaintext, sender.signing_public_key().clone()).is_err());
+    }
+
+    #[test]
+    fn decrypt_group_stream_rejects_a_chunk_length_prefix_larger_than_any_legitimate_sender_would_emit() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+
+        let mut ciphertext = Vec::new();
+        let manifest = sender.encrypt_group_body_stream(&key, b"hi group".as_slice(), &mut ciphertext).unwrap();
+
+        // The manifest's signature doesn't cover the per-chunk length
+        // prefixes, so a peer can claim any length here without forging
+        // anything — this is exactly the claim decrypt_group_stream must
+        // reject before it ever allocates a buffer for it.
+        ciphertext[0..4].copy_from_slice(&u32::MAX.to_be_bytes());
+
+        let mut plaintext = Vec::new();
+        let err = receiver
+            .decrypt_group_stream(&key, &manifest, ciphertext.as_slice(), &mut plaintext, sender.signing_public_key().clone())
+            .unwrap_err();
+        assert!(matches!(err, Error::User(UserError::GroupStreamChunkTooLarge { .. })));
+    }
+
+    #[test]
+    fn decrypt_group_stream_verified_round_trips_a_multi_chunk_body() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+
+        let data = vec![0x5au8; crate::constants::GROUP_STREAM_CHUNK_LEN * 2 + 17];
+        let mut ciphertext = Vec::new();
+        let manifest = sender.encrypt_group_body_stream(&key, data.as_slice(), &mut ciphertext).unwrap();
+
+        let mut plaintext = Vec::new();
+        receiver
+            .decrypt_group_stream_verified(&key, &manifest, ciphertext.as_slice(), &mut plaintext, sender.signing_public_key().clone())
+            .unwrap();
+        assert_eq!(plaintext, data);
+    }
+
+    #[test]
+    fn decrypt_group_stream_verified_yields_no_plaintext_for_a_corrupted_final_chunk() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+
+        let data = vec![0x5au8; crate::constants::GROUP_STREAM_CHUNK_LEN * 2];
+        let mut ciphertext = Vec::new();
+        let manifest = sender.encrypt_group_body_stream(&key, data.as_slice(), &mut ciphertext).unwrap();
+
+        // Flip a byte inside the last chunk's ciphertext, leaving every
+        // earlier chunk untouched and still independently valid.
+        let last_byte = ciphertext.len() - 1;
+        ciphertext[last_byte] ^= 0xff;
+
+        let mut plaintext = Vec::new();
+        let err = receiver
+            .decrypt_group_stream_verified(&key, &manifest, ciphertext.as_slice(), &mut plaintext, sender.signing_public_key().clone())
+            .unwrap_err();
+        assert!(matches!(err, Error::Other(_)));
+        assert!(plaintext.is_empty());
+    }
+
+    #[test]
+    fn decrypt_group_stream_verified_rejects_a_chunk_length_prefix_larger_than_any_legitimate_sender_would_emit() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+
+        let mut ciphertext = Vec::new();
+        let manifest = sender.encrypt_group_body_stream(&key, b"hi group".as_slice(), &mut ciphertext).unwrap();
+        ciphertext[0..4].copy_from_slice(&u32::MAX.to_be_bytes());
+
+        let mut plaintext = Vec::new();
+        let err = receiver
+            .decrypt_group_stream_verified(&key, &manifest, ciphertext.as_slice(), &mut plaintext, sender.signing_public_key().clone())
+            .unwrap_err();
+        assert!(matches!(err, Error::User(UserError::GroupStreamChunkTooLarge { .. })));
+        assert!(plaintext.is_empty());
+    }
+
+    #[test]
+    fn decrypt_group_stream_verified_does_not_leave_its_staging_file_behind() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+
+        let data = vec![0x5au8; crate::constants::GROUP_STREAM_CHUNK_LEN + 1];
+        let mut ciphertext = Vec::new();
+        let manifest = sender.encrypt_group_body_stream(&key, data.as_slice(), &mut ciphertext).unwrap();
+
+        let before: std::collections::HashSet<_> = std::fs::read_dir(std::env::temp_dir()).unwrap().filter_map(|e| e.ok().map(|e| e.path())).collect();
+
+        let mut plaintext = Vec::new();
+        receiver
+            .decrypt_group_stream_verified(&key, &manifest, ciphertext.as_slice(), &mut plaintext, sender.signing_public_key().clone())
+            .unwrap();
+
+        let after: std::collections::HashSet<_> = std::fs::read_dir(std::env::temp_dir()).unwrap().filter_map(|e| e.ok().map(|e| e.path())).collect();
+        assert_eq!(before, after, "GroupStreamStaging's temp file should be removed once decrypt_group_stream_verified returns");
+    }
+
+    #[test]
+    fn incremental_mode_already_released_earlier_chunks_before_the_same_corruption_is_found() {
+        // Contrast with the verified test above: the incremental mode's
+        // whole tradeoff is that it doesn't hold plaintext back, so this
+        // is expected — it's exactly what `decrypt_group_stream_verified`
+        // exists to avoid.
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+
+        let data = vec![0x5au8; crate::constants::GROUP_STREAM_CHUNK_LEN * 2];
+        let mut ciphertext = Vec::new();
+        let manifest = sender.encrypt_group_body_stream(&key, data.as_slice(), &mut ciphertext).unwrap();
+
+        let last_byte = ciphertext.len() - 1;
+        ciphertext[last_byte] ^= 0xff;
+
+        let mut plaintext = Vec::new();
+        assert!(receiver.decrypt_group_stream(&key, &manifest, ciphertext.as_slice(), &mut plaintext, sender.signing_public_key().clone()).is_err());
+        assert!(!plaintext.is_empty());
+    }
+
+    #[test]
+    fn encrypt_direct_to_round_trips_with_a_public_profile_data_recipient() {
+        use crate::profile::ProfileBuilder;
+
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = ProfileBuilder::new().name("bob").build().unwrap();
+        let receiver_public = receiver.public();
+
+        let sealed = sender.encrypt_direct_to(&receiver_public, b"hi bob".to_vec()).unwrap();
+        let (content_type, plaintext) = receiver.context().decrypt_direct_to(&sealed, sender.signing_public_key().clone(), receiver_public.profile_id()).unwrap();
+
+        assert_eq!(plaintext, b"hi bob");
+        assert_eq!(content_type, crate::constants::DEFAULT_CONTENT_TYPE);
+    }
+
+    #[test]
+    fn decrypt_direct_to_rejects_an_envelope_addressed_to_a_different_profile_id() {
+        use crate::profile::ProfileBuilder;
+
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = ProfileBuilder::new().name("bob").build().unwrap();
+        let receiver_public = receiver.public();
+        let someone_else = ProfileBuilder::new().name("carol").build().unwrap().public();
+
+        let sealed = sender.encrypt_direct_to(&receiver_public, b"hi bob".to_vec()).unwrap();
+
+        // The mismatched profile_id changes the AAD, which changes the
+        // signed bytes too, so this surfaces as a signature failure; the
+        // signature result is checked before any "no matching key" outcome.
+        let err = receiver.context().decrypt_direct_to(&sealed, sender.signing_public_key().clone(), someone_else.profile_id()).unwrap_err();
+        assert!(matches!(err, Error::SignatureInvalid { .. }));
+    }
+
+    #[test]
+    fn decrypt_group_any_ct_finds_the_matching_key_at_every_position() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+
+        let mut sealed = sender
+            .encrypt_group(&key, vec![receiver.encryption_public_key().clone()], b"hi group".to_vec())
+            .unwrap();
+        let (_pk, envelope) = sealed.remove(0);
+
+        for position in 0..3 {
+            let mut candidates = vec![GroupKey::generate(), GroupKey::generate(), GroupKey::generate()];
+            candidates[position] = key.clone();
+
+            let opened = receiver.decrypt_group_any_ct(&candidates, &envelope, sender.signing_public_key().clone()).unwrap();
+            assert_eq!(opened, b"hi group");
+        }
+    }
+
+    #[test]
+    fn decrypt_group_any_ct_reports_unknown_group_key_when_none_match() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+
+        let mut sealed = sender
+            .encrypt_group(&key, vec![receiver.encryption_public_key().clone()], b"hi group".to_vec())
+            .unwrap();
+        let (_pk, envelope) = sealed.remove(0);
+
+        let candidates = vec![GroupKey::generate(), GroupKey::generate()];
+        let err = receiver.decrypt_group_any_ct(&candidates, &envelope, sender.signing_public_key().clone()).unwrap_err();
+        assert!(matches!(err, Error::User(crate::error::UserError::UnknownGroupKey { .. })));
+    }
+
+    #[test]
+    fn a_forged_signature_emits_a_signature_invalid_security_event() {
+        use crate::audit::{clear_security_sink, set_security_sink, SecurityEvent};
+        use std::sync::{Arc, Mutex};
+
+        let sender = EncryptionContext::generate().unwrap();
+        let impostor = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+
+        let mut sealed = sender.encrypt_direct(receiver.encryption_public_key().clone(), b"hi".to_vec()).unwrap();
+        sealed.signature = impostor.sign_detached(domain::DIRECT_MESSAGE, b"not the real signed bytes").unwrap();
+
+        let received: Arc<Mutex<Vec<SecurityEvent>>> = Arc::new(Mutex::new(Vec::new()));
+        let received_in_sink = received.clone();
+        set_security_sink(move |event| received_in_sink.lock().unwrap().push(event.clone()));
+
+        let result = receiver.decrypt_direct(&sealed, sender.signing_public_key().clone());
+        assert!(matches!(result, Err(Error::SignatureInvalid { .. })));
+
+        let events = received.lock().unwrap();
+        assert!(events.iter().any(|event| matches!(event, SecurityEvent::SignatureInvalid { .. })));
+
+        drop(events);
+        clear_security_sink();
+    }
+}
@@ -0,0 +1,106 @@
+//! Bounded, opt-in cache of KEM decapsulation results.
+//!
+//! Decapsulating is the expensive step of
+//! [`super::EncryptionContext::decrypt_direct`] — normally paid again
+//! every time the same envelope is processed, e.g. because a
+//! retransmission redelivered an identical ciphertext. `DecapsulationCache`
+//! memoizes the [`SharedSecret`] a `kem_ciphertext` already decapsulated
+//! to, keyed by a hash of the ciphertext bytes, so a repeat costs a cache
+//! lookup instead of a full decapsulation.
+//!
+//! Bounded by `capacity`, evicting the oldest entry once full — the same
+//! memory/detection-window tradeoff [`super::NonceTracker`] makes, here
+//! trading memory for decapsulation time instead. Nothing reaches for this
+//! by default: [`super::EncryptionContext::decrypt_direct`] never touches
+//! a cache, and only [`super::EncryptionContext::decrypt_direct_cached`]
+//! consults one, so a caller has to construct a `DecapsulationCache` and
+//! pass it in explicitly to opt in.
+//!
+//! An evicted secret is zeroized before being dropped, so key material
+//! doesn't linger in memory just because it scrolled out of the window
+//! rather than being explicitly discarded.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use sha2::{Digest, Sha256};
+
+use super::types::SharedSecret;
+
+type CacheKey = [u8; 32];
+
+fn cache_key(kem_ciphertext: &[u8]) -> CacheKey {
+    Sha256::digest(kem_ciphertext).into()
+}
+
+/// A bounded, sliding-window memo of `kem_ciphertext -> SharedSecret`,
+/// consulted by [`super::EncryptionContext::decrypt_direct_cached`].
+pub struct DecapsulationCache {
+    capacity: usize,
+    entries: Mutex<HashMap<CacheKey, SharedSecret>>,
+    order: Mutex<VecDeque<CacheKey>>,
+}
+
+impl DecapsulationCache {
+    /// Cache up to `capacity` most recent decapsulation results.
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, entries: Mutex::new(HashMap::new()), order: Mutex::new(VecDeque::new()) }
+    }
+
+    /// The cached `SharedSecret` for `kem_ciphertext`, if its decapsulation
+    /// result hasn't scrolled out of the tracked window.
+    pub fn get(&self, kem_ciphertext: &[u8]) -> Option<SharedSecret> {
+        self.entries.lock().unwrap().get(&cache_key(kem_ciphertext)).cloned()
+    }
+
+    /// Record that `kem_ciphertext` decapsulates to `secret`. Callers only
+    /// reach this after a cache miss, so re-inserting an already-cached
+    /// ciphertext isn't a case this needs to optimize for.
+    pub fn insert(&self, kem_ciphertext: &[u8], secret: SharedSecret) {
+        let key = cache_key(kem_ciphertext);
+        self.entries.lock().unwrap().insert(key, secret);
+
+        let mut order = self.order.lock().unwrap();
+        order.push_back(key);
+        if order.len() > self.capacity {
+            if let Some(evicted_key) = order.pop_front() {
+                if let Some(mut evicted) = self.entries.lock().unwrap().remove(&evicted_key) {
+                    evicted.zeroize();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_repeated_ciphertext_hits_the_cache() {
+        let cache = DecapsulationCache::new(8);
+        let secret = SharedSecret::from_slice(&[7u8; 32]).unwrap();
+        cache.insert(b"some ciphertext", secret.clone());
+
+        let hit = cache.get(b"some ciphertext").unwrap();
+        assert_eq!(hit.as_bytes(), secret.as_bytes());
+    }
+
+    #[test]
+    fn an_uncached_ciphertext_misses() {
+        let cache = DecapsulationCache::new(8);
+        assert!(cache.get(b"never inserted").is_none());
+    }
+
+    #[test]
+    fn eviction_drops_the_oldest_entry_once_over_capacity() {
+        let cache = DecapsulationCache::new(2);
+        cache.insert(b"one", SharedSecret::from_slice(&[1u8; 32]).unwrap());
+        cache.insert(b"two", SharedSecret::from_slice(&[2u8; 32]).unwrap());
+        cache.insert(b"three", SharedSecret::from_slice(&[3u8; 32]).unwrap()); // evicts "one"
+
+        assert!(cache.get(b"one").is_none());
+        assert!(cache.get(b"two").is_some());
+        assert!(cache.get(b"three").is_some());
+    }
+}
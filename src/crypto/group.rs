@@ -0,0 +1,304 @@
+//! Pre-shared symmetric keys for group messaging.
+
+use std::hash::{Hash, Hasher};
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::Argon2;
+use hkdf::Hkdf;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::crypto::types::SharedSecret;
+
+/// A shared symmetric key known to every member of a group, used to
+/// encrypt/decrypt [`super::GroupEncryption`] envelopes without a
+/// per-message KEM cost.
+///
+/// Equality and hashing are keyed only on `id`, not `secret`: two
+/// `GroupKey`s are the "same key" for lookup purposes iff they identify
+/// the same key epoch, and a non-constant-time secret comparison isn't
+/// needed just to find one in a collection.
+#[derive(Clone)]
+pub struct GroupKey {
+    id: Uuid,
+    secret: SharedSecret,
+}
+
+impl PartialEq for GroupKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for GroupKey {}
+
+impl Hash for GroupKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+/// Shows `id` only — never `secret`, so a `GroupKey` accidentally landing
+/// in a log line via `{:?}` doesn't leak key material.
+impl std::fmt::Debug for GroupKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GroupKey").field("id", &self.id).field("secret", &"<redacted>").finish()
+    }
+}
+
+impl GroupKey {
+    /// Generate a fresh random group key.
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        Self {
+            id: Uuid::new_v4(),
+            secret: SharedSecret::from_slice(&bytes).expect("32 bytes"),
+        }
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn secret(&self) -> &SharedSecret {
+        &self.secret
+    }
+
+    /// Whether this key's id is `id`, without needing to construct or
+    /// borrow another `GroupKey` just to compare.
+    pub fn matches_id(&self, id: Uuid) -> bool {
+        self.id == id
+    }
+
+    /// Derive a `GroupKey` deterministically from a shared `passphrase` and
+    /// `group_name`, for small human-coordinated groups that would rather
+    /// agree on a word than relay an invite. Every member who enters the
+    /// same `group_name`/`passphrase` derives the identical key and id, so
+    /// there's nothing to exchange — but the key is then only as strong as
+    /// the passphrase, not the 256 random bits [`Self::generate`] gives
+    /// you. Prefer `generate` (plus an invite) whenever exchanging a key is
+    /// possible.
+    ///
+    /// The Argon2id salt is itself derived from `group_name` rather than
+    /// randomized, since every member must land on the same salt without
+    /// coordinating one out of band. That's what makes this deterministic,
+    /// but it also means, unlike a proper randomized-salt password hash,
+    /// it gives no protection against a precomputed dictionary attack
+    /// targeting a specific well-known group name.
+    pub fn from_passphrase(group_name: &str, passphrase: &str) -> crate::Result<Self> {
+        let id = Uuid::new_v5(&Uuid::NAMESPACE_OID, group_name.as_bytes());
+
+        let salt = Sha256::digest(group_name.as_bytes());
+        let mut secret_bytes = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), &salt[..16], &mut secret_bytes)
+            .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+
+        Ok(Self { id, secret: SharedSecret::from_slice(&secret_bytes)? })
+    }
+
+    /// Derive a sub-key for a single thread within this group, so members
+    /// who already share the root key can split off a sub-topic without a
+    /// separate invite: every member who derives with the same `label`
+    /// lands on the identical id and secret, deterministically, with no
+    /// coordination beyond agreeing on the label string.
+    ///
+    /// The id is a UUIDv5 of the root key's id and `label`; the secret is
+    /// HKDF-SHA256 over the root secret, salted with a fixed domain string
+    /// and expanded with `label` as the info parameter, so distinct labels
+    /// under the same root never collide.
+    ///
+    /// This does *not* provide any isolation from someone who holds the
+    /// root key: compromising `self`'s secret lets an attacker derive
+    /// every subkey for every label the same way a legitimate member
+    /// would. Use it to organize threads within a group that already
+    /// trusts all its members with the root key, not to wall off a
+    /// sub-topic from any of them.
+    pub fn derive_subkey(&self, label: &str) -> Self {
+        let id = Uuid::new_v5(&self.id, label.as_bytes());
+
+        let hkdf = Hkdf::<Sha256>::new(Some(b"resolution:group-subkey:v1"), self.secret.as_bytes());
+        let mut secret_bytes = [0u8; 32];
+        hkdf.expand(label.as_bytes(), &mut secret_bytes).expect("32 bytes is a valid HKDF-SHA256 output length");
+
+        Self { id, secret: SharedSecret::from_slice(&secret_bytes).expect("32 bytes") }
+    }
+
+    /// Reconstruct a `GroupKey` from the `id || secret` bytes produced by
+    /// sealing it into a `Message::GroupInvite`.
+    pub fn from_bytes(bytes: &[u8]) -> crate::Result<Self> {
+        if bytes.len() != 16 + 32 {
+            return Err(crate::error::UserError::BadLength { expected: 16 + 32, got: bytes.len() }.into());
+        }
+        let id = Uuid::from_slice(&bytes[..16]).map_err(anyhow::Error::from)?;
+        let secret = SharedSecret::from_slice(&bytes[16..])?;
+        Ok(Self { id, secret })
+    }
+}
+
+/// A [`GroupKey`]'s AEAD cipher, constructed once and reused across many
+/// messages instead of re-running `Aes256Gcm::new_from_slice` per call, the
+/// way [`crate::crypto::EncryptionContext::encrypt_group`] and
+/// [`crate::crypto::EncryptionContext::decrypt_group`] do. Worth reaching
+/// for when a group is busy enough that the repeated key-schedule setup
+/// shows up; for occasional messages the one-shot functions are simpler
+/// and remain the recommended default.
+///
+/// No benchmark in this crate's `benches/` measures `GroupCipher`, so no
+/// number is claimed here for the per-message savings — `Aes256Gcm::new_from_slice` does a
+/// fixed amount of key-schedule work per call, and `GroupCipher` amortizes
+/// that fixed cost across every message sent through it instead of paying
+/// it again each time.
+pub struct GroupCipher {
+    id: Uuid,
+    cipher: Aes256Gcm,
+}
+
+impl GroupCipher {
+    /// Build a reusable cipher for `key`.
+    pub fn new(key: &GroupKey) -> crate::Result<Self> {
+        let cipher = Aes256Gcm::new_from_slice(key.secret().as_bytes()).map_err(|e| anyhow::anyhow!(e))?;
+        Ok(Self { id: key.id, cipher })
+    }
+
+    /// The id of the [`GroupKey`] this cipher was built from.
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Encrypt `data` under `nonce`. As with the one-shot functions, callers
+    /// must never reuse a nonce with the same key.
+    pub fn encrypt(&self, nonce: [u8; 12], data: impl AsRef<[u8]>) -> crate::Result<Vec<u8>> {
+        self.cipher.encrypt(Nonce::from_slice(&nonce), data.as_ref()).map_err(|_| anyhow::anyhow!("group encryption failed").into())
+    }
+
+    /// Decrypt `data`, previously produced by [`Self::encrypt`] (or by the
+    /// one-shot [`crate::crypto::EncryptionContext::encrypt_group`] under
+    /// the same key) with the given `nonce`.
+    pub fn decrypt(&self, nonce: [u8; 12], data: impl AsRef<[u8]>) -> crate::Result<Vec<u8>> {
+        self.cipher.decrypt(Nonce::from_slice(&nonce), data.as_ref()).map_err(|_| anyhow::anyhow!("group decryption failed").into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn matches_id_checks_the_id_only() {
+        let key = GroupKey::generate();
+        assert!(key.matches_id(key.id()));
+        assert!(!key.matches_id(Uuid::new_v4()));
+    }
+
+    #[test]
+    fn keys_with_the_same_id_are_equal_regardless_of_secret() {
+        let key = GroupKey::generate();
+        let same_id_different_secret = GroupKey { id: key.id(), secret: GroupKey::generate().secret().clone() };
+        assert_eq!(key, same_id_different_secret);
+    }
+
+    #[test]
+    fn group_keys_can_live_in_a_hash_set_by_id() {
+        let a = GroupKey::generate();
+        let b = GroupKey::generate();
+
+        let mut set = HashSet::new();
+        set.insert(a.clone());
+        set.insert(b.clone());
+        assert_eq!(set.len(), 2);
+
+        // Inserting a key with an id already present is a no-op.
+        let a_again = GroupKey { id: a.id(), secret: GroupKey::generate().secret().clone() };
+        set.insert(a_again);
+        assert_eq!(set.len(), 2);
+
+        assert!(set.contains(&a));
+        assert!(set.contains(&b));
+    }
+
+    #[test]
+    fn from_passphrase_is_deterministic() {
+        let a = GroupKey::from_passphrase("book club", "correct horse battery staple").unwrap();
+        let b = GroupKey::from_passphrase("book club", "correct horse battery staple").unwrap();
+        assert_eq!(a.id(), b.id());
+        assert_eq!(a.secret().as_bytes(), b.secret().as_bytes());
+    }
+
+    #[test]
+    fn from_passphrase_differs_per_group_name_or_passphrase() {
+        let base = GroupKey::from_passphrase("book club", "correct horse battery staple").unwrap();
+        let other_group = GroupKey::from_passphrase("chess club", "correct horse battery staple").unwrap();
+        let other_passphrase = GroupKey::from_passphrase("book club", "hunter2").unwrap();
+
+        assert_ne!(base.id(), other_group.id());
+        assert_ne!(base.secret().as_bytes(), other_passphrase.secret().as_bytes());
+    }
+
+    #[test]
+    fn derive_subkey_is_deterministic_for_the_same_label() {
+        let root = GroupKey::generate();
+        let mine = root.derive_subkey("#general");
+        let theirs = root.derive_subkey("#general");
+
+        assert_eq!(mine.id(), theirs.id());
+        assert_eq!(mine.secret().as_bytes(), theirs.secret().as_bytes());
+    }
+
+    #[test]
+    fn derive_subkey_differs_per_label_or_root() {
+        let root = GroupKey::generate();
+        let general = root.derive_subkey("#general");
+        let random = root.derive_subkey("#random");
+        let other_root = GroupKey::generate().derive_subkey("#general");
+
+        assert_ne!(general.id(), random.id());
+        assert_ne!(general.secret().as_bytes(), random.secret().as_bytes());
+        assert_ne!(general.id(), other_root.id());
+        assert_ne!(general.secret().as_bytes(), other_root.secret().as_bytes());
+    }
+
+    #[test]
+    fn group_cipher_ciphertext_is_decryptable_by_the_one_shot_path() {
+        use crate::crypto::domain;
+        use crate::crypto::types::GroupEncryption;
+        use crate::crypto::EncryptionContext;
+
+        let context = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+        let cipher = GroupCipher::new(&key).unwrap();
+        assert_eq!(cipher.id(), key.id());
+
+        let nonce = [7u8; 12];
+        let payload = cipher.encrypt(nonce, b"reused cipher".to_vec()).unwrap();
+
+        let mut signed_bytes = key.id().as_bytes().to_vec();
+        signed_bytes.extend_from_slice(&payload);
+        let signature = context.sign_detached(domain::GROUP_MESSAGE, &signed_bytes).unwrap();
+
+        let envelope = GroupEncryption { key_id: key.id(), nonce: nonce.to_vec(), payload, signature, prev_hash: None };
+        let opened = context.decrypt_group(&key, &envelope, context.signing_public_key().clone()).unwrap();
+        assert_eq!(opened, b"reused cipher");
+    }
+
+    #[test]
+    fn one_shot_ciphertext_is_decryptable_by_group_cipher() {
+        use crate::crypto::EncryptionContext;
+
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+
+        let sealed = sender.encrypt_group(&key, vec![receiver.encryption_public_key().clone()], b"hi group".to_vec()).unwrap();
+        let (_, envelope) = &sealed[0];
+
+        let cipher = GroupCipher::new(&key).unwrap();
+        let nonce: [u8; 12] = envelope.nonce.as_slice().try_into().unwrap();
+        let opened = cipher.decrypt(nonce, &envelope.payload).unwrap();
+        assert_eq!(opened, b"hi group");
+    }
+}
@@ -0,0 +1,33 @@
+//! Post-quantum end-to-end encryption primitives.
+//!
+//! [`EncryptionContext`] holds a profile's KEM and signature key material
+//! and implements the sealed-envelope operations. [`GroupKey`] is a
+//! pre-shared symmetric key for cheap group messaging once members already
+//! know it (established via a [`crate::message::Message::GroupInvite`]).
+
+mod compression;
+mod context;
+mod conversation;
+mod decap_cache;
+mod group;
+mod multisig;
+mod nonce_tracker;
+mod padding;
+mod signed;
+mod types;
+mod verify;
+
+pub use compression::CompressionOutcome;
+pub use context::{check_entropy, domain, encryption_key_from_bytes, key_sizes, signing_key_from_bytes, verify_chain, verify_detached, EncryptionContext, KemChoice, KeySizes, SigChoice};
+pub use conversation::Conversation;
+pub use decap_cache::DecapsulationCache;
+pub use group::{GroupCipher, GroupKey};
+pub use multisig::{verify_threshold, MultiSig};
+pub use nonce_tracker::NonceTracker;
+pub use padding::PaddingScheme;
+pub use signed::Signed;
+pub use types::{EnvelopeKind, EnvelopeOverhead, GroupEncryption, GroupStreamManifest, SharedSecret, SingleEncryption};
+pub use verify::{verify_ct, VerifyOutcome};
+
+pub use oqs::kem;
+pub use oqs::sig;
@@ -0,0 +1,170 @@
+//! Aggregated approval for group actions that need more than one admin's
+//! signature — e.g. removing a member from a group — before they take
+//! effect.
+//!
+//! This crate doesn't yet have a `GroupRoster`/admin-membership type to
+//! integrate against: [`crate::roster::Roster`] tracks a profile's own
+//! contacts, not a group's admin set. [`MultiSig`] and [`verify_threshold`]
+//! are written so that whichever type ends up owning "who is an admin of
+//! this group" can plug straight in as the `signers` list; there's
+//! nothing here that assumes a particular admin-membership representation.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{domain, verify_detached, EncryptionContext};
+use crate::error::UserError;
+use crate::identity::ProfileId;
+use crate::profile::PublicProfileData;
+use crate::Result;
+
+/// A growing set of admin signatures over the same canonical action bytes
+/// (e.g. the msgpack encoding of "remove member X"), collected as each
+/// admin approves. See [`verify_threshold`] to check whether enough of
+/// them, from distinct admins, actually hold.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MultiSig {
+    signatures: Vec<(ProfileId, Vec<u8>)>,
+}
+
+impl MultiSig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `signer`'s approval of `action`, attributed to `signer_id`.
+    /// Doesn't check for a duplicate signer itself — that's
+    /// [`verify_threshold`]'s job, since only it knows which signatures
+    /// are actually going to be counted.
+    pub fn add(&mut self, action: &[u8], signer: &EncryptionContext, signer_id: ProfileId) -> Result<()> {
+        let signature = signer.sign_detached(domain::GROUP_ADMIN_ACTION, action)?;
+        self.signatures.push((signer_id, signature));
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.signatures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.signatures.is_empty()
+    }
+}
+
+/// Confirm `multisig` carries at least `threshold` valid signatures over
+/// `action`, from distinct admins in `signers`. Any repeated signer
+/// (whether or not their signature even verifies) fails the whole check
+/// with [`UserError::DuplicateSigner`] — a group action shouldn't be
+/// approved because one admin's signature was counted twice, so this
+/// fails closed rather than silently deduplicating. A signature from
+/// someone not in `signers`, or one that doesn't verify, is ignored
+/// rather than rejected outright: it just doesn't count toward the
+/// threshold.
+pub fn verify_threshold(action: &[u8], multisig: &MultiSig, signers: &[&PublicProfileData], threshold: usize) -> Result<()> {
+    let mut seen = HashSet::new();
+    let mut confirmed = 0usize;
+
+    for (signer_id, signature) in &multisig.signatures {
+        if !seen.insert(signer_id.clone()) {
+            return Err(UserError::DuplicateSigner { profile_id: signer_id.clone() }.into());
+        }
+
+        let Some(signer) = signers.iter().find(|candidate| candidate.profile_id() == signer_id) else {
+            continue;
+        };
+        if verify_detached(domain::GROUP_ADMIN_ACTION, action, signature, signer.signing_key()).is_ok() {
+            confirmed += 1;
+        }
+    }
+
+    if confirmed >= threshold {
+        Ok(())
+    } else {
+        Err(UserError::BelowSignatureThreshold { got: confirmed, threshold }.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::ProfileBuilder;
+
+    /// `EncryptionContext` isn't `Clone`, so to get both a `PublicProfileData`
+    /// and a still-usable signing context back out, round-trip the secret
+    /// key material through [`EncryptionContext::to_secret_bytes`] to
+    /// produce a second, independent context with the same keys.
+    fn admin(name: &str) -> (EncryptionContext, PublicProfileData) {
+        let secret_bytes = EncryptionContext::generate().unwrap().to_secret_bytes().unwrap();
+        let profile = ProfileBuilder::new().identity(name, EncryptionContext::from_secret_bytes(&secret_bytes).unwrap()).build().unwrap();
+        let public = profile.public();
+        let context = EncryptionContext::from_secret_bytes(&secret_bytes).unwrap();
+        (context, public)
+    }
+
+    #[test]
+    fn exactly_threshold_distinct_valid_signatures_passes() {
+        let (alice_ctx, alice) = admin("alice");
+        let (bob_ctx, bob) = admin("bob");
+        let action = b"remove member carol".to_vec();
+
+        let mut multisig = MultiSig::new();
+        multisig.add(&action, &alice_ctx, alice.profile_id().clone()).unwrap();
+        multisig.add(&action, &bob_ctx, bob.profile_id().clone()).unwrap();
+
+        assert!(verify_threshold(&action, &multisig, &[&alice, &bob], 2).is_ok());
+    }
+
+    #[test]
+    fn below_threshold_signatures_fails() {
+        let (alice_ctx, alice) = admin("alice");
+        let (_bob_ctx, bob) = admin("bob");
+        let action = b"remove member carol".to_vec();
+
+        let mut multisig = MultiSig::new();
+        multisig.add(&action, &alice_ctx, alice.profile_id().clone()).unwrap();
+
+        let err = verify_threshold(&action, &multisig, &[&alice, &bob], 2).unwrap_err();
+        assert!(matches!(err, crate::Error::User(UserError::BelowSignatureThreshold { got: 1, threshold: 2 })));
+    }
+
+    #[test]
+    fn duplicate_signer_is_rejected_even_if_below_threshold_otherwise() {
+        let (alice_ctx, alice) = admin("alice");
+        let action = b"remove member carol".to_vec();
+
+        let mut multisig = MultiSig::new();
+        multisig.add(&action, &alice_ctx, alice.profile_id().clone()).unwrap();
+        multisig.add(&action, &alice_ctx, alice.profile_id().clone()).unwrap();
+
+        let err = verify_threshold(&action, &multisig, &[&alice], 1).unwrap_err();
+        assert!(matches!(err, crate::Error::User(UserError::DuplicateSigner { .. })));
+    }
+
+    #[test]
+    fn a_signature_over_a_different_action_does_not_count() {
+        let (alice_ctx, alice) = admin("alice");
+        let (bob_ctx, bob) = admin("bob");
+
+        let mut multisig = MultiSig::new();
+        multisig.add(b"remove member carol", &alice_ctx, alice.profile_id().clone()).unwrap();
+        multisig.add(b"remove member dave", &bob_ctx, bob.profile_id().clone()).unwrap();
+
+        let err = verify_threshold(b"remove member carol", &multisig, &[&alice, &bob], 2).unwrap_err();
+        assert!(matches!(err, crate::Error::User(UserError::BelowSignatureThreshold { got: 1, threshold: 2 })));
+    }
+
+    #[test]
+    fn a_signature_from_someone_outside_signers_does_not_count() {
+        let (alice_ctx, alice) = admin("alice");
+        let (outsider_ctx, outsider) = admin("mallory");
+        let action = b"remove member carol".to_vec();
+
+        let mut multisig = MultiSig::new();
+        multisig.add(&action, &alice_ctx, alice.profile_id().clone()).unwrap();
+        multisig.add(&action, &outsider_ctx, outsider.profile_id().clone()).unwrap();
+
+        let err = verify_threshold(&action, &multisig, &[&alice], 2).unwrap_err();
+        assert!(matches!(err, crate::Error::User(UserError::BelowSignatureThreshold { got: 1, threshold: 2 })));
+    }
+}
@@ -0,0 +1,92 @@
+//! Best-effort detection of a reused AEAD nonce under a group key.
+//!
+//! GCM's security guarantee breaks down the moment the same nonce is used
+//! twice under the same key, e.g. because a buggy or malicious sender
+//! replayed state. [`NonceTracker`] can only catch this *after* the fact —
+//! by the time a second envelope with a seen `(key_id, nonce)` pair shows
+//! up, whatever confidentiality loss the first reuse caused has already
+//! happened. It does not, and cannot, prevent the first occurrence.
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+
+use uuid::Uuid;
+
+/// A `(key_id, nonce)` pair identifying one AEAD invocation under a group
+/// key. The nonce is stored at whatever length the sender's suite used
+/// rather than a fixed `[u8; 12]`, so this doesn't have to change if a
+/// future suite picks a different nonce size.
+type NonceKey = (Uuid, Vec<u8>);
+
+/// A bounded, sliding-window record of `(key_id, nonce)` pairs seen so
+/// far, consulted by [`super::EncryptionContext::decrypt_group_checked`].
+///
+/// Bounded by `capacity` rather than growing without limit: once full, the
+/// oldest observation is evicted to make room for the newest, so a
+/// long-lived tracker costs a fixed amount of memory instead of growing
+/// for the life of the process. This means a nonce reused far enough
+/// apart to have scrolled out of the window won't be caught — a
+/// deliberate memory/detection-window tradeoff, not an oversight.
+pub struct NonceTracker {
+    capacity: usize,
+    seen: Mutex<HashSet<NonceKey>>,
+    order: Mutex<VecDeque<NonceKey>>,
+}
+
+impl NonceTracker {
+    /// Track up to `capacity` most recent `(key_id, nonce)` pairs.
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, seen: Mutex::new(HashSet::new()), order: Mutex::new(VecDeque::new()) }
+    }
+
+    /// Record `(key_id, nonce)`, returning `true` if it hadn't been seen
+    /// before (still within the tracked window) or `false` if it's a
+    /// repeat.
+    pub fn observe(&self, key_id: Uuid, nonce: Vec<u8>) -> bool {
+        let entry = (key_id, nonce);
+        let mut seen = self.seen.lock().unwrap();
+        if !seen.insert(entry.clone()) {
+            return false;
+        }
+        drop(seen);
+
+        let mut order = self.order.lock().unwrap();
+        order.push_back(entry);
+        if order.len() > self.capacity {
+            if let Some(evicted) = order.pop_front() {
+                self.seen.lock().unwrap().remove(&evicted);
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_fresh_nonce_is_observed_but_a_repeat_is_not() {
+        let tracker = NonceTracker::new(8);
+        let key_id = Uuid::new_v4();
+        assert!(tracker.observe(key_id, vec![1u8; 12]));
+        assert!(!tracker.observe(key_id, vec![1u8; 12]));
+    }
+
+    #[test]
+    fn the_same_nonce_under_a_different_key_id_is_not_a_reuse() {
+        let tracker = NonceTracker::new(8);
+        assert!(tracker.observe(Uuid::new_v4(), vec![1u8; 12]));
+        assert!(tracker.observe(Uuid::new_v4(), vec![1u8; 12]));
+    }
+
+    #[test]
+    fn a_nonce_evicted_by_the_sliding_window_can_be_observed_again() {
+        let tracker = NonceTracker::new(2);
+        let key_id = Uuid::new_v4();
+        assert!(tracker.observe(key_id, vec![1u8; 12]));
+        assert!(tracker.observe(key_id, vec![2u8; 12]));
+        assert!(tracker.observe(key_id, vec![3u8; 12])); // evicts [1u8; 12]
+        assert!(tracker.observe(key_id, vec![1u8; 12])); // no longer tracked, looks fresh again
+    }
+}
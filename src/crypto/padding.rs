@@ -0,0 +1,95 @@
+//! Plaintext padding, applied before AEAD encryption so ciphertext length
+//! stops leaking the exact plaintext length to network observers.
+
+use crate::error::UserError;
+use crate::Result;
+
+/// How aggressively to bucket plaintext lengths together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaddingScheme {
+    /// Round up to the next power of two, so an observer only learns which
+    /// octave a message falls in.
+    PowerOfTwo,
+    /// Round up to the next multiple of a fixed bucket size, for a coarser
+    /// or finer tradeoff than powers of two.
+    Bucket(usize),
+}
+
+impl PaddingScheme {
+    fn padded_len(self, len: usize) -> usize {
+        match self {
+            PaddingScheme::PowerOfTwo => len.next_power_of_two(),
+            PaddingScheme::Bucket(size) => {
+                assert!(size > 0, "padding bucket size must be non-zero");
+                len.div_ceil(size) * size
+            }
+        }
+    }
+}
+
+/// Prefix `data` with its true length and zero-pad it out to `scheme`'s
+/// next bucket. The result is meant to be encrypted whole, so the padding
+/// ends up inside the AEAD ciphertext rather than appended alongside it.
+pub(crate) fn pad(data: &[u8], scheme: PaddingScheme) -> Vec<u8> {
+    let prefixed_len = data.len() + 4;
+    let mut buf = Vec::with_capacity(scheme.padded_len(prefixed_len));
+    buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    buf.extend_from_slice(data);
+    buf.resize(scheme.padded_len(prefixed_len), 0);
+    buf
+}
+
+/// Reverse [`pad`], trimming the padding back off using the embedded true
+/// length. Fails if the length prefix is missing or claims more data than
+/// is actually present.
+pub(crate) fn unpad(padded: &[u8]) -> Result<Vec<u8>> {
+    if padded.len() < 4 {
+        return Err(UserError::BadLength { expected: 4, got: padded.len() }.into());
+    }
+    let len = u32::from_be_bytes(padded[..4].try_into().unwrap()) as usize;
+    let end = 4 + len;
+    if end > padded.len() {
+        return Err(UserError::BadLength { expected: end, got: padded.len() }.into());
+    }
+    Ok(padded[4..end].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn power_of_two_buckets_equal_ciphertext_lengths() {
+        let lengths = [1usize, 30, 31, 32, 33, 100, 1000];
+        let padded_lens: Vec<_> = lengths.iter().map(|&len| pad(&vec![0u8; len], PaddingScheme::PowerOfTwo).len()).collect();
+
+        // Once the 4-byte length prefix is counted, 28 bytes fill a 32-byte
+        // bucket exactly and 29 spill into 64, so only check within a shared
+        // bucket: 10 and 12 bytes both pad to 16.
+        assert_eq!(pad(&vec![1u8; 10], PaddingScheme::PowerOfTwo).len(), pad(&vec![2u8; 12], PaddingScheme::PowerOfTwo).len());
+        assert!(padded_lens.iter().all(|&len| len.is_power_of_two()));
+    }
+
+    #[test]
+    fn bucket_scheme_rounds_up_to_multiple() {
+        for len in [1usize, 15, 16, 17, 63, 64] {
+            let padded = pad(&vec![0u8; len], PaddingScheme::Bucket(16));
+            assert_eq!(padded.len() % 16, 0);
+        }
+    }
+
+    #[test]
+    fn pad_then_unpad_round_trips() {
+        for len in [0usize, 1, 17, 255, 1000] {
+            let data = vec![0xab; len];
+            let padded = pad(&data, PaddingScheme::Bucket(64));
+            assert_eq!(unpad(&padded).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn unpad_rejects_truncated_input() {
+        assert!(unpad(&[0, 0, 0]).is_err());
+        assert!(unpad(&[0, 0, 0, 5, 1, 2]).is_err());
+    }
+}
@@ -0,0 +1,107 @@
+//! A generic signed wrapper for any serializable value.
+//!
+//! [`crate::profile::SignedProfile`] and [`super::EncryptionContext`]'s
+//! `encrypt_direct`/`encrypt_group` each hand-roll their own "serialize,
+//! sign, verify" plumbing for one specific type. `Signed<T>` is that same
+//! shape made generic, for protocol types (presence, rosters,
+//! announcements) that need to be signed and verified but don't need
+//! encryption. `SignedProfile` isn't rebuilt on top of this yet — it also
+//! carries an expiry and clock-skew policy that don't belong in a generic
+//! wrapper — but new signed-but-not-encrypted types should use `Signed<T>`
+//! rather than repeating the pattern by hand.
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::crypto::{domain, sig, verify_detached, EncryptionContext};
+use crate::Result;
+
+/// The canonical msgpack bytes of a `T`, plus a detached signature over
+/// them. `T` itself isn't kept around: [`Self::verify`] re-decodes it from
+/// `bytes` so a caller can never observe an unverified value.
+#[derive(Debug, Clone)]
+pub struct Signed<T> {
+    bytes: Vec<u8>,
+    signature: Vec<u8>,
+    _value: PhantomData<T>,
+}
+
+impl<T: Serialize> Signed<T> {
+    /// Sign `value` with `context`'s signing key.
+    pub fn new(context: &EncryptionContext, value: &T) -> Result<Self> {
+        let bytes = crate::encoding::to_vec(value)?;
+        let signature = context.sign_detached(domain::SIGNED_VALUE, &bytes)?;
+        Ok(Self { bytes, signature, _value: PhantomData })
+    }
+}
+
+impl<T: DeserializeOwned> Signed<T> {
+    /// Verify the signature against `signer` and, if it holds, decode and
+    /// return the signed value.
+    pub fn verify(&self, signer: impl AsRef<sig::PublicKey>) -> Result<T> {
+        verify_detached(domain::SIGNED_VALUE, &self.bytes, &self.signature, signer.as_ref())?;
+        crate::encoding::from_slice(&self.bytes)
+    }
+}
+
+/// Wire representation of a [`Signed<T>`]: just the signed bytes and
+/// signature, with no dependence on `T` itself, since a receiver needs to
+/// hold them before it can even attempt [`Signed::verify`].
+#[derive(Serialize, serde::Deserialize)]
+struct SignedWire {
+    bytes: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl<T> Signed<T> {
+    /// Encode as bytes suitable for transmission, e.g. as iroh discovery
+    /// `UserData`. See [`Self::from_bytes`] for the inverse.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        crate::encoding::to_vec(&SignedWire { bytes: self.bytes.clone(), signature: self.signature.clone() })
+    }
+
+    /// Inverse of [`Self::to_bytes`]. Doesn't verify the signature or
+    /// decode `T` yet — call [`Self::verify`] for that once you have a
+    /// candidate signer's key.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let wire: SignedWire = crate::encoding::from_slice(bytes)?;
+        Ok(Self { bytes: wire.bytes, signature: wire.signature, _value: PhantomData })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::{ProfileBuilder, PublicProfileData};
+
+    #[test]
+    fn signed_public_profile_data_round_trips() {
+        let profile = ProfileBuilder::new().name("alice").build().unwrap();
+        let signed = Signed::new(profile.context(), &profile.public()).unwrap();
+
+        let verified: PublicProfileData = signed.verify(profile.context().signing_public_key().clone()).unwrap();
+        assert_eq!(verified.name(), "alice");
+    }
+
+    #[test]
+    fn wrong_signer_fails_verification() {
+        let profile = ProfileBuilder::new().name("alice").build().unwrap();
+        let impostor = EncryptionContext::generate().unwrap();
+        let signed = Signed::new(profile.context(), &profile.public()).unwrap();
+
+        assert!(signed.verify(impostor.signing_public_key().clone()).is_err());
+    }
+
+    #[test]
+    fn to_bytes_then_from_bytes_round_trips_and_still_verifies() {
+        let profile = ProfileBuilder::new().name("alice").build().unwrap();
+        let signed = Signed::new(profile.context(), &profile.public()).unwrap();
+
+        let bytes = signed.to_bytes().unwrap();
+        let decoded = Signed::<PublicProfileData>::from_bytes(&bytes).unwrap();
+
+        let verified: PublicProfileData = decoded.verify(profile.context().signing_public_key().clone()).unwrap();
+        assert_eq!(verified.name(), "alice");
+    }
+}
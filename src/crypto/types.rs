@@ -0,0 +1,305 @@
+//! Wire types produced and consumed by [`super::EncryptionContext`].
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use zeroize::Zeroize;
+
+use crate::error::UserError;
+use crate::identity::MessageId;
+use crate::Result;
+
+/// Hash `parts` and truncate to a [`MessageId`]. Not a security boundary,
+/// just a stable dedup key, so 128 bits of a wider digest is plenty.
+fn message_id_of(parts: &[&[u8]]) -> MessageId {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    let digest = hasher.finalize();
+    let mut id = [0u8; 16];
+    id.copy_from_slice(&digest[..16]);
+    MessageId(id)
+}
+
+/// An AEAD key, either derived from a KEM decapsulation or generated
+/// directly for a [`super::GroupKey`].
+#[derive(Clone)]
+pub struct SharedSecret([u8; 32]);
+
+impl SharedSecret {
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Build a `SharedSecret` from raw bytes, requiring exactly 32 bytes.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != 32 {
+            return Err(UserError::BadLength { expected: 32, got: bytes.len() }.into());
+        }
+        let mut buf = [0u8; 32];
+        buf.copy_from_slice(bytes);
+        Ok(Self(buf))
+    }
+
+    /// Overwrite this secret's bytes with zero. Used by
+    /// [`crate::crypto::DecapsulationCache`] when it evicts an entry, so a
+    /// secret that's fallen out of the cache's window doesn't linger in
+    /// memory just because nothing has dropped it yet.
+    pub(crate) fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl TryFrom<Vec<u8>> for SharedSecret {
+    type Error = crate::Error;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self> {
+        Self::from_slice(&bytes)
+    }
+}
+
+/// What a [`SingleEncryption`]'s plaintext represents, authenticated
+/// alongside the rest of the envelope so a receiver holding just the raw,
+/// still-sealed envelope can tell which of `decrypt_direct` or
+/// `decrypt_group`-family method to reach for without a trial decryption.
+/// See [`SingleEncryption::kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EnvelopeKind {
+    /// A regular direct message, produced by
+    /// [`super::EncryptionContext::encrypt_direct`] and its variants.
+    Direct,
+    /// A [`super::GroupKey`] sealed to a single recipient, produced by
+    /// [`super::EncryptionContext::encrypt_group_key_to`] as part of a
+    /// [`crate::message::Message::GroupInvite`]. The plaintext this opens
+    /// to is key material, not chat content.
+    GroupWrapped,
+}
+
+impl EnvelopeKind {
+    /// A single-byte encoding folded into the signed bytes alongside
+    /// `content_type`, so tampering with it (e.g. relabeling a
+    /// `GroupWrapped` envelope as `Direct` to smuggle key material past a
+    /// dispatcher that only decrypts messages it expects) is caught by
+    /// signature verification the same way a tampered `content_type` is.
+    pub(crate) fn signed_byte(self) -> u8 {
+        match self {
+            EnvelopeKind::Direct => 0,
+            EnvelopeKind::GroupWrapped => 1,
+        }
+    }
+}
+
+/// An envelope sealed to a single recipient's KEM public key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SingleEncryption {
+    pub kem_ciphertext: Vec<u8>,
+    /// AEAD nonce, at whatever length the sender's suite used. See
+    /// [`super::EncryptionContext::nonce_len`].
+    pub nonce: Vec<u8>,
+    pub payload: Vec<u8>,
+    pub signature: Vec<u8>,
+    /// MIME-like tag for the plaintext (e.g. `text/plain`), authenticated
+    /// by the same signature as the ciphertext but not itself encrypted.
+    pub content_type: String,
+    /// What kind of plaintext this envelope opens to. See
+    /// [`SingleEncryption::kind`].
+    kind: EnvelopeKind,
+}
+
+impl SingleEncryption {
+    pub(crate) fn new(kem_ciphertext: Vec<u8>, nonce: Vec<u8>, payload: Vec<u8>, signature: Vec<u8>, content_type: String, kind: EnvelopeKind) -> Self {
+        Self { kem_ciphertext, nonce, payload, signature, content_type, kind }
+    }
+
+    /// Whether this envelope's plaintext is a regular direct message or a
+    /// wrapped group key. Read straight off the envelope, no decryption
+    /// required — the value is authenticated by the envelope's signature,
+    /// so it's safe to route on before ever calling `decrypt_direct`.
+    pub fn kind(&self) -> EnvelopeKind {
+        self.kind
+    }
+
+    /// A stable identifier derived from this envelope's ciphertext bytes,
+    /// not its plaintext: useful for ack/receipt correlation and
+    /// offline-queue dedup, since the same received envelope always
+    /// hashes to the same id. Re-encrypting identical plaintext produces a
+    /// fresh nonce and signature, and therefore a different id.
+    pub fn message_id(&self) -> MessageId {
+        message_id_of(&[&self.kem_ciphertext, &self.nonce, &self.payload, &self.signature, self.content_type.as_bytes(), &[self.kind.signed_byte()]])
+    }
+}
+
+/// An envelope encrypted directly under a pre-shared [`super::GroupKey`],
+/// with no per-message KEM cost.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupEncryption {
+    pub key_id: Uuid,
+    /// AEAD nonce, at whatever length the sender's suite used. See
+    /// [`super::EncryptionContext::nonce_len`].
+    pub nonce: Vec<u8>,
+    pub payload: Vec<u8>,
+    pub signature: Vec<u8>,
+    /// [`Self::chain_hash`] of the previous authenticated message in this
+    /// conversation, for groups where order matters. Part of the signed
+    /// bytes when present — see
+    /// [`super::EncryptionContext::encrypt_group_body_chained`] — so a
+    /// tampered link is caught by signature verification, and
+    /// [`super::verify_chain`] can catch reordering, insertion, or
+    /// deletion across the whole sequence. `None` for messages outside a
+    /// chain, e.g. everything produced by [`super::EncryptionContext::encrypt_group_body`].
+    pub prev_hash: Option<[u8; 32]>,
+}
+
+impl GroupEncryption {
+    /// See [`SingleEncryption::message_id`]: same determinism guarantee,
+    /// hashed over this envelope's own fields instead.
+    pub fn message_id(&self) -> MessageId {
+        message_id_of(&[self.key_id.as_bytes(), &self.nonce, &self.payload, &self.signature])
+    }
+
+    /// Hash of this envelope's authenticated bytes, including its
+    /// signature — the value the next message in an ordered conversation
+    /// links to via its own `prev_hash`. See
+    /// [`super::EncryptionContext::encrypt_group_body_chained`] and
+    /// [`super::verify_chain`].
+    pub fn chain_hash(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(self.key_id.as_bytes());
+        hasher.update(&self.nonce);
+        hasher.update(&self.payload);
+        hasher.update(&self.signature);
+        hasher.finalize().into()
+    }
+}
+
+/// The fixed and worst-case per-message overhead a suite adds, from
+/// [`super::EncryptionContext::overhead_bytes`]. Lets an app compute an
+/// effective MTU or chunk size without hand-tracking algorithm parameters
+/// as they change across suites.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnvelopeOverhead {
+    /// KEM ciphertext length. Only [`SingleEncryption`] carries one — a
+    /// [`GroupEncryption`] has none, since its key is already shared.
+    pub kem_ciphertext_len: usize,
+    /// AEAD nonce length. See [`super::EncryptionContext::nonce_len`].
+    pub nonce_len: usize,
+    /// AES-256-GCM's authentication tag, appended to the ciphertext by
+    /// the AEAD itself.
+    pub aead_tag_len: usize,
+    /// The signing algorithm's maximum signature length. Falcon (this
+    /// build's default) produces variable-length signatures at or below
+    /// this bound, so a real envelope's signature is usually a little
+    /// shorter — use this value when sizing a fixed buffer or MTU
+    /// budget, not when predicting an exact wire size.
+    pub max_signature_len: usize,
+    /// A [`GroupEncryption`]'s `key_id`. Absent from a [`SingleEncryption`].
+    pub group_key_id_len: usize,
+}
+
+impl EnvelopeOverhead {
+    /// Worst-case fixed overhead [`super::EncryptionContext::encrypt_direct`]
+    /// (and its `_typed`/`_padded`/`_compressed` variants) adds around the
+    /// plaintext, excluding `content_type` — that's caller-controlled, not
+    /// part of the crypto suite.
+    pub fn direct_bytes(&self) -> usize {
+        self.kem_ciphertext_len + self.nonce_len + self.aead_tag_len + self.max_signature_len
+    }
+
+    /// As [`Self::direct_bytes`], but for
+    /// [`super::EncryptionContext::encrypt_group`]/`encrypt_group_body`:
+    /// no KEM ciphertext (the key's already shared), plus the envelope's
+    /// `key_id`.
+    pub fn group_bytes(&self) -> usize {
+        self.group_key_id_len + self.nonce_len + self.aead_tag_len + self.max_signature_len
+    }
+
+    /// The size a standard base64 encoding of `raw_len` bytes of this
+    /// overhead would take — for apps that carry an envelope as text
+    /// (e.g. a `resolution://` link or an iroh discovery `UserData`
+    /// beacon, like [`crate::presence::Presence::signed`]) rather than
+    /// raw bytes.
+    pub fn base64_inflated_len(raw_len: usize) -> usize {
+        raw_len.div_ceil(3) * 4
+    }
+}
+
+/// Describes a body sealed under a pre-shared [`super::GroupKey`] chunk by
+/// chunk instead of all at once, by
+/// [`super::EncryptionContext::encrypt_group_stream`]. Carries everything
+/// a receiver needs to re-derive each chunk's nonce and check it hasn't
+/// been truncated, but none of the ciphertext itself — that's written
+/// straight to the caller's `writer` as it's produced, which is the whole
+/// point of streaming it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupStreamManifest {
+    pub key_id: Uuid,
+    /// Random per-stream nonce prefix; each chunk's nonce is this prefix
+    /// followed by its big-endian chunk index, so no two chunks in the
+    /// same stream (or across streams, so long as the prefix isn't
+    /// reused) ever share a nonce under `key_id`.
+    pub nonce_prefix: [u8; 4],
+    /// Total number of chunks, signed alongside `nonce_prefix` so a
+    /// truncated stream (one ending after fewer chunks than promised)
+    /// is caught even though each individual chunk already
+    /// authenticates itself.
+    pub chunk_count: u64,
+    pub signature: Vec<u8>,
+}
+
+impl GroupStreamManifest {
+    /// See [`SingleEncryption::message_id`]. Bound to the manifest only,
+    /// not the streamed ciphertext itself, since that never fully exists
+    /// in memory at once.
+    pub fn message_id(&self) -> MessageId {
+        message_id_of(&[self.key_id.as_bytes(), &self.nonce_prefix, &self.chunk_count.to_be_bytes(), &self.signature])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::EncryptionContext;
+    use crate::crypto::GroupKey;
+
+    #[test]
+    fn message_id_is_stable_and_ciphertext_bound() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+
+        let sealed = sender.encrypt_direct(receiver.encryption_public_key().clone(), b"hi".to_vec()).unwrap();
+        assert_eq!(sealed.message_id(), sealed.message_id());
+
+        // Re-encrypting the same plaintext gets a fresh nonce/signature,
+        // so it must not collide with the original envelope's id.
+        let resealed = sender.encrypt_direct(receiver.encryption_public_key().clone(), b"hi".to_vec()).unwrap();
+        assert_ne!(sealed.message_id(), resealed.message_id());
+    }
+
+    #[test]
+    fn shared_secret_from_slice_reports_expected_then_got_on_bad_length() {
+        let err = SharedSecret::from_slice(&[0u8; 31]).unwrap_err();
+        match err {
+            crate::Error::User(UserError::BadLength { expected, got }) => {
+                assert_eq!(expected, 32);
+                assert_eq!(got, 31);
+            }
+            other => panic!("expected UserError::BadLength, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn group_message_id_is_stable_and_ciphertext_bound() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let key = GroupKey::generate();
+
+        let sealed = sender.encrypt_group(&key, vec![receiver.encryption_public_key().clone()], b"hi group".to_vec()).unwrap();
+        let (_, envelope) = &sealed[0];
+        assert_eq!(envelope.message_id(), envelope.message_id());
+
+        let resealed = sender.encrypt_group(&key, vec![receiver.encryption_public_key().clone()], b"hi group".to_vec()).unwrap();
+        let (_, resealed_envelope) = &resealed[0];
+        assert_ne!(envelope.message_id(), resealed_envelope.message_id());
+    }
+}
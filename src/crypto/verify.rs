@@ -0,0 +1,73 @@
+//! A verification result that defers branching, for callers that want to
+//! do a fixed amount of work regardless of whether a signature checked out
+//! before deciding what to do about it.
+//!
+//! Threat model, honestly: on a real network, jitter and scheduling noise
+//! dwarf any timing signal from a failed-vs-passed verification, so this
+//! doesn't meaningfully harden anything against a remote attacker. It's
+//! aimed at a *local* one — co-resident on the same host, or otherwise
+//! positioned to measure with far less noise than the network adds — who
+//! could otherwise learn something from an early return that skips
+//! decryption work entirely on a bad signature.
+
+use oqs::sig;
+
+use crate::crypto::context::verify_detached;
+use crate::error::Error;
+use crate::Result;
+
+/// The outcome of a signature check, held without having branched on it.
+pub enum VerifyOutcome {
+    Valid,
+    Invalid(Error),
+}
+
+impl VerifyOutcome {
+    pub fn is_valid(&self) -> bool {
+        matches!(self, VerifyOutcome::Valid)
+    }
+
+    /// Consume the outcome, turning `Invalid` back into its error. Call
+    /// this only once the caller has already done whatever fixed-shape
+    /// work it wanted to perform independent of the result.
+    pub fn into_result(self) -> Result<()> {
+        match self {
+            VerifyOutcome::Valid => Ok(()),
+            VerifyOutcome::Invalid(e) => Err(e),
+        }
+    }
+}
+
+/// Verify `bytes` against `signature` the same way [`verify_detached`]
+/// does, but return a [`VerifyOutcome`] instead of a `Result` so the
+/// caller can't accidentally `?`-early-return on it before doing
+/// unrelated work that should happen either way.
+pub fn verify_ct(purpose: &str, bytes: &[u8], signature: &[u8], signer: &sig::PublicKey) -> VerifyOutcome {
+    match verify_detached(purpose, bytes, signature, signer) {
+        Ok(()) => VerifyOutcome::Valid,
+        Err(e) => VerifyOutcome::Invalid(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::context::domain;
+    use crate::crypto::EncryptionContext;
+
+    #[test]
+    fn valid_and_invalid_outcomes_behave_the_same_shape() {
+        let context = EncryptionContext::generate().unwrap();
+        let other = EncryptionContext::generate().unwrap();
+        let bytes = b"some signed bytes";
+        let signature = context.sign_detached(domain::DEVICE_BINDING, bytes).unwrap();
+
+        let valid = verify_ct(domain::DEVICE_BINDING, bytes, &signature, context.signing_public_key());
+        let invalid = verify_ct(domain::DEVICE_BINDING, bytes, &signature, other.signing_public_key());
+
+        assert!(valid.is_valid());
+        assert!(!invalid.is_valid());
+        assert!(valid.into_result().is_ok());
+        assert!(invalid.into_result().is_err());
+    }
+}
@@ -0,0 +1,162 @@
+//! Human-friendly discriminant suffixes disambiguating profiles that share
+//! a display name, derived from a profile's public key material.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::crypto::{kem, sig};
+use crate::error::UserError;
+use crate::Result;
+
+/// Which algorithm a discriminant suffix was computed with. Stored on
+/// [`crate::profile::PublicProfileData`] so `Profile::public()` keeps
+/// producing ids in whatever scheme is current, while
+/// [`crate::profile::PublicProfileData::validate`] can still recompute and
+/// check discriminants that predate this field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiscriminantScheme {
+    /// The original 4-digit CRC-16/CCITT scheme. A 16-bit checksum is a
+    /// small enough space that a deliberate collision is feasible, so this
+    /// is kept only for compatibility with ids computed before
+    /// [`DiscriminantScheme::Sha256Base32`] existed.
+    Crc16,
+    /// SHA-256 of the key material, truncated to 40 bits and base32
+    /// encoded. Collision-resistant enough that a name-and-discriminant
+    /// pair can't practically be forged for someone else's keys. The
+    /// scheme new profiles are built with.
+    Sha256Base32,
+}
+
+impl Default for DiscriminantScheme {
+    /// What a wire blob predating this field is assumed to have used,
+    /// matching `discriminant()`'s original (CRC-16-only) behavior.
+    fn default() -> Self {
+        DiscriminantScheme::Crc16
+    }
+}
+
+/// Compute the discriminant suffix for a key pair under `scheme`.
+pub fn discriminant(scheme: DiscriminantScheme, signing: &sig::PublicKey, encryption: &kem::PublicKey) -> String {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(signing.as_ref());
+    bytes.extend_from_slice(encryption.as_ref());
+
+    match scheme {
+        DiscriminantScheme::Crc16 => format!("{:04}", crc16_ccitt(&bytes) % 10_000),
+        DiscriminantScheme::Sha256Base32 => {
+            let digest = Sha256::digest(&bytes);
+            base32::encode(base32::Alphabet::RFC4648 { padding: false }, &digest[..5]).to_lowercase()
+        }
+    }
+}
+
+/// Guess which [`DiscriminantScheme`] produced `suffix`, from its shape
+/// alone: the legacy scheme is always exactly 4 decimal digits, so
+/// anything else must be the newer base32 scheme. Used to recompute a
+/// discriminant for verification without trusting a self-reported scheme
+/// field, so a profile can't claim the stronger scheme while actually
+/// carrying an easily-collided legacy one.
+pub fn scheme_for_suffix(suffix: &str) -> DiscriminantScheme {
+    if suffix.len() == 4 && suffix.chars().all(|c| c.is_ascii_digit()) {
+        DiscriminantScheme::Crc16
+    } else {
+        DiscriminantScheme::Sha256Base32
+    }
+}
+
+fn crc16_ccitt(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            if crc & 0x8000 != 0 {
+                crc = (crc << 1) ^ 0x1021;
+            } else {
+                crc <<= 1;
+            }
+        }
+    }
+    crc
+}
+
+/// Join a display name and discriminant into a `name#DISCRIMINANT` id.
+pub fn format_profile_id(name: &str, discriminant: &str) -> String {
+    format!("{name}#{discriminant}")
+}
+
+/// Split a `name#DISCRIMINANT` profile id back into its parts. `None` if
+/// `profile_id` has no `#` separator.
+pub fn split_profile_id(profile_id: &str) -> Option<(&str, &str)> {
+    profile_id.rsplit_once('#')
+}
+
+/// Check that `profile_id`'s discriminant suffix is actually derived from
+/// `signing`/`encryption`, so a claimed `name#DISCRIMINANT` can't be paired
+/// with keys it was never computed from. The scheme is inferred from the
+/// suffix's own shape (see [`scheme_for_suffix`]) rather than trusted from
+/// a caller-supplied field, so a profile can't claim the stronger scheme
+/// while actually carrying an easily-collided legacy one.
+///
+/// The single authoritative implementation behind
+/// [`crate::profile::PublicProfileData::validate`], and anything else
+/// that needs to confirm a discriminant against keys — URI parsing, blob
+/// import, the handshake's peer-profile check — so there's exactly one
+/// place this logic can drift.
+pub fn verify_discriminant(profile_id: &str, signing: &sig::PublicKey, encryption: &kem::PublicKey) -> Result<()> {
+    let (_, suffix) =
+        split_profile_id(profile_id).ok_or_else(|| UserError::DiscriminantMismatch { expected: String::new(), got: profile_id.to_string() })?;
+
+    let expected = discriminant(scheme_for_suffix(suffix), signing, encryption);
+    if expected != suffix {
+        return Err(UserError::DiscriminantMismatch { expected, got: suffix.to_string() }.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::EncryptionContext;
+
+    #[test]
+    fn crc16_discriminant_is_four_digits() {
+        let context = EncryptionContext::generate().unwrap();
+        let value = discriminant(DiscriminantScheme::Crc16, context.signing_public_key(), context.encryption_public_key());
+        assert_eq!(value.len(), 4);
+        assert!(value.chars().all(|c| c.is_ascii_digit()));
+        assert_eq!(scheme_for_suffix(&value), DiscriminantScheme::Crc16);
+    }
+
+    #[test]
+    fn sha256_base32_discriminant_is_recognized_as_such() {
+        let context = EncryptionContext::generate().unwrap();
+        let value = discriminant(DiscriminantScheme::Sha256Base32, context.signing_public_key(), context.encryption_public_key());
+        assert_eq!(scheme_for_suffix(&value), DiscriminantScheme::Sha256Base32);
+    }
+
+    #[test]
+    fn split_profile_id_roundtrips_format_profile_id() {
+        let id = format_profile_id("alice", "ab12cdef");
+        assert_eq!(split_profile_id(&id), Some(("alice", "ab12cdef")));
+    }
+
+    #[test]
+    fn verify_discriminant_accepts_a_profile_id_matching_its_own_keys() {
+        let context = EncryptionContext::generate().unwrap();
+        let suffix = discriminant(DiscriminantScheme::Sha256Base32, context.signing_public_key(), context.encryption_public_key());
+        let profile_id = format_profile_id("alice", &suffix);
+
+        assert!(verify_discriminant(&profile_id, context.signing_public_key(), context.encryption_public_key()).is_ok());
+    }
+
+    #[test]
+    fn verify_discriminant_rejects_swapped_keys() {
+        let alice = EncryptionContext::generate().unwrap();
+        let bob = EncryptionContext::generate().unwrap();
+        let suffix = discriminant(DiscriminantScheme::Sha256Base32, alice.signing_public_key(), alice.encryption_public_key());
+        let profile_id = format_profile_id("alice", &suffix);
+
+        let err = verify_discriminant(&profile_id, bob.signing_public_key(), bob.encryption_public_key()).unwrap_err();
+        assert!(matches!(err, crate::Error::User(crate::error::UserError::DiscriminantMismatch { .. })));
+    }
+}
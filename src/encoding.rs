@@ -0,0 +1,295 @@
+//! Canonical msgpack encoding used for the wire format.
+//!
+//! msgpack's own integer formats (fixint, uint8/16/32/64, int8/16/32/64)
+//! are always big-endian, regardless of the encoding host's native
+//! endianness — this is part of the format spec, not something
+//! `rmp_serde` or this module has to arrange. That means any integer
+//! field added to a `Serialize` struct that flows through [`to_vec`]
+//! (sequence numbers, timestamps, chunk indices, and the like) already
+//! serializes identically on a big-endian and a little-endian machine
+//! with no extra work — see the `integer_fields_are_portable_across_host_endianness`
+//! test below for byte-level proof. Where this crate encodes an integer
+//! *outside* msgpack — [`crate::framing`]'s raw length-prefix header, the
+//! session and handshake frame headers, padding's length prefix, group
+//! stream chunk headers — it's written and read with explicit
+//! `to_be_bytes`/`from_be_bytes` calls for the same reason.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::error::UserError;
+use crate::Result;
+
+pub fn to_vec<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    Ok(rmp_serde::to_vec_named(value).map_err(anyhow::Error::from)?)
+}
+
+pub fn from_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    Ok(rmp_serde::from_slice(bytes).map_err(anyhow::Error::from)?)
+}
+
+/// As [`from_slice`], but also rejects bytes left over after the msgpack
+/// value. `from_slice` alone stops reading once it has a complete value
+/// and silently ignores whatever follows, which is fine for a stream but
+/// wrong for a caller-supplied blob that's supposed to contain exactly
+/// one value (a truncated copy-paste or two concatenated blobs should be
+/// an error, not silently accepted).
+pub fn from_slice_exact<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    let mut deserializer = rmp_serde::Deserializer::new(bytes);
+    let value = T::deserialize(&mut deserializer).map_err(anyhow::Error::from)?;
+    let remaining = deserializer.get_ref().len();
+    if remaining > 0 {
+        return Err(UserError::TrailingBytes { extra: remaining }.into());
+    }
+    Ok(value)
+}
+
+/// Bounds a decode is allowed to cost before any of it is trusted enough
+/// to hand to the real deserializer. This crate only speaks msgpack on the
+/// wire today, but the type itself doesn't assume that — a future
+/// encoding can accept the same `DecodeLimits` instead of reinventing its
+/// own policy (and its own bugs) for the unbounded-decode DoS this exists
+/// to close.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    /// Reject `bytes` outright if it's longer than this, before parsing
+    /// anything.
+    pub max_bytes: usize,
+    /// Reject a value nested (an array or map containing an array or map,
+    /// and so on) deeper than this.
+    pub max_depth: usize,
+    /// Reject an array or map whose header claims more elements than
+    /// this, even if the input is too short to actually contain them.
+    pub max_collection_len: usize,
+}
+
+impl DecodeLimits {
+    /// What [`crate::framing`] and [`crate::session`] apply to everything
+    /// they read off the wire, before a signature has even been checked.
+    /// Generous enough for any message this crate itself produces; tight
+    /// enough that a peer can't use a deeply nested or wildly oversized
+    /// structure to exhaust memory or blow the stack first.
+    pub const DEFAULT: DecodeLimits = DecodeLimits { max_bytes: crate::framing::MAX_ENVELOPE_LEN, max_depth: 32, max_collection_len: 1 << 20 };
+}
+
+/// As [`from_slice`], but first walking `bytes`'s msgpack structure to
+/// check it against `limits`, without materializing any of it into `T`.
+pub fn from_slice_with_limits<T: DeserializeOwned>(bytes: &[u8], limits: &DecodeLimits) -> Result<T> {
+    check_limits(bytes, limits)?;
+    from_slice(bytes)
+}
+
+/// As [`from_slice_exact`], but first walking `bytes`'s msgpack structure
+/// to check it against `limits`, without materializing any of it into `T`.
+pub fn from_slice_exact_with_limits<T: DeserializeOwned>(bytes: &[u8], limits: &DecodeLimits) -> Result<T> {
+    check_limits(bytes, limits)?;
+    from_slice_exact(bytes)
+}
+
+fn check_limits(bytes: &[u8], limits: &DecodeLimits) -> Result<()> {
+    if bytes.len() > limits.max_bytes {
+        return Err(UserError::DecodedValueTooLarge { len: bytes.len(), max: limits.max_bytes }.into());
+    }
+    MsgpackCursor { bytes, pos: 0 }.skip_value(0, limits)
+}
+
+/// A minimal msgpack structure walker: reads just enough of each value's
+/// header to know how many bytes to skip, recursing into arrays and maps
+/// only as far as `limits.max_depth` before erroring out — so a
+/// maliciously deep input can't blow this walker's own stack either.
+struct MsgpackCursor<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> MsgpackCursor<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(n).filter(|&end| end <= self.bytes.len());
+        match end {
+            Some(end) => {
+                let slice = &self.bytes[self.pos..end];
+                self.pos = end;
+                Ok(slice)
+            }
+            None => Err(anyhow::anyhow!("msgpack value truncated").into()),
+        }
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn skip_value(&mut self, depth: usize, limits: &DecodeLimits) -> Result<()> {
+        let tag = self.byte()?;
+        match tag {
+            0x00..=0x7f | 0xe0..=0xff | 0xc0 | 0xc2 | 0xc3 => Ok(()),
+            0xcc | 0xd0 => self.take(1).map(drop),
+            0xcd | 0xd1 => self.take(2).map(drop),
+            0xce | 0xd2 | 0xca => self.take(4).map(drop),
+            0xcf | 0xd3 | 0xcb => self.take(8).map(drop),
+            0xd4 => self.take(2).map(drop),
+            0xd5 => self.take(3).map(drop),
+            0xd6 => self.take(5).map(drop),
+            0xd7 => self.take(9).map(drop),
+            0xd8 => self.take(17).map(drop),
+            0xa0..=0xbf => self.take((tag & 0x1f) as usize).map(drop),
+            0xd9 => {
+                let len = self.byte()? as usize;
+                self.take(len).map(drop)
+            }
+            0xda => {
+                let len = self.u16()? as usize;
+                self.take(len).map(drop)
+            }
+            0xdb => {
+                let len = self.u32()? as usize;
+                self.take(len).map(drop)
+            }
+            0xc4 => {
+                let len = self.byte()? as usize;
+                self.take(len).map(drop)
+            }
+            0xc5 => {
+                let len = self.u16()? as usize;
+                self.take(len).map(drop)
+            }
+            0xc6 => {
+                let len = self.u32()? as usize;
+                self.take(len).map(drop)
+            }
+            0xc7 => {
+                let len = self.byte()? as usize;
+                self.take(1)?;
+                self.take(len).map(drop)
+            }
+            0xc8 => {
+                let len = self.u16()? as usize;
+                self.take(1)?;
+                self.take(len).map(drop)
+            }
+            0xc9 => {
+                let len = self.u32()? as usize;
+                self.take(1)?;
+                self.take(len).map(drop)
+            }
+            0x90..=0x9f => self.skip_collection((tag & 0x0f) as usize, 1, depth, limits),
+            0xdc => {
+                let len = self.u16()? as usize;
+                self.skip_collection(len, 1, depth, limits)
+            }
+            0xdd => {
+                let len = self.u32()? as usize;
+                self.skip_collection(len, 1, depth, limits)
+            }
+            0x80..=0x8f => self.skip_collection((tag & 0x0f) as usize, 2, depth, limits),
+            0xde => {
+                let len = self.u16()? as usize;
+                self.skip_collection(len, 2, depth, limits)
+            }
+            0xdf => {
+                let len = self.u32()? as usize;
+                self.skip_collection(len, 2, depth, limits)
+            }
+            0xc1 => Err(anyhow::anyhow!("reserved msgpack tag 0xc1").into()),
+        }
+    }
+
+    /// `len` is the number of elements; `values_per_element` is 2 for a
+    /// map (each element is a key and a value) and 1 for an array.
+    fn skip_collection(&mut self, len: usize, values_per_element: usize, depth: usize, limits: &DecodeLimits) -> Result<()> {
+        if len > limits.max_collection_len {
+            return Err(UserError::CollectionTooLong { len, max: limits.max_collection_len }.into());
+        }
+        let depth = depth + 1;
+        if depth > limits.max_depth {
+            return Err(UserError::DecodeTooDeep { depth, max: limits.max_depth }.into());
+        }
+        for _ in 0..(len * values_per_element) {
+            self.skip_value(depth, limits)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nested_fixarrays(depth: usize) -> Vec<u8> {
+        let mut bytes = vec![0x90 | 1u8; depth]; // `depth` fixarrays of length 1, nested...
+        bytes.push(0x00); // ...bottoming out in a single fixint.
+        bytes
+    }
+
+    #[test]
+    fn from_slice_with_limits_accepts_a_value_within_every_limit() {
+        let limits = DecodeLimits { max_bytes: 1024, max_depth: 4, max_collection_len: 16 };
+        let decoded: Vec<Vec<i32>> = from_slice_with_limits(&to_vec(&vec![vec![1, 2, 3]]).unwrap(), &limits).unwrap();
+        assert_eq!(decoded, vec![vec![1, 2, 3]]);
+    }
+
+    #[test]
+    fn from_slice_with_limits_rejects_bytes_over_the_max() {
+        let limits = DecodeLimits { max_bytes: 3, max_depth: 32, max_collection_len: 1024 };
+        let err = from_slice_with_limits::<Vec<i32>>(&to_vec(&vec![1, 2, 3]).unwrap(), &limits).unwrap_err();
+        assert!(matches!(err, crate::Error::User(UserError::DecodedValueTooLarge { .. })));
+    }
+
+    #[test]
+    fn from_slice_with_limits_rejects_nesting_deeper_than_max_depth() {
+        let limits = DecodeLimits { max_bytes: 1024, max_depth: 8, max_collection_len: 1024 };
+        let err = from_slice_with_limits::<serde::de::IgnoredAny>(&nested_fixarrays(9), &limits).unwrap_err();
+        assert!(matches!(err, crate::Error::User(UserError::DecodeTooDeep { max: 8, .. })));
+    }
+
+    #[test]
+    fn from_slice_with_limits_accepts_nesting_at_exactly_max_depth() {
+        let limits = DecodeLimits { max_bytes: 1024, max_depth: 8, max_collection_len: 1024 };
+        from_slice_with_limits::<serde::de::IgnoredAny>(&nested_fixarrays(8), &limits).unwrap();
+    }
+
+    #[test]
+    fn from_slice_with_limits_rejects_a_collection_header_claiming_too_many_elements() {
+        let limits = DecodeLimits { max_bytes: 1024, max_depth: 32, max_collection_len: 4 };
+        // array32 header claiming 1,000,000 elements, without any of them
+        // actually present — the check must reject this from the header
+        // alone, since a real decode would otherwise try to read a
+        // million elements out of a few dozen input bytes.
+        let mut bytes = vec![0xdd];
+        bytes.extend_from_slice(&1_000_000u32.to_be_bytes());
+        let err = from_slice_with_limits::<serde::de::IgnoredAny>(&bytes, &limits).unwrap_err();
+        assert!(matches!(err, crate::Error::User(UserError::CollectionTooLong { len: 1_000_000, max: 4 })));
+    }
+
+    #[test]
+    fn integer_fields_are_portable_across_host_endianness() {
+        // msgpack picks the narrowest tag that fits the value, then
+        // encodes the value itself big-endian — fixed, regardless of the
+        // machine doing the encoding. Hardcoding the expected bytes here
+        // means this test would fail the moment that stopped being true
+        // on any host, big- or little-endian alike.
+        assert_eq!(to_vec(&0u64).unwrap(), vec![0x00]);
+        assert_eq!(to_vec(&127u64).unwrap(), vec![0x7f]);
+        assert_eq!(to_vec(&128u64).unwrap(), vec![0xcc, 0x80]);
+        assert_eq!(to_vec(&256u64).unwrap(), vec![0xcd, 0x01, 0x00]);
+        assert_eq!(to_vec(&65_536u64).unwrap(), vec![0xce, 0x00, 0x01, 0x00, 0x00]);
+        assert_eq!(to_vec(&4_294_967_296u64).unwrap(), vec![0xcf, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn from_slice_exact_with_limits_still_rejects_trailing_bytes() {
+        let limits = DecodeLimits::DEFAULT;
+        let mut bytes = to_vec(&42i32).unwrap();
+        bytes.push(0xc0);
+        let err = from_slice_exact_with_limits::<i32>(&bytes, &limits).unwrap_err();
+        assert!(matches!(err, crate::Error::User(UserError::TrailingBytes { .. })));
+    }
+}
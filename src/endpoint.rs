@@ -0,0 +1,272 @@
+//! Building iroh endpoints bound to a [`Profile`]'s identity, and
+//! connecting to peers through them.
+
+use crate::constants::PROTOCOL_ALPN;
+use crate::error::Error;
+use crate::profile::Profile;
+use crate::Result;
+
+/// A discovery mechanism [`Profile::make_endpoint_with_discovery`] can
+/// bring up. Each is tried independently — a device with no route to n0's
+/// discovery service can still get local-network discovery, and vice
+/// versa — rather than one failing mechanism taking the whole endpoint
+/// down with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiscoveryMechanism {
+    /// n0's hosted DNS/pkarr discovery service.
+    N0,
+    /// mDNS discovery on the local network.
+    LocalNetwork,
+}
+
+impl DiscoveryMechanism {
+    const ALL: [DiscoveryMechanism; 2] = [DiscoveryMechanism::N0, DiscoveryMechanism::LocalNetwork];
+
+    fn apply(self, builder: iroh::endpoint::Builder) -> iroh::endpoint::Builder {
+        match self {
+            DiscoveryMechanism::N0 => builder.discovery_n0(),
+            DiscoveryMechanism::LocalNetwork => builder.discovery_local_network(),
+        }
+    }
+}
+
+impl Profile {
+    /// Build an iroh endpoint advertising the production ALPN.
+    pub async fn make_endpoint(&self) -> Result<iroh::Endpoint> {
+        self.make_endpoint_with_alpn(PROTOCOL_ALPN).await
+    }
+
+    /// Build an iroh endpoint advertising `alpn` instead of the production
+    /// value. Both peers in a connection must agree on the ALPN, or the
+    /// handshake fails at the transport layer before this crate ever sees
+    /// it. Intended for integration tests (so they don't collide with real
+    /// peers) and for private deployments that want their own namespace.
+    ///
+    /// See [`Self::make_endpoint_with_discovery`] for a variant that also
+    /// reports which discovery mechanisms actually came up.
+    pub async fn make_endpoint_with_alpn(&self, alpn: &[u8]) -> Result<iroh::Endpoint> {
+        Ok(self.make_endpoint_with_discovery(alpn).await?.0)
+    }
+
+    /// As [`Self::make_endpoint_with_alpn`], but discovery is best-effort:
+    /// each known [`DiscoveryMechanism`] is probed independently (by
+    /// attempting a throwaway bind with just that mechanism enabled), the
+    /// survivors are folded into the endpoint actually returned, and only
+    /// [`Error::NoDiscoveryAvailable`] is returned if every mechanism
+    /// failed to come up. A mechanism failing to probe doesn't fail the
+    /// whole call — e.g. no route to n0's discovery service no longer
+    /// means local-network discovery is unavailable too.
+    pub async fn make_endpoint_with_discovery(&self, alpn: &[u8]) -> Result<(iroh::Endpoint, Vec<DiscoveryMechanism>)> {
+        bind_with_best_effort_discovery(alpn, &DiscoveryMechanism::ALL, |mechanism| probe(mechanism, alpn)).await
+    }
+
+    /// Build an iroh endpoint that never touches a relay: relay usage is
+    /// disabled outright, and only [`DiscoveryMechanism::LocalNetwork`] is
+    /// brought up, so nothing about this endpoint's traffic or presence
+    /// reaches n0's hosted infrastructure. Suited to LAN-only or
+    /// privacy-focused deployments where reachability outside the local
+    /// network isn't wanted. Connect through it with
+    /// [`connect_direct_only`], which rejects a peer address that's only
+    /// reachable via relay up front rather than letting the connection
+    /// attempt fail on its own.
+    pub async fn make_endpoint_direct_only(&self, alpn: &[u8]) -> Result<iroh::Endpoint> {
+        DiscoveryMechanism::LocalNetwork
+            .apply(iroh::Endpoint::builder().alpns(vec![alpn.to_vec()]).relay_mode(iroh::RelayMode::Disabled))
+            .bind()
+            .await
+            .map_err(anyhow::Error::from)
+            .map_err(Error::from)
+    }
+}
+
+/// Bind a throwaway endpoint with only `mechanism` enabled, to check
+/// whether it comes up on its own, without side effects on the real
+/// endpoint being built.
+async fn probe(mechanism: DiscoveryMechanism, alpn: &[u8]) -> bool {
+    let builder = mechanism.apply(iroh::Endpoint::builder().alpns(vec![alpn.to_vec()]));
+    match builder.bind().await {
+        Ok(endpoint) => {
+            endpoint.close().await;
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// The best-effort fallback logic itself, decoupled from actually calling
+/// into iroh so it can be exercised against a rigged `probe` that fails on
+/// command, without needing a real network condition to reproduce a
+/// discovery failure. `probe` is asked about every mechanism up front (so
+/// the report is complete even though only the survivors get bound), then
+/// the real endpoint is bound once, with just the survivors applied.
+async fn bind_with_best_effort_discovery<F, Fut>(alpn: &[u8], mechanisms: &[DiscoveryMechanism], mut probe: F) -> Result<(iroh::Endpoint, Vec<DiscoveryMechanism>)>
+where
+    F: FnMut(DiscoveryMechanism) -> Fut,
+    Fut: std::future::Future<Output = bool>,
+{
+    let mut available = Vec::new();
+    for &mechanism in mechanisms {
+        if probe(mechanism).await {
+            available.push(mechanism);
+        }
+    }
+
+    if available.is_empty() {
+        return Err(Error::NoDiscoveryAvailable);
+    }
+
+    let mut builder = iroh::Endpoint::builder().alpns(vec![alpn.to_vec()]);
+    for &mechanism in &available {
+        builder = mechanism.apply(builder);
+    }
+    let endpoint = builder.bind().await.map_err(anyhow::Error::from)?;
+    Ok((endpoint, available))
+}
+
+/// Turn a [`crate::profile::PublicProfileData::to_uri`] link into a
+/// dialable [`iroh::NodeAddr`] in one call: parses the URI, validates the
+/// embedded profile's discriminant (via
+/// [`crate::profile::PublicProfileData::from_blob`]) and its suggested
+/// relay against the profile's own signed relay list (via
+/// [`crate::profile::PublicProfileData::relay_is_trusted`]), and returns
+/// typed [`crate::error::UserError`] variants for each way the URI can be
+/// malformed rather than a generic parse failure.
+pub fn node_addr_from_uri(uri: &str) -> Result<iroh::NodeAddr> {
+    let (_profile, node_id, relay) = crate::profile::decode_uri(uri)?;
+
+    let mut addr = iroh::NodeAddr::new(node_id);
+    if let Some(relay) = relay {
+        let relay_url: iroh::RelayUrl = relay
+            .parse()
+            .map_err(|_| crate::error::UserError::InvalidResolutionUri { reason: "relay is not a valid URL".to_string() })?;
+        addr = addr.with_relay_url(relay_url);
+    }
+    Ok(addr)
+}
+
+/// Connect to `addr` over `endpoint`, advertising `alpn`.
+///
+/// iroh reports ALPN/TLS version mismatches as an opaque connection error
+/// indistinguishable from a routing or timeout failure. We can't recover
+/// the peer's advertised version from a failed handshake, but a mismatch
+/// is recognizable from the error text, so we surface it as
+/// [`Error::UnsupportedProtocolVersion`] instead of the usual
+/// [`Error::Other`] catch-all.
+pub async fn connect(endpoint: &iroh::Endpoint, addr: impl Into<iroh::NodeAddr>, alpn: &[u8]) -> Result<iroh::endpoint::Connection> {
+    endpoint.connect(addr, alpn).await.map_err(|err| map_connect_error(err, alpn))
+}
+
+/// As [`connect`], but for an endpoint built with
+/// [`Profile::make_endpoint_direct_only`]: rejects `addr` up front with
+/// [`Error::PeerRequiresRelay`] if it carries no direct addresses, since a
+/// relay-only address can never succeed once relay usage has been disabled
+/// on the endpoint, and letting the attempt run would just surface a
+/// generic, harder-to-act-on transport failure instead.
+pub async fn connect_direct_only(endpoint: &iroh::Endpoint, addr: impl Into<iroh::NodeAddr>, alpn: &[u8]) -> Result<iroh::endpoint::Connection> {
+    let addr = addr.into();
+    if addr.direct_addresses().next().is_none() {
+        return Err(Error::PeerRequiresRelay);
+    }
+    connect(endpoint, addr, alpn).await
+}
+
+fn map_connect_error(err: iroh::endpoint::ConnectError, alpn: &[u8]) -> Error {
+    if err.to_string().to_lowercase().contains("alpn") {
+        Error::UnsupportedProtocolVersion {
+            local: String::from_utf8_lossy(alpn).into_owned(),
+            remote: None,
+        }
+    } else {
+        Error::Other(anyhow::Error::from(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::ProfileBuilder;
+
+    #[tokio::test]
+    async fn disjoint_alpns_surface_as_unsupported_protocol_version() {
+        let alice = ProfileBuilder::new().name("alice").build().unwrap();
+        let bob = ProfileBuilder::new().name("bob").build().unwrap();
+
+        let alice_endpoint = alice.make_endpoint_with_alpn(b"resolution/alice-test").await.unwrap();
+        let bob_endpoint = bob.make_endpoint_with_alpn(b"resolution/bob-test").await.unwrap();
+        let bob_addr = bob_endpoint.node_addr().await.unwrap();
+
+        let result = connect(&alice_endpoint, bob_addr, b"resolution/alice-test").await;
+        assert!(matches!(result, Err(Error::UnsupportedProtocolVersion { .. })));
+    }
+
+    #[tokio::test]
+    async fn make_endpoint_with_discovery_reports_which_mechanisms_it_actually_bound() {
+        let alice = ProfileBuilder::new().name("alice").build().unwrap();
+        let (endpoint, mechanisms) = alice.make_endpoint_with_discovery(b"resolution/discovery-report-test").await.unwrap();
+        assert_eq!(mechanisms, vec![DiscoveryMechanism::N0, DiscoveryMechanism::LocalNetwork]);
+        endpoint.close().await;
+    }
+
+    #[tokio::test]
+    async fn a_failing_discovery_mechanism_doesnt_prevent_binding_with_the_rest() {
+        let (endpoint, mechanisms) = bind_with_best_effort_discovery(b"resolution/discovery-fallback-test", &DiscoveryMechanism::ALL, |mechanism| async move {
+            mechanism != DiscoveryMechanism::LocalNetwork
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(mechanisms, vec![DiscoveryMechanism::N0]);
+        endpoint.close().await;
+    }
+
+    #[tokio::test]
+    async fn every_discovery_mechanism_failing_is_reported_instead_of_silently_binding_with_none() {
+        let result = bind_with_best_effort_discovery(b"resolution/discovery-none-test", &DiscoveryMechanism::ALL, |_| async { false }).await;
+
+        assert!(matches!(result, Err(Error::NoDiscoveryAvailable)));
+    }
+
+    #[tokio::test]
+    async fn make_endpoint_direct_only_binds_with_relay_disabled() {
+        let alice = ProfileBuilder::new().name("alice").build().unwrap();
+        let endpoint = alice.make_endpoint_direct_only(b"resolution/direct-only-test").await.unwrap();
+        endpoint.close().await;
+    }
+
+    #[tokio::test]
+    async fn connect_direct_only_rejects_a_relay_only_address_up_front() {
+        let alice = ProfileBuilder::new().name("alice").build().unwrap();
+        let endpoint = alice.make_endpoint_direct_only(b"resolution/direct-only-reject-test").await.unwrap();
+
+        let node_id = iroh::SecretKey::generate(&mut rand::thread_rng()).public();
+        let relay_url: iroh::RelayUrl = "https://relay.example.com".parse().unwrap();
+        let addr = iroh::NodeAddr::new(node_id).with_relay_url(relay_url);
+
+        let result = connect_direct_only(&endpoint, addr, b"resolution/direct-only-reject-test").await;
+        assert!(matches!(result, Err(Error::PeerRequiresRelay)));
+
+        endpoint.close().await;
+    }
+
+    #[test]
+    fn node_addr_from_uri_round_trips_a_dialable_profile() {
+        let node_id = iroh::SecretKey::generate(&mut rand::thread_rng()).public();
+        let profile = ProfileBuilder::new().name("alice").node_id(node_id).relay("https://relay.example.com").build().unwrap();
+        let uri = profile.public().to_uri().unwrap();
+
+        let addr = node_addr_from_uri(&uri).unwrap();
+        assert_eq!(addr.node_id, node_id);
+    }
+
+    #[test]
+    fn node_addr_from_uri_rejects_malformed_base64() {
+        let result = node_addr_from_uri("resolution://not-valid-base64url!!");
+        assert!(matches!(result, Err(Error::User(crate::error::UserError::InvalidResolutionUri { .. }))));
+    }
+
+    #[test]
+    fn node_addr_from_uri_rejects_a_uri_without_the_scheme() {
+        let result = node_addr_from_uri("not-a-resolution-uri");
+        assert!(matches!(result, Err(Error::User(crate::error::UserError::InvalidResolutionUri { .. }))));
+    }
+}
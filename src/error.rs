@@ -0,0 +1,453 @@
+//! Crate-wide error types.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::identity::ProfileId;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Top-level error type returned by fallible crate operations.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// A caller-facing error: bad input, policy violation, or similar.
+    #[error(transparent)]
+    User(#[from] UserError),
+
+    /// Catch-all for lower-level failures (I/O, transport, crypto backend)
+    /// that don't yet have a dedicated variant.
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+
+    /// The transport-level connection attempt failed because the peer
+    /// doesn't speak a protocol version we're compatible with, rather than
+    /// for some unrelated network reason. `remote` is `None` when the peer
+    /// didn't advertise a version we could recover from the failed
+    /// handshake.
+    #[error("no compatible protocol version (we speak {local}, peer speaks {remote:?})")]
+    UnsupportedProtocolVersion { local: String, remote: Option<String> },
+
+    /// A [`crate::crypto::EncryptionContext::decrypt_direct`] call couldn't
+    /// open the envelope with the current KEM key or any retired key still
+    /// held in its rotation ring.
+    #[error("no current or retired key could decrypt this envelope")]
+    NoMatchingKey,
+
+    /// [`crate::crypto::EncryptionContext::generate_with`] was asked for an
+    /// algorithm whose cargo feature isn't compiled into this build.
+    #[error("algorithm {0} is not enabled for this build")]
+    AlgorithmNotEnabled(&'static str),
+
+    /// A signature genuinely failed to verify against the claimed signer,
+    /// as opposed to a lower-level crypto backend failure (bad key
+    /// encoding, algorithm init failure, and the like, which stay
+    /// [`Error::Other`]). Distinguished so callers can show a plain
+    /// "message signature invalid" instead of a generic internal error.
+    #[error("signature does not verify for signer {signer_fingerprint}")]
+    SignatureInvalid { signer_fingerprint: String },
+
+    /// [`crate::session::handshake::handshake`] didn't complete the
+    /// profile exchange before its deadline elapsed or its
+    /// `CancellationToken` fired. The underlying connection has already
+    /// been closed.
+    #[error("handshake did not complete before its timeout or cancellation")]
+    HandshakeTimeout,
+
+    /// [`crate::crypto::EncryptionContext::decrypt_group_checked`] saw a
+    /// `(key_id, nonce)` pair its [`crate::crypto::NonceTracker`] had
+    /// already recorded. Detected after the fact: whatever GCM
+    /// confidentiality loss the first reuse caused already happened by
+    /// the time the second envelope arrives.
+    #[error("nonce reused under group key {key_id}")]
+    NonceReuse { key_id: Uuid },
+
+    /// [`crate::crypto::check_entropy`] found the system RNG's output
+    /// failing a basic sanity check, most likely because it's still
+    /// drawing from an under-seeded pool (a freshly booted or embedded
+    /// system). Returned by `check_entropy` itself and by
+    /// [`crate::crypto::EncryptionContext::generate_checked`];
+    /// [`crate::crypto::EncryptionContext::generate`] never runs the check.
+    #[error("system entropy source failed a basic sanity check")]
+    InsufficientEntropy,
+
+    /// [`crate::session::handshake::handshake`]'s post-handshake capability
+    /// confirmation didn't match: the peer's signed hash of the
+    /// capabilities it originally advertised doesn't correspond to what
+    /// this side actually received during negotiation. Either the
+    /// capability exchange was tampered with in transit (an attempted
+    /// downgrade to a weaker suite) or something has gone wrong locally —
+    /// either way, the handshake aborts rather than proceeding on
+    /// unverified capabilities.
+    #[error("peer's confirmed capabilities don't match what was received during negotiation")]
+    DowngradeDetected,
+
+    /// [`crate::crypto::verify_chain`] found a message in the sequence
+    /// whose `prev_hash` doesn't match the hash of the message before it —
+    /// the conversation was reordered, had a message removed, or had one
+    /// inserted. `index` is the position of the first message that fails
+    /// to link up.
+    #[error("group message chain broken at index {index}")]
+    ChainBroken { index: usize },
+
+    /// [`crate::session::handshake::handshake`]'s closing transcript
+    /// signature didn't verify: the peer's signed hash of every byte
+    /// exchanged during the handshake doesn't match what this side
+    /// actually sent and received. Unlike [`Error::DowngradeDetected`],
+    /// which only covers the capability advertisement, this catches
+    /// tampering with *any* handshake message (profile exchange included).
+    #[error("peer's handshake transcript signature does not match the messages actually exchanged")]
+    HandshakeTampered,
+
+    /// [`crate::profile::Profile::make_endpoint_with_alpn`] tried every discovery
+    /// mechanism it knows about and none of them came up (e.g. no network
+    /// reachable for local discovery, and n0's discovery service is also
+    /// unreachable). The endpoint itself never got bound.
+    #[error("no discovery mechanism could be brought up")]
+    NoDiscoveryAvailable,
+
+    /// [`crate::session::Session::reverify`] was called with
+    /// `user_confirmed: false`: the user declined to confirm the peer's
+    /// new keys, so the session was closed rather than left trusting
+    /// them.
+    #[error("re-verification declined; session closed")]
+    ReverificationDeclined,
+
+    /// Reserved for a future, independent check that an envelope was
+    /// sealed under the expected conversation id, distinguishable from a
+    /// forged signature or tampered ciphertext. Not emitted today:
+    /// [`crate::crypto::Conversation::decrypt`] folds the conversation id
+    /// into the same signed bytes as the rest of the envelope, so a wrong
+    /// conversation id and a forged signature both surface as
+    /// [`Error::SignatureInvalid`] and can't be told apart without a wire
+    /// format change.
+    #[error("envelope does not belong to this conversation")]
+    WrongConversation,
+
+    /// [`crate::endpoint::connect_direct_only`] was given a
+    /// [`iroh::NodeAddr`] with no direct addresses, only a relay url —
+    /// unreachable from an endpoint built with
+    /// [`crate::profile::Profile::make_endpoint_direct_only`], which never brings up
+    /// a relay.
+    #[error("peer is only reachable via relay, but this endpoint is direct-only")]
+    PeerRequiresRelay,
+
+    /// [`crate::receipt::Receipt::verify`] found a signature that verifies
+    /// fine, but names a different `message_id` than the one the caller is
+    /// checking for — a genuine receipt, just not for this message.
+    #[error("receipt is for message {got}, expected {expected}")]
+    ReceiptMessageMismatch { expected: crate::identity::MessageId, got: crate::identity::MessageId },
+}
+
+impl Error {
+    /// Look inside an [`Error::Other`] for a concrete underlying error
+    /// type, e.g. a transport error this crate doesn't have a dedicated
+    /// variant for. Returns `None` for every other variant, or if the
+    /// wrapped error isn't a `T`.
+    pub fn downcast_ref<T: std::error::Error + Send + Sync + 'static>(&self) -> Option<&T> {
+        match self {
+            Error::Other(err) => err.downcast_ref::<T>(),
+            _ => None,
+        }
+    }
+
+    /// Best-effort check for a timeout. Based on the error's display text
+    /// rather than a typed variant, since the transport errors this
+    /// usually wraps (iroh, QUIC) don't expose one we can match on here —
+    /// same caveat as [`crate::endpoint::connect`]'s ALPN-mismatch
+    /// detection.
+    pub fn is_timeout(&self) -> bool {
+        self.message_contains("timed out") || self.message_contains("timeout")
+    }
+
+    /// Best-effort check for a connection-level failure (refused, reset,
+    /// closed, unreachable), same caveat as [`Self::is_timeout`].
+    pub fn is_connection_error(&self) -> bool {
+        ["connection refused", "connection reset", "connection closed", "unreachable", "broken pipe"]
+            .iter()
+            .any(|needle| self.message_contains(needle))
+    }
+
+    fn message_contains(&self, needle: &str) -> bool {
+        self.to_string().to_lowercase().contains(needle)
+    }
+
+    /// A coarse HTTP-ish status code for services (gateways, metrics
+    /// dashboards) that want to bucket errors without matching on every
+    /// variant themselves. Purely advisory — nothing in this crate is an
+    /// HTTP server, so treat this as a suggestion for callers that are.
+    pub fn status_code(&self) -> u16 {
+        match self {
+            Error::User(inner) => inner.status_code(),
+            Error::SignatureInvalid { .. } => 401,
+            Error::NoMatchingKey => 401,
+            Error::UnsupportedProtocolVersion { .. } => 503,
+            Error::AlgorithmNotEnabled(_) => 500,
+            Error::HandshakeTimeout => 408,
+            Error::NonceReuse { .. } => 409,
+            Error::InsufficientEntropy => 503,
+            Error::DowngradeDetected => 401,
+            Error::ChainBroken { .. } => 409,
+            Error::HandshakeTampered => 401,
+            Error::NoDiscoveryAvailable => 503,
+            Error::ReverificationDeclined => 401,
+            Error::WrongConversation => 401,
+            Error::PeerRequiresRelay => 503,
+            Error::ReceiptMessageMismatch { .. } => 400,
+            Error::Other(_) if self.is_timeout() || self.is_connection_error() => 503,
+            Error::Other(_) => 500,
+        }
+    }
+
+    /// The standard HTTP reason phrase for [`Self::status_code`].
+    pub fn status_reason(&self) -> &'static str {
+        match self.status_code() {
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            408 => "Request Timeout",
+            409 => "Conflict",
+            413 => "Payload Too Large",
+            503 => "Service Unavailable",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+/// Errors caused by how the crate was called, as opposed to internal or
+/// transport failures. Kept separate so they can eventually be reported
+/// back to a peer without leaking internal detail. `Serialize`/`Deserialize`
+/// so a variant like [`UserError::UnknownAlgorithm`] can travel over the
+/// wire as a rejection reason instead of just being logged locally.
+#[derive(Debug, Error, Serialize, Deserialize)]
+pub enum UserError {
+    /// A fixed-length field (e.g. a [`crate::crypto::SharedSecret`]) was
+    /// constructed from the wrong number of bytes.
+    #[error("expected {expected} bytes, got {got}")]
+    BadLength { expected: usize, got: usize },
+
+    /// A `content_type` string exceeded [`crate::constants::MAX_CONTENT_TYPE_LEN`].
+    #[error("content type is {len} bytes, max is {max}")]
+    ContentTypeTooLong { len: usize, max: usize },
+
+    /// A deserialized profile's `version` is newer than this build
+    /// understands.
+    #[error("profile version {got} is newer than the {max_understood} this build understands")]
+    UnsupportedProfileVersion { got: u16, max_understood: u16 },
+
+    /// A [`crate::profile::SignedProfile`] was verified after its
+    /// `valid_until` (plus clock-skew tolerance) had passed.
+    #[error("profile expired at unix time {valid_until}")]
+    ProfileExpired { valid_until: u64 },
+
+    /// A peer advertised, or an envelope referenced, a crypto suite or
+    /// algorithm name this build doesn't recognize. Returned instead of
+    /// panicking or silently falling back to the crate defaults.
+    #[error("unknown algorithm: {name}")]
+    UnknownAlgorithm { name: String },
+
+    /// [`crate::crypto::EncryptionContext::decrypt_group_any`] was given a
+    /// candidate key set that didn't include the envelope's `key_id`.
+    #[error("no candidate key matches group key id {key_id}")]
+    UnknownGroupKey { key_id: Uuid },
+
+    /// [`crate::crypto::EncryptionContext::encrypt_group`] was called with
+    /// an empty target set, almost always a caller bug: the resulting
+    /// envelope would be addressed to nobody.
+    #[error("encrypt_group called with no recipients")]
+    NoRecipients,
+
+    /// [`crate::profile::PublicProfileData::validate`] recomputed a
+    /// profile's discriminant from its key material and it didn't match
+    /// the suffix carried in `profile_id`.
+    #[error("profile id discriminant mismatch: expected {expected}, got {got}")]
+    DiscriminantMismatch { expected: String, got: String },
+
+    /// [`crate::framing::write_envelope`] or [`crate::framing::read_envelope`]
+    /// encountered an envelope larger than [`crate::framing::MAX_ENVELOPE_LEN`].
+    #[error("envelope is {len} bytes, max is {max}")]
+    EnvelopeTooLarge { len: usize, max: usize },
+
+    /// [`crate::encoding::from_slice_exact`] decoded a complete msgpack
+    /// value but `extra` bytes were left over afterward, e.g. a truncated
+    /// paste of two concatenated blobs.
+    #[error("{extra} trailing byte(s) after the msgpack value")]
+    TrailingBytes { extra: usize },
+
+    /// [`crate::crypto::EncryptionContext::encrypt_group`] was given more
+    /// targets than `max` allows. See
+    /// [`crate::constants::MAX_GROUP_RECIPIENTS`].
+    #[error("{got} recipients exceeds the limit of {max}")]
+    TooManyRecipients { got: usize, max: usize },
+
+    /// A decoded AEAD nonce didn't have the length the selected suite
+    /// requires. Every suite this build supports uses a 12-byte nonce
+    /// today, but the check is against the suite's own
+    /// [`crate::crypto::EncryptionContext::nonce_len`] rather than a
+    /// hardcoded `12`, so a future suite with a different nonce size (e.g.
+    /// XChaCha20's 24 bytes) doesn't have to touch this variant.
+    #[error("expected a {expected}-byte nonce, got {got}")]
+    BadNonceLength { expected: usize, got: usize },
+
+    /// A [`crate::encoding::DecodeLimits`]-bounded decode saw more bytes
+    /// than [`crate::encoding::DecodeLimits::max_bytes`] allows, before
+    /// attempting to parse any of it.
+    #[error("encoded value is {len} bytes, max is {max}")]
+    DecodedValueTooLarge { len: usize, max: usize },
+
+    /// A [`crate::encoding::DecodeLimits`]-bounded decode found nesting
+    /// (arrays/maps containing arrays/maps, and so on) deeper than
+    /// [`crate::encoding::DecodeLimits::max_depth`] allows.
+    #[error("nested {depth} levels deep, max is {max}")]
+    DecodeTooDeep { depth: usize, max: usize },
+
+    /// A [`crate::encoding::DecodeLimits`]-bounded decode found an array
+    /// or map claiming more elements than
+    /// [`crate::encoding::DecodeLimits::max_collection_len`] allows.
+    #[error("collection has {len} elements, max is {max}")]
+    CollectionTooLong { len: usize, max: usize },
+
+    /// [`crate::crypto::verify_threshold`] found the same admin's
+    /// signature counted more than once in a [`crate::crypto::MultiSig`].
+    #[error("profile {profile_id} signed more than once")]
+    DuplicateSigner { profile_id: ProfileId },
+
+    /// [`crate::crypto::verify_threshold`] found fewer distinct valid
+    /// signatures than the required threshold.
+    #[error("{got} valid signature(s), threshold is {threshold}")]
+    BelowSignatureThreshold { got: usize, threshold: usize },
+
+    /// [`crate::profile::PublicProfileData::to_uri`] was called on a
+    /// profile with no dialable node id: neither its own `node_id` nor any
+    /// announced, verified device.
+    #[error("profile has no dialable node id to encode into a resolution:// URI")]
+    NoDialableNodeId,
+
+    /// [`crate::endpoint::node_addr_from_uri`] couldn't parse `uri` as a
+    /// well-formed `resolution://` link. `reason` describes which part
+    /// failed.
+    #[error("malformed resolution:// URI: {reason}")]
+    InvalidResolutionUri { reason: String },
+
+    /// [`crate::endpoint::node_addr_from_uri`] found a suggested relay in
+    /// the URI that the embedded profile never signed — e.g. an attacker
+    /// splicing in a relay they control while leaving the rest of the URI's
+    /// payload untouched.
+    #[error("relay {relay} is not one of this profile's signed relays")]
+    UntrustedRelay { relay: String },
+
+    /// [`crate::store::FilesystemStore`] was given a key that isn't safe to
+    /// join onto its directory as a single filename — containing a path
+    /// separator, or equal to `.`/`..`. Keys reach a store from wire input
+    /// (e.g. a [`crate::identity::ProfileId`]'s string form), so this is
+    /// rejected rather than silently sanitized.
+    #[error("store key {key:?} is not a valid filename")]
+    InvalidStoreKey { key: String },
+
+    /// [`crate::crypto::EncryptionContext::decrypt_group_stream`] or
+    /// [`crate::crypto::EncryptionContext::decrypt_group_stream_verified`]
+    /// read a chunk length prefix bigger than a legitimate sender (bounded
+    /// to [`crate::constants::GROUP_STREAM_CHUNK_LEN`] plus AEAD overhead
+    /// by [`crate::crypto::EncryptionContext::encrypt_group_body_stream`])
+    /// would ever emit. Rejected before allocating a buffer for it, so a
+    /// claimed length can't be used to force an outsized allocation.
+    #[error("group stream chunk is {len} bytes, max is {max}")]
+    GroupStreamChunkTooLarge { len: usize, max: usize },
+
+    /// [`crate::crypto::compression::unwrap`] inflated a compressed
+    /// payload past `max` bytes without hitting the end of it. Compressed
+    /// input is attacker-controlled (it arrives inside a decrypted
+    /// envelope), so decompression is capped the same way any other
+    /// envelope payload is — without a cap, a small compressed blob could
+    /// inflate to an arbitrarily large allocation, a classic
+    /// decompression bomb.
+    #[error("decompressed value is over {max} bytes")]
+    DecompressedValueTooLarge { max: usize },
+}
+
+impl UserError {
+    /// See [`Error::status_code`]. Most `UserError`s are a plain 400, but
+    /// a couple of variants map more specifically.
+    fn status_code(&self) -> u16 {
+        match self {
+            UserError::ContentTypeTooLong { .. } | UserError::EnvelopeTooLarge { .. } => 413,
+            UserError::ProfileExpired { .. } => 401,
+            UserError::BadLength { .. }
+            | UserError::UnsupportedProfileVersion { .. }
+            | UserError::UnknownAlgorithm { .. }
+            | UserError::UnknownGroupKey { .. }
+            | UserError::NoRecipients
+            | UserError::DiscriminantMismatch { .. }
+            | UserError::TrailingBytes { .. }
+            | UserError::TooManyRecipients { .. }
+            | UserError::BadNonceLength { .. }
+            | UserError::DecodeTooDeep { .. }
+            | UserError::CollectionTooLong { .. }
+            | UserError::DuplicateSigner { .. }
+            | UserError::BelowSignatureThreshold { .. }
+            | UserError::NoDialableNodeId
+            | UserError::InvalidResolutionUri { .. }
+            | UserError::UntrustedRelay { .. }
+            | UserError::InvalidStoreKey { .. }
+            | UserError::GroupStreamChunkTooLarge { .. } => 400,
+            UserError::DecodedValueTooLarge { .. } | UserError::DecompressedValueTooLarge { .. } => 413,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    fn wrapped_io_error(kind: io::ErrorKind, message: &str) -> Error {
+        Error::Other(anyhow::Error::from(io::Error::new(kind, message)))
+    }
+
+    #[test]
+    fn downcast_ref_recovers_the_wrapped_error_type() {
+        let err = wrapped_io_error(io::ErrorKind::TimedOut, "deadline exceeded");
+        let io_err = err.downcast_ref::<io::Error>().expect("should downcast to io::Error");
+        assert_eq!(io_err.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn downcast_ref_returns_none_for_the_wrong_type_or_variant() {
+        let err = wrapped_io_error(io::ErrorKind::TimedOut, "deadline exceeded");
+        assert!(err.downcast_ref::<std::fmt::Error>().is_none());
+
+        let typed = Error::NoMatchingKey;
+        assert!(typed.downcast_ref::<io::Error>().is_none());
+    }
+
+    #[test]
+    fn is_timeout_matches_timeout_flavored_messages() {
+        assert!(wrapped_io_error(io::ErrorKind::TimedOut, "operation timed out").is_timeout());
+        assert!(!wrapped_io_error(io::ErrorKind::TimedOut, "operation timed out").is_connection_error());
+    }
+
+    #[test]
+    fn is_connection_error_matches_connection_flavored_messages() {
+        assert!(wrapped_io_error(io::ErrorKind::ConnectionReset, "connection reset by peer").is_connection_error());
+        assert!(!wrapped_io_error(io::ErrorKind::ConnectionReset, "connection reset by peer").is_timeout());
+    }
+
+    #[test]
+    fn status_code_buckets_each_error_category() {
+        assert_eq!(Error::from(UserError::BadLength { expected: 32, got: 1 }).status_code(), 400);
+        assert_eq!(Error::from(UserError::ContentTypeTooLong { len: 300, max: 255 }).status_code(), 413);
+        assert_eq!(Error::from(UserError::ProfileExpired { valid_until: 0 }).status_code(), 401);
+        assert_eq!(Error::SignatureInvalid { signer_fingerprint: "abcd".into() }.status_code(), 401);
+        assert_eq!(Error::NoMatchingKey.status_code(), 401);
+        assert_eq!(Error::UnsupportedProtocolVersion { local: "1".into(), remote: None }.status_code(), 503);
+        assert_eq!(Error::AlgorithmNotEnabled("mlkem1024").status_code(), 500);
+        assert_eq!(wrapped_io_error(io::ErrorKind::TimedOut, "operation timed out").status_code(), 503);
+        assert_eq!(wrapped_io_error(io::ErrorKind::Other, "unexpected backend failure").status_code(), 500);
+    }
+
+    #[test]
+    fn status_reason_matches_status_code() {
+        assert_eq!(Error::NoMatchingKey.status_reason(), "Unauthorized");
+        assert_eq!(Error::from(UserError::ContentTypeTooLong { len: 300, max: 255 }).status_reason(), "Payload Too Large");
+        assert_eq!(Error::AlgorithmNotEnabled("mlkem1024").status_reason(), "Internal Server Error");
+    }
+}
@@ -0,0 +1,77 @@
+//! Async length-prefixed framing for reading and writing envelopes
+//! directly on a raw stream, for callers that aren't (yet) using the full
+//! [`crate::session::Session`] abstraction. This is the same wire framing
+//! [`crate::session`] and the handshake's profile exchange each hand-roll
+//! for their own message types, pulled out once so callers working
+//! directly with [`crate::crypto::SingleEncryption`] don't have to.
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::crypto::SingleEncryption;
+use crate::error::UserError;
+use crate::Result;
+
+/// Largest encoded envelope [`read_envelope`] will accept. Enforced on
+/// both sides: [`write_envelope`] refuses to send anything bigger, and
+/// [`read_envelope`] rejects an oversized length prefix before allocating
+/// a buffer for it, so a peer can't force an unbounded allocation just by
+/// lying about a length.
+pub const MAX_ENVELOPE_LEN: usize = 16 * 1024 * 1024;
+
+/// Write `env`'s msgpack encoding to `stream`, length-prefixed with a
+/// big-endian `u32`.
+pub async fn write_envelope<W: AsyncWrite + Unpin>(stream: &mut W, env: &SingleEncryption) -> Result<()> {
+    let bytes = crate::encoding::to_vec(env)?;
+    if bytes.len() > MAX_ENVELOPE_LEN {
+        return Err(UserError::EnvelopeTooLarge { len: bytes.len(), max: MAX_ENVELOPE_LEN }.into());
+    }
+    stream.write_all(&(bytes.len() as u32).to_be_bytes()).await.map_err(anyhow::Error::from)?;
+    stream.write_all(&bytes).await.map_err(anyhow::Error::from)?;
+    Ok(())
+}
+
+/// Read one length-prefixed, msgpack-encoded envelope from `stream`.
+pub async fn read_envelope<R: AsyncRead + Unpin>(stream: &mut R) -> Result<SingleEncryption> {
+    let mut len_buf = [0u8; 4];
+    stream.read_exact(&mut len_buf).await.map_err(anyhow::Error::from)?;
+    let len = u32::from_be_bytes(len_buf) as usize;
+    if len > MAX_ENVELOPE_LEN {
+        return Err(UserError::EnvelopeTooLarge { len, max: MAX_ENVELOPE_LEN }.into());
+    }
+    let mut buf = vec![0u8; len];
+    stream.read_exact(&mut buf).await.map_err(anyhow::Error::from)?;
+    crate::encoding::from_slice_with_limits(&buf, &crate::encoding::DecodeLimits::DEFAULT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::EncryptionContext;
+
+    #[tokio::test]
+    async fn write_then_read_round_trips_an_envelope() {
+        let sender = EncryptionContext::generate().unwrap();
+        let receiver = EncryptionContext::generate().unwrap();
+        let envelope = sender.encrypt_direct(receiver.encryption_public_key().clone(), b"hi".to_vec()).unwrap();
+
+        let mut buf = Vec::new();
+        write_envelope(&mut buf, &envelope).await.unwrap();
+
+        let mut cursor = std::io::Cursor::new(buf);
+        let read_back = read_envelope(&mut cursor).await.unwrap();
+
+        assert_eq!(read_back.kem_ciphertext, envelope.kem_ciphertext);
+        assert_eq!(read_back.payload, envelope.payload);
+        assert_eq!(read_back.signature, envelope.signature);
+    }
+
+    #[tokio::test]
+    async fn read_envelope_rejects_a_length_prefix_over_the_max() {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&((MAX_ENVELOPE_LEN + 1) as u32).to_be_bytes());
+        let mut cursor = std::io::Cursor::new(buf);
+
+        let err = read_envelope(&mut cursor).await.unwrap_err();
+        assert!(matches!(err, crate::Error::User(UserError::EnvelopeTooLarge { .. })));
+    }
+}
@@ -0,0 +1,129 @@
+//! Core identifier types shared across the crate.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// A stable identifier for a published profile, of the form
+/// `name#DISCRIMINANT`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ProfileId(String);
+
+impl ProfileId {
+    pub fn new(raw: impl Into<String>) -> Self {
+        Self(raw.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for ProfileId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<String> for ProfileId {
+    fn from(raw: String) -> Self {
+        Self(raw)
+    }
+}
+
+impl From<&str> for ProfileId {
+    fn from(raw: &str) -> Self {
+        Self(raw.to_string())
+    }
+}
+
+/// A fixed-size, hash-derived stand-in for a [`ProfileId`], for callers
+/// that want a cheap `HashMap` key or wire field where the full
+/// `name#DISCRIMINANT` string would be needlessly bulky — e.g.
+/// [`crate::roster::Roster`] or [`crate::session::SessionPool`]'s session
+/// cache. Derived by SHA-256-hashing the profile_id's string form and
+/// keeping the first 16 bytes.
+///
+/// Collisions are only a theoretical concern — a birthday bound around
+/// 2^64 distinct profile_ids before even a 50% chance of one — not
+/// something this crate defends against. Treat it as an efficient index,
+/// not a security boundary; anywhere a mismatch would matter (signing,
+/// verification, pinning), use the full [`ProfileId`] instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CompactProfileId([u8; 16]);
+
+impl From<&ProfileId> for CompactProfileId {
+    fn from(id: &ProfileId) -> Self {
+        let digest = Sha256::digest(id.as_str().as_bytes());
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(&digest[..16]);
+        Self(bytes)
+    }
+}
+
+/// Shows only the first 4 bytes as hex — enough to eyeball in a log line
+/// without printing the full 16-byte digest.
+impl fmt::Display for CompactProfileId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.0[..4] {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A content-addressed identifier for an encrypted envelope, derived from
+/// its ciphertext bytes rather than its plaintext.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MessageId(pub [u8; 16]);
+
+impl fmt::Display for MessageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn distinct_profile_ids_map_to_distinct_compact_ids() {
+        let ids: Vec<ProfileId> = (0..1000).map(|i| ProfileId::new(format!("user-{i}#0000"))).collect();
+        let compact: HashSet<CompactProfileId> = ids.iter().map(CompactProfileId::from).collect();
+        assert_eq!(compact.len(), ids.len());
+    }
+
+    #[test]
+    fn the_same_profile_id_always_maps_to_the_same_compact_id() {
+        let id = ProfileId::new("alice#1234");
+        assert_eq!(CompactProfileId::from(&id), CompactProfileId::from(&id));
+    }
+
+    #[test]
+    fn usable_as_a_hashmap_key() {
+        use std::collections::HashMap;
+
+        let alice = ProfileId::new("alice#1234");
+        let bob = ProfileId::new("bob#5678");
+
+        let mut map = HashMap::new();
+        map.insert(CompactProfileId::from(&alice), "alice's session");
+        map.insert(CompactProfileId::from(&bob), "bob's session");
+
+        assert_eq!(map.get(&CompactProfileId::from(&alice)), Some(&"alice's session"));
+        assert_eq!(map.get(&CompactProfileId::from(&bob)), Some(&"bob's session"));
+    }
+
+    #[test]
+    fn display_shows_a_short_hex_prefix() {
+        let id = ProfileId::new("alice#1234");
+        let compact = CompactProfileId::from(&id);
+        assert_eq!(compact.to_string().len(), 8);
+    }
+}
@@ -0,0 +1,53 @@
+//! Resolution protocol: end-to-end encrypted messaging over iroh.
+//!
+//! See `specification/SPEC.md` for the wire-level design this crate
+//! implements.
+//!
+//! The `transport` feature (on by default) gates the modules that pull in
+//! iroh's networking stack — [`endpoint`], [`session`], [`presence`], and
+//! [`transfer`] (chunk sizing for transfers over a [`session::Session`]).
+//! Disabling it is meant to leave a lean crate exposing just [`crypto`],
+//! [`encoding`], [`error`], and the identifier types in [`identity`], for
+//! consumers that only want the encrypt/decrypt/sign primitives without
+//! iroh's dependency tree. [`profile`] still depends on `iroh::NodeId` for
+//! `PublicProfileData`'s dialable device addresses, so it isn't gated —
+//! fully decoupling identity from a transport-specific node id would be a
+//! larger refactor of its own, tracked separately from this pass. This
+//! checkout has no `Cargo.toml`, so there's no `[features]` table to
+//! declare `transport` in and no way to actually build with
+//! `--no-default-features` here — the `#[cfg(feature = "transport")]`
+//! gates below are written the same way [`crypto::KemChoice`]'s
+//! algorithm-selection gates are: correct once a real manifest defines the
+//! feature. In this tree today nothing enables `transport`, so the modules
+//! it gates are left out of the build.
+
+pub mod attachment;
+pub mod audit;
+pub mod clock;
+pub mod constants;
+pub mod crypto;
+pub mod discriminant;
+pub mod encoding;
+#[cfg(feature = "transport")]
+pub mod endpoint;
+pub mod error;
+pub mod framing;
+pub mod identity;
+pub mod message;
+pub mod pinning;
+#[cfg(feature = "transport")]
+pub mod presence;
+pub mod profile;
+pub mod queue;
+pub mod receipt;
+pub mod revocation;
+pub mod roster;
+pub mod runtime;
+#[cfg(feature = "transport")]
+pub mod session;
+pub mod store;
+#[cfg(feature = "transport")]
+pub mod transfer;
+
+pub use error::{Error, Result};
+pub use identity::{MessageId, ProfileId};
@@ -0,0 +1,176 @@
+//! The `Message` enum: everything that can flow over an established
+//! [`crate::session::Session`].
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::crypto::SingleEncryption;
+use crate::profile::SignedProfile;
+
+/// A message sent over a session's encrypted channel.
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// An application-level chat payload.
+    Chat { body: Vec<u8> },
+
+    /// Invites the receiver into a group by sealing the group's key to
+    /// their encryption key. See [`crate::session::Session::invite_to_group`].
+    GroupInvite {
+        conversation_id: Uuid,
+        sealed_key: SingleEncryption,
+    },
+
+    /// The sender's current signed profile, sent unprompted so the
+    /// receiver's roster can pick up a display-name change or a key
+    /// rotation without waiting for the next handshake. See
+    /// [`crate::session::Session::announce_profile`]. Still just a claim
+    /// until the receiver calls [`SignedProfile::verify`] on it.
+    ProfileAnnouncement(SignedProfile),
+
+    /// A message whose tag this build doesn't recognize, most likely
+    /// because a newer peer sent a variant added after this build shipped.
+    /// Carried as its raw encoded payload rather than failing the whole
+    /// decode, so a stream can keep flowing and the app can log or ignore
+    /// it as it sees fit. `bytes` is exactly what [`Self::deserialize`]
+    /// found under `tag` — nothing about it has been re-encoded or
+    /// altered, so anything that signs or verifies over the wire bytes of
+    /// a `Message` still covers an `Unknown` one the same as any other.
+    Unknown { tag: u8, bytes: Vec<u8> },
+}
+
+const TAG_CHAT: u8 = 0;
+const TAG_GROUP_INVITE: u8 = 1;
+const TAG_PROFILE_ANNOUNCEMENT: u8 = 2;
+
+/// Wire representation of [`Message`]: an explicit numeric tag plus the
+/// variant's own msgpack-encoded fields, rather than serde's usual
+/// name-tagged enum encoding. Doing the tagging ourselves is what lets an
+/// unrecognized `tag` fall through to [`Message::Unknown`] instead of
+/// failing deserialization outright.
+#[derive(Serialize, Deserialize)]
+struct MessageWire {
+    tag: u8,
+    body: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ChatBody {
+    body: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GroupInviteBody {
+    conversation_id: Uuid,
+    sealed_key: SingleEncryption,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ProfileAnnouncementBody {
+    signed: SignedProfile,
+}
+
+impl Serialize for Message {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let wire = match self {
+            Message::Chat { body } => MessageWire {
+                tag: TAG_CHAT,
+                body: crate::encoding::to_vec(&ChatBody { body: body.clone() }).map_err(serde::ser::Error::custom)?,
+            },
+            Message::GroupInvite { conversation_id, sealed_key } => MessageWire {
+                tag: TAG_GROUP_INVITE,
+                body: crate::encoding::to_vec(&GroupInviteBody { conversation_id: *conversation_id, sealed_key: sealed_key.clone() })
+                    .map_err(serde::ser::Error::custom)?,
+            },
+            Message::ProfileAnnouncement(signed) => MessageWire {
+                tag: TAG_PROFILE_ANNOUNCEMENT,
+                body: crate::encoding::to_vec(&ProfileAnnouncementBody { signed: signed.clone() }).map_err(serde::ser::Error::custom)?,
+            },
+            Message::Unknown { tag, bytes } => MessageWire { tag: *tag, body: bytes.clone() },
+        };
+        wire.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Message {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let wire = MessageWire::deserialize(deserializer)?;
+        Ok(match wire.tag {
+            TAG_CHAT => {
+                let ChatBody { body } = crate::encoding::from_slice_with_limits(&wire.body, &crate::encoding::DecodeLimits::DEFAULT).map_err(serde::de::Error::custom)?;
+                Message::Chat { body }
+            }
+            TAG_GROUP_INVITE => {
+                let GroupInviteBody { conversation_id, sealed_key } =
+                    crate::encoding::from_slice_with_limits(&wire.body, &crate::encoding::DecodeLimits::DEFAULT).map_err(serde::de::Error::custom)?;
+                Message::GroupInvite { conversation_id, sealed_key }
+            }
+            TAG_PROFILE_ANNOUNCEMENT => {
+                let ProfileAnnouncementBody { signed } =
+                    crate::encoding::from_slice_with_limits(&wire.body, &crate::encoding::DecodeLimits::DEFAULT).map_err(serde::de::Error::custom)?;
+                Message::ProfileAnnouncement(signed)
+            }
+            tag => Message::Unknown { tag, bytes: wire.body },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chat_round_trips_through_encoding() {
+        let message = Message::Chat { body: b"hello".to_vec() };
+        let bytes = crate::encoding::to_vec(&message).unwrap();
+        let decoded: Message = crate::encoding::from_slice(&bytes).unwrap();
+        match decoded {
+            Message::Chat { body } => assert_eq!(body, b"hello"),
+            other => panic!("expected Chat, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn profile_announcement_round_trips_through_encoding() {
+        use crate::profile::ProfileBuilder;
+
+        let alice = ProfileBuilder::new().name("alice").build().unwrap();
+        let signed = alice.signed_public_profile().unwrap();
+        let message = Message::ProfileAnnouncement(signed);
+
+        let bytes = crate::encoding::to_vec(&message).unwrap();
+        let decoded: Message = crate::encoding::from_slice(&bytes).unwrap();
+        match decoded {
+            Message::ProfileAnnouncement(signed) => assert!(signed.verify(false).is_ok()),
+            other => panic!("expected ProfileAnnouncement, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn an_unrecognized_tag_decodes_to_unknown_instead_of_failing() {
+        let future = MessageWire { tag: 200, body: vec![1, 2, 3] };
+        let bytes = crate::encoding::to_vec(&future).unwrap();
+
+        let decoded: Message = crate::encoding::from_slice(&bytes).unwrap();
+        match decoded {
+            Message::Unknown { tag, bytes } => {
+                assert_eq!(tag, 200);
+                assert_eq!(bytes, vec![1, 2, 3]);
+            }
+            other => panic!("expected Unknown, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn unknown_re_encodes_its_raw_bytes_unchanged() {
+        let message = Message::Unknown { tag: 200, bytes: vec![9, 9, 9] };
+        let bytes = crate::encoding::to_vec(&message).unwrap();
+        let decoded: Message = crate::encoding::from_slice(&bytes).unwrap();
+        match decoded {
+            Message::Unknown { tag, bytes } => {
+                assert_eq!(tag, 200);
+                assert_eq!(bytes, vec![9, 9, 9]);
+            }
+            other => panic!("expected Unknown, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,267 @@
+//! Trust-on-first-use pinning of peer key material.
+
+use crate::clock::{Clock, SystemClock};
+use crate::crypto::{kem, sig};
+use crate::identity::ProfileId;
+use crate::store::{MemoryStore, Store};
+
+/// The key material pinned for a single contact.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PinnedKeys {
+    pub signing: Vec<u8>,
+    pub encryption: Vec<u8>,
+    /// When this pin was recorded, unix seconds. Only consulted by
+    /// [`KeyPinStore::merge`]'s last-writer-wins reconciliation — not by
+    /// [`KeyPinStore::observe`]'s own change detection, which compares
+    /// key material only (see [`Self::same_keys`]).
+    pub pinned_at: u64,
+}
+
+impl PinnedKeys {
+    /// Whether `self` and `other` pin the same key material, ignoring
+    /// `pinned_at`.
+    fn same_keys(&self, other: &PinnedKeys) -> bool {
+        self.signing == other.signing && self.encryption == other.encryption
+    }
+}
+
+/// Two devices' local pins for the same contact disagreeing on which
+/// keys are trusted, surfaced by [`KeyPinStore::merge`] instead of
+/// silently picking one: unlike [`crate::roster::Roster`]'s cached
+/// contact metadata, a pin *is* the trust boundary that detects a
+/// possible key-compromise or machine-in-the-middle, so overwriting one
+/// pin with another on nothing but a timestamp comparison would defeat
+/// the point of pinning in the first place.
+#[derive(Debug, Clone)]
+pub struct PinConflict {
+    pub profile_id: ProfileId,
+    pub local: PinnedKeys,
+    pub incoming: PinnedKeys,
+}
+
+/// The outcome of [`KeyPinStore::merge`]: every pin disagreement found,
+/// left for the application to resolve (e.g. by prompting the user to
+/// pick a side, or treating it as a possible compromise worth
+/// investigating).
+#[derive(Debug, Clone, Default)]
+pub struct MergeReport {
+    pub conflicts: Vec<PinConflict>,
+}
+
+impl MergeReport {
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+/// Trust-on-first-use store of the keys we've previously seen for each
+/// contact, used to detect a key change (possible key compromise or a
+/// machine-in-the-middle). Backed by a [`Store`] rather than a bare
+/// `HashMap` so callers who need pins to survive a restart can plug in
+/// [`crate::store::FilesystemStore`] instead of the in-memory default.
+/// [`crate::queue::OutboundQueue`] and [`crate::revocation::RevocationStore`]
+/// get the same treatment. [`crate::roster::Roster`] does not: its
+/// `contacts` and `last_seen` are two maps serialized together as one
+/// unit (see `RosterWire`), which doesn't fit `Store`'s flat
+/// single-map-of-one-value-type contract without either splitting it into
+/// two stores (losing the ability to load/save it as one atomic blob) or
+/// giving `Store` a wider contract than every other user of it needs.
+pub struct KeyPinStore<S: Store<String, PinnedKeys> = MemoryStore<String, PinnedKeys>> {
+    store: S,
+}
+
+impl KeyPinStore<MemoryStore<String, PinnedKeys>> {
+    pub fn new() -> Self {
+        Self { store: MemoryStore::default() }
+    }
+}
+
+impl Default for KeyPinStore<MemoryStore<String, PinnedKeys>> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Store<String, PinnedKeys>> KeyPinStore<S> {
+    /// Use a specific [`Store`] backend instead of the in-memory default.
+    pub fn with_store(store: S) -> Self {
+        Self { store }
+    }
+
+    /// Compare presented keys against the pin for `profile_id`, storing
+    /// them if this is the first time we've seen this contact. Returns
+    /// `true` if the presented keys differ from a previously pinned value.
+    ///
+    /// Uses the real wall clock to stamp the pin; see [`Self::observe_at`]
+    /// to supply a [`Clock`] instead, e.g. a [`crate::clock::MockClock`]
+    /// in tests.
+    pub fn observe(&self, profile_id: &ProfileId, signing: &sig::PublicKey, encryption: &kem::PublicKey) -> bool {
+        self.observe_at(profile_id, signing, encryption, &SystemClock)
+    }
+
+    /// As [`Self::observe`], but recording `clock`'s time as the pin's
+    /// `pinned_at` instead of the real wall clock.
+    pub fn observe_at(&self, profile_id: &ProfileId, signing: &sig::PublicKey, encryption: &kem::PublicKey, clock: &dyn Clock) -> bool {
+        let presented = PinnedKeys { signing: signing.as_ref().to_vec(), encryption: encryption.as_ref().to_vec(), pinned_at: clock.now_unix() };
+        let key = profile_id.to_string();
+        let changed = matches!(self.store.get(&key), Ok(Some(previous)) if !previous.same_keys(&presented));
+        let _ = self.store.put(key, presented);
+        if changed {
+            crate::audit::emit(crate::audit::SecurityEvent::KeyChangeDetected { profile_id: profile_id.clone() });
+        }
+        changed
+    }
+
+    /// Merge `other`'s pins into `self`, reconciling the same identity
+    /// restored on two devices that have each pinned contacts
+    /// independently. A contact pinned only in `other` is adopted
+    /// outright. A contact pinned in both with the *same* key material
+    /// just keeps whichever `pinned_at` is more recent. A contact pinned
+    /// in both with *different* key material is never resolved
+    /// automatically — `self`'s existing pin is left untouched and the
+    /// disagreement is recorded as a [`PinConflict`] in the returned
+    /// [`MergeReport`] for the application to resolve.
+    pub fn merge(&self, other: &Self) -> MergeReport {
+        let mut report = MergeReport::default();
+
+        for (key, incoming) in other.store.list().unwrap_or_default() {
+            match self.store.get(&key) {
+                Ok(Some(local)) if local.same_keys(&incoming) => {
+                    if incoming.pinned_at > local.pinned_at {
+                        let _ = self.store.put(key, incoming);
+                    }
+                }
+                Ok(Some(local)) => {
+                    report.conflicts.push(PinConflict { profile_id: ProfileId::new(key), local, incoming });
+                }
+                Ok(None) => {
+                    let _ = self.store.put(key, incoming);
+                }
+                Err(_) => {}
+            }
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::EncryptionContext;
+    use crate::store::FilesystemStore;
+
+    fn profile_id() -> ProfileId {
+        ProfileId::new("alice#0001")
+    }
+
+    #[test]
+    fn observe_reports_no_change_the_first_time_a_profile_is_seen() {
+        let pins = KeyPinStore::new();
+        let context = EncryptionContext::generate().unwrap();
+        let changed = pins.observe(&profile_id(), context.signing_public_key(), context.encryption_public_key());
+        assert!(!changed);
+    }
+
+    #[test]
+    fn observe_reports_no_change_when_the_same_keys_are_presented_again() {
+        let pins = KeyPinStore::new();
+        let context = EncryptionContext::generate().unwrap();
+        pins.observe(&profile_id(), context.signing_public_key(), context.encryption_public_key());
+        let changed = pins.observe(&profile_id(), context.signing_public_key(), context.encryption_public_key());
+        assert!(!changed);
+    }
+
+    #[test]
+    fn observe_reports_a_change_when_different_keys_are_presented() {
+        let pins = KeyPinStore::new();
+        let first = EncryptionContext::generate().unwrap();
+        let second = EncryptionContext::generate().unwrap();
+        pins.observe(&profile_id(), first.signing_public_key(), first.encryption_public_key());
+        let changed = pins.observe(&profile_id(), second.signing_public_key(), second.encryption_public_key());
+        assert!(changed);
+    }
+
+    #[test]
+    fn observe_emits_a_key_change_detected_security_event_only_when_keys_change() {
+        use std::sync::{Arc, Mutex};
+
+        let received: Arc<Mutex<Vec<crate::audit::SecurityEvent>>> = Arc::new(Mutex::new(Vec::new()));
+        let received_in_sink = received.clone();
+        crate::audit::set_security_sink(move |event| received_in_sink.lock().unwrap().push(event.clone()));
+
+        let pins = KeyPinStore::new();
+        let first = EncryptionContext::generate().unwrap();
+        let second = EncryptionContext::generate().unwrap();
+        pins.observe(&profile_id(), first.signing_public_key(), first.encryption_public_key());
+        pins.observe(&profile_id(), second.signing_public_key(), second.encryption_public_key());
+
+        let events = received.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(&events[0], crate::audit::SecurityEvent::KeyChangeDetected { profile_id } if *profile_id == self::profile_id()));
+
+        crate::audit::clear_security_sink();
+    }
+
+    #[test]
+    fn key_pin_store_works_against_a_filesystem_backed_store() {
+        let dir = std::env::temp_dir().join(format!("resolution-protocol-pinning-test-{}", uuid::Uuid::new_v4()));
+        let pins = KeyPinStore::with_store(FilesystemStore::new(&dir).unwrap());
+        let context = EncryptionContext::generate().unwrap();
+
+        assert!(!pins.observe(&profile_id(), context.signing_public_key(), context.encryption_public_key()));
+        assert!(!pins.observe(&profile_id(), context.signing_public_key(), context.encryption_public_key()));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn merge_adopts_a_pin_only_present_in_the_other_store() {
+        use crate::clock::MockClock;
+
+        let local = KeyPinStore::new();
+        let remote = KeyPinStore::new();
+        let context = EncryptionContext::generate().unwrap();
+        remote.observe_at(&profile_id(), context.signing_public_key(), context.encryption_public_key(), &MockClock::at(1_000));
+
+        let report = local.merge(&remote);
+        assert!(report.is_clean());
+        assert!(!local.observe(&profile_id(), context.signing_public_key(), context.encryption_public_key()));
+    }
+
+    #[test]
+    fn merge_keeps_the_newer_pinned_at_when_both_sides_agree_on_keys() {
+        use crate::clock::MockClock;
+
+        let local = KeyPinStore::new();
+        let remote = KeyPinStore::new();
+        let context = EncryptionContext::generate().unwrap();
+        local.observe_at(&profile_id(), context.signing_public_key(), context.encryption_public_key(), &MockClock::at(1_000));
+        remote.observe_at(&profile_id(), context.signing_public_key(), context.encryption_public_key(), &MockClock::at(2_000));
+
+        let report = local.merge(&remote);
+        assert!(report.is_clean());
+        assert_eq!(local.store.get(&profile_id().to_string()).unwrap().unwrap().pinned_at, 2_000);
+    }
+
+    #[test]
+    fn merge_reports_a_conflict_instead_of_overwriting_disagreeing_keys() {
+        use crate::clock::MockClock;
+
+        let local = KeyPinStore::new();
+        let remote = KeyPinStore::new();
+        let first = EncryptionContext::generate().unwrap();
+        let second = EncryptionContext::generate().unwrap();
+        local.observe_at(&profile_id(), first.signing_public_key(), first.encryption_public_key(), &MockClock::at(1_000));
+        // A much later timestamp on the remote side must not matter: a
+        // key disagreement is never resolved by picking a side.
+        remote.observe_at(&profile_id(), second.signing_public_key(), second.encryption_public_key(), &MockClock::at(9_999));
+
+        let report = local.merge(&remote);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].profile_id, profile_id());
+
+        // `local`'s original pin is untouched.
+        assert!(!local.observe(&profile_id(), first.signing_public_key(), first.encryption_public_key()));
+    }
+}
@@ -0,0 +1,288 @@
+//! Live presence tracking for roster contacts.
+//!
+//! Presence is derived from iroh discovery's `UserData` for each contact's
+//! node id: whenever discovery reports the peer, they're [`PresenceEvent::Online`];
+//! once discovery stops reporting them for longer than [`DEBOUNCE`], they're
+//! [`PresenceEvent::Offline`]. A `UserData` payload change while online is a
+//! [`PresenceEvent::StatusChanged`].
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use futures_util::stream::{self, Stream};
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{sig, EncryptionContext, Signed};
+use crate::identity::ProfileId;
+use crate::profile::Profile;
+use crate::roster::Roster;
+
+/// A status beacon a profile can sign and broadcast, e.g. as iroh
+/// discovery `UserData`. Signing closes the gap a bare status string
+/// leaves open: without it, anyone who can influence what discovery
+/// reports for a node — not just the node's own signing key holder —
+/// could publish a status on that node's behalf.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Presence {
+    status: Option<String>,
+}
+
+impl Presence {
+    pub fn new(status: Option<String>) -> Self {
+        Self { status }
+    }
+
+    pub fn status(&self) -> Option<&str> {
+        self.status.as_deref()
+    }
+
+    /// Sign this beacon with `context`'s signing key. The caller is
+    /// responsible for however these bytes get carried (e.g.
+    /// base64-encoded into discovery `UserData`, which iroh only carries
+    /// as text — see [`DebouncedWatcher`]'s use of this for that case).
+    /// See [`Self::verify`] for the inverse.
+    pub fn signed(&self, context: &EncryptionContext) -> crate::Result<Vec<u8>> {
+        Signed::new(context, self)?.to_bytes()
+    }
+
+    /// Verify a beacon produced by [`Self::signed`] against `signer`.
+    pub fn verify(bytes: &[u8], signer: impl AsRef<sig::PublicKey>) -> crate::Result<Self> {
+        Signed::<Presence>::from_bytes(bytes)?.verify(signer)
+    }
+}
+
+/// A change in a contact's connectivity or advertised status.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PresenceEvent {
+    Online(ProfileId),
+    Offline(ProfileId),
+    StatusChanged { profile_id: ProfileId, status: String },
+}
+
+/// How long a contact must go unseen by discovery before we report them
+/// offline, to smooth over momentary discovery flaps.
+pub const DEBOUNCE: Duration = Duration::from_secs(15);
+
+/// A source of raw discovery sightings for a single node, decoupled from
+/// iroh so the debouncing logic can be exercised without a live endpoint.
+pub trait PresenceSource {
+    /// `Some(user_data)` if discovery currently reports the node, `None`
+    /// if it doesn't.
+    fn poll_sighting(&mut self) -> Option<Option<String>>;
+}
+
+/// [`PresenceSource`] backed by a live iroh endpoint's discovery service.
+pub struct DiscoverySource {
+    endpoint: iroh::Endpoint,
+    node_id: iroh::NodeId,
+}
+
+impl DiscoverySource {
+    pub fn new(endpoint: iroh::Endpoint, node_id: iroh::NodeId) -> Self {
+        Self { endpoint, node_id }
+    }
+}
+
+impl PresenceSource for DiscoverySource {
+    fn poll_sighting(&mut self) -> Option<Option<String>> {
+        self.endpoint
+            .discovery()
+            .and_then(|d| d.user_data(&self.node_id))
+            .map(|data| Some(data.to_string()))
+    }
+}
+
+struct DebouncedWatcher<S> {
+    profile_id: ProfileId,
+    source: S,
+    signer: sig::PublicKey,
+    online: bool,
+    last_status: Option<String>,
+    last_seen: Option<Instant>,
+    roster: Option<Arc<Roster>>,
+}
+
+impl<S: PresenceSource> DebouncedWatcher<S> {
+    /// Base64-decode and verify `encoded` as a [`Presence`] beacon signed
+    /// by this contact's own signing key, returning its status. `None` for
+    /// anything that doesn't decode or doesn't verify — a raw string a
+    /// third party could publish on this contact's behalf is never
+    /// surfaced as their status.
+    fn verify_beacon(&self, encoded: &str) -> Option<String> {
+        let bytes = STANDARD.decode(encoded).ok()?;
+        Presence::verify(&bytes, &self.signer).ok()?.status().map(str::to_string)
+    }
+
+    fn tick(&mut self, out: &mut VecDeque<PresenceEvent>) {
+        match self.source.poll_sighting() {
+            Some(status) => {
+                self.last_seen = Some(Instant::now());
+                if let Some(roster) = &self.roster {
+                    roster.touch(&self.profile_id);
+                }
+                if !self.online {
+                    self.online = true;
+                    out.push_back(PresenceEvent::Online(self.profile_id.clone()));
+                }
+
+                // An absent or unverifiable beacon leaves `last_status`
+                // untouched: presence-only sightings (no status
+                // published) and dropped forgeries neither surface an
+                // event nor erase a status we already trust.
+                if let Some(verified) = status.as_deref().and_then(|encoded| self.verify_beacon(encoded)) {
+                    if Some(verified.as_str()) != self.last_status.as_deref() {
+                        out.push_back(PresenceEvent::StatusChanged {
+                            profile_id: self.profile_id.clone(),
+                            status: verified.clone(),
+                        });
+                        self.last_status = Some(verified);
+                    }
+                }
+            }
+            None => {
+                let stale = self.last_seen.map(|t| t.elapsed() >= DEBOUNCE).unwrap_or(true);
+                if self.online && stale {
+                    self.online = false;
+                    out.push_back(PresenceEvent::Offline(self.profile_id.clone()));
+                }
+            }
+        }
+    }
+}
+
+impl Profile {
+    /// Subscribe to presence changes for every contact in `roster`. Each
+    /// sighting also touches the contact's `last_seen` in `roster`.
+    pub fn watch_presence(
+        &self,
+        endpoint: iroh::Endpoint,
+        roster: Arc<Roster>,
+    ) -> impl Stream<Item = PresenceEvent> {
+        let watchers: Vec<DebouncedWatcher<DiscoverySource>> = roster
+            .profile_ids()
+            .filter_map(|profile_id| {
+                let contact = roster.get(&profile_id)?;
+                let node_id = contact.node_id()?;
+                let signer = contact.signing_key().clone();
+                Some(DebouncedWatcher {
+                    profile_id,
+                    source: DiscoverySource::new(endpoint.clone(), node_id),
+                    signer,
+                    online: false,
+                    last_status: None,
+                    last_seen: None,
+                    roster: Some(roster.clone()),
+                })
+            })
+            .collect();
+        watch_stream(watchers)
+    }
+}
+
+/// Polls every watcher in a round-robin, buffering the events each tick
+/// produces so callers get one item per poll.
+fn watch_stream<S: PresenceSource + Unpin>(
+    watchers: Vec<DebouncedWatcher<S>>,
+) -> impl Stream<Item = PresenceEvent> {
+    let pending = VecDeque::new();
+    stream::unfold((watchers, pending, 0usize), |(mut watchers, mut pending, mut idx)| async move {
+        loop {
+            if let Some(event) = pending.pop_front() {
+                return Some((event, (watchers, pending, idx)));
+            }
+            if watchers.is_empty() {
+                return None;
+            }
+            idx %= watchers.len();
+            watchers[idx].tick(&mut pending);
+            idx += 1;
+            if idx == watchers.len() {
+                idx = 0;
+                crate::runtime::sleep(Duration::from_millis(200)).await;
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+
+    struct ScriptedSource {
+        script: VecDeque<Option<Option<String>>>,
+    }
+
+    impl PresenceSource for ScriptedSource {
+        fn poll_sighting(&mut self) -> Option<Option<String>> {
+            self.script.pop_front().unwrap_or(None)
+        }
+    }
+
+    fn throwaway_signer() -> sig::PublicKey {
+        EncryptionContext::generate().unwrap().signing_public_key().clone()
+    }
+
+    #[tokio::test]
+    async fn a_first_sighting_emits_online() {
+        let profile_id = ProfileId::from("alice#1234");
+        let watcher = DebouncedWatcher {
+            profile_id: profile_id.clone(),
+            source: ScriptedSource {
+                script: VecDeque::from([Some(None), Some(None)]),
+            },
+            signer: throwaway_signer(),
+            online: false,
+            last_status: None,
+            last_seen: None,
+            roster: None,
+        };
+
+        let mut stream = Box::pin(watch_stream(vec![watcher]));
+        let first = stream.next().await.unwrap();
+        assert_eq!(first, PresenceEvent::Online(profile_id));
+    }
+
+    #[test]
+    fn presence_signed_by_the_wrong_key_fails_verification() {
+        let signer = EncryptionContext::generate().unwrap();
+        let impostor = EncryptionContext::generate().unwrap();
+        let beacon = Presence::new(Some("at the gym".to_string())).signed(&signer).unwrap();
+
+        assert!(Presence::verify(&beacon, impostor.signing_public_key().clone()).is_err());
+        let verified = Presence::verify(&beacon, signer.signing_public_key().clone()).unwrap();
+        assert_eq!(verified.status(), Some("at the gym"));
+    }
+
+    #[tokio::test]
+    async fn a_beacon_signed_by_the_wrong_key_is_dropped_instead_of_surfaced() {
+        let signer = EncryptionContext::generate().unwrap();
+        let impostor = EncryptionContext::generate().unwrap();
+        let forged = STANDARD.encode(Presence::new(Some("forged status".to_string())).signed(&impostor).unwrap());
+
+        let profile_id = ProfileId::from("alice#1234");
+        let watcher = DebouncedWatcher {
+            profile_id: profile_id.clone(),
+            source: ScriptedSource {
+                script: VecDeque::from([Some(Some(forged))]),
+            },
+            signer: signer.signing_public_key().clone(),
+            online: false,
+            last_status: None,
+            last_seen: None,
+            roster: None,
+        };
+
+        let mut stream = Box::pin(watch_stream(vec![watcher]));
+        let first = stream.next().await.unwrap();
+        assert_eq!(first, PresenceEvent::Online(profile_id));
+
+        // No StatusChanged follows: the forged beacon was dropped rather
+        // than surfaced. The next event, if any, would only be Offline
+        // after DEBOUNCE — nothing arrives within this short window.
+        assert!(tokio::time::timeout(Duration::from_millis(50), stream.next()).await.is_err());
+    }
+}
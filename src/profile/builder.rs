@@ -0,0 +1,89 @@
+//! Builder for constructing a [`Profile`].
+
+use crate::crypto::EncryptionContext;
+use crate::profile::Profile;
+
+/// Builds a [`Profile`], validating the display name before construction.
+#[derive(Default)]
+pub struct ProfileBuilder {
+    name: Option<String>,
+    context: Option<EncryptionContext>,
+    node_id: Option<iroh::NodeId>,
+    relays: Vec<String>,
+}
+
+impl ProfileBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn node_id(mut self, node_id: iroh::NodeId) -> Self {
+        self.node_id = Some(node_id);
+        self
+    }
+
+    /// Set the name and encryption context together, for callers that need
+    /// the resulting [`Profile::profile_id`] to be reproducible — e.g. a
+    /// test rebuilding a peer's profile from stored key material, or a
+    /// migration reusing a previously published identity. `profile_id` is
+    /// derived from the name and the context's public keys, so building
+    /// twice with the same `(name, context)` inputs yields the same id.
+    pub fn identity(mut self, name: impl Into<String>, context: EncryptionContext) -> Self {
+        self.name = Some(name.into());
+        self.context = Some(context);
+        self
+    }
+
+    /// Add a preferred relay URL. Signed as part of the profile's public
+    /// data, so contacts can trust it came from this profile and not from
+    /// whatever server happens to be relaying a `resolution://` link — see
+    /// [`crate::profile::PublicProfileData::relay_is_trusted`].
+    pub fn relay(mut self, relay: impl Into<String>) -> Self {
+        self.relays.push(relay.into());
+        self
+    }
+
+    pub fn build(self) -> crate::Result<Profile> {
+        let name = self
+            .name
+            .ok_or_else(|| anyhow::anyhow!("profile name is required"))?;
+        let context = match self.context {
+            Some(context) => context,
+            None => EncryptionContext::generate()?,
+        };
+        Ok(Profile {
+            name,
+            context,
+            node_id: self.node_id,
+            devices: Vec::new(),
+            relays: self.relays,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::EncryptionContext;
+
+    #[test]
+    fn building_twice_with_the_same_identity_yields_the_same_profile_id() {
+        let secret_bytes = EncryptionContext::generate().unwrap().to_secret_bytes().unwrap();
+
+        let first = ProfileBuilder::new()
+            .identity("alice", EncryptionContext::from_secret_bytes(&secret_bytes).unwrap())
+            .build()
+            .unwrap();
+        let second = ProfileBuilder::new()
+            .identity("alice", EncryptionContext::from_secret_bytes(&secret_bytes).unwrap())
+            .build()
+            .unwrap();
+
+        assert_eq!(first.profile_id(), second.profile_id());
+    }
+}
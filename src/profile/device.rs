@@ -0,0 +1,59 @@
+//! Multi-device identity support: mapping one logical profile to several
+//! iroh node keys, each with its own encryption key but all vouched for by
+//! the profile's single signing key.
+
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{domain, encryption_key_from_bytes, kem, sig, verify_detached, EncryptionContext};
+use crate::Result;
+
+/// One device belonging to a profile.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    pub node_id: iroh::NodeId,
+    encryption_key: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl DeviceInfo {
+    /// Sign a new device entry with the profile's signing key.
+    pub fn sign(context: &EncryptionContext, node_id: iroh::NodeId, encryption_key: &kem::PublicKey) -> Result<Self> {
+        let encryption_key = encryption_key.as_ref().to_vec();
+        let signature = context.sign_detached(domain::DEVICE_BINDING, &signing_bytes(node_id, &encryption_key))?;
+        Ok(Self { node_id, encryption_key, signature })
+    }
+
+    pub fn encryption_key(&self) -> Result<kem::PublicKey> {
+        encryption_key_from_bytes(&self.encryption_key)
+    }
+
+    /// Verify this device was genuinely vouched for by `signer`, the
+    /// profile's signing key.
+    pub fn verify(&self, signer: &sig::PublicKey) -> Result<()> {
+        verify_detached(domain::DEVICE_BINDING, &signing_bytes(self.node_id, &self.encryption_key), &self.signature, signer)
+    }
+}
+
+fn signing_bytes(node_id: iroh::NodeId, encryption_key: &[u8]) -> Vec<u8> {
+    let mut bytes = node_id.as_bytes().to_vec();
+    bytes.extend_from_slice(encryption_key);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tampered_device_fails_verification() {
+        let context = EncryptionContext::generate().unwrap();
+        let other = EncryptionContext::generate().unwrap();
+        let node_id = iroh::SecretKey::generate(&mut rand::thread_rng()).public();
+
+        let mut device = DeviceInfo::sign(&context, node_id, context.encryption_public_key()).unwrap();
+        assert!(device.verify(context.signing_public_key()).is_ok());
+
+        device.encryption_key = other.encryption_public_key().as_ref().to_vec();
+        assert!(device.verify(context.signing_public_key()).is_err());
+    }
+}
@@ -0,0 +1,585 @@
+//! Local and remote identity types.
+
+mod builder;
+mod device;
+mod sealed_secret;
+mod signed;
+
+pub use builder::ProfileBuilder;
+pub use device::DeviceInfo;
+pub use sealed_secret::{KdfParams, SealedProfile};
+pub use signed::{SignedProfile, CLOCK_SKEW_TOLERANCE};
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+
+use crate::clock::{Clock, SystemClock};
+use crate::crypto::{encryption_key_from_bytes, kem, sig, signing_key_from_bytes, EncryptionContext};
+use crate::discriminant::{discriminant, format_profile_id, verify_discriminant, DiscriminantScheme};
+use crate::error::UserError;
+use crate::identity::ProfileId;
+
+/// The newest profile schema version this build understands. A profile
+/// with a higher version is rejected rather than silently misread.
+pub const CURRENT_PROFILE_VERSION: u16 = 1;
+
+fn default_profile_version() -> u16 {
+    1
+}
+
+/// URI scheme for a [`PublicProfileData::to_uri`] link.
+pub const RESOLUTION_URI_SCHEME: &str = "resolution://";
+
+/// Wire payload of a `resolution://` URI: everything
+/// [`crate::endpoint::node_addr_from_uri`] needs beyond what's already in
+/// the embedded [`PublicProfileData::to_blob`] blob. `node_id` is carried
+/// separately because it's never part of that blob (see
+/// `PublicProfileDataWire::relays`'s doc comment for why), and `relay` is
+/// carried separately, unsigned, because a URI is often generated ad hoc
+/// for a specific rendezvous rather than copied from a profile's own
+/// signed `relays` list — [`PublicProfileData::relay_is_trusted`] is what
+/// lets a parser tell a genuine suggestion from a spliced-in one.
+#[derive(Serialize, Deserialize)]
+struct UriPayload {
+    profile: Vec<u8>,
+    node_id: [u8; 32],
+    relay: Option<String>,
+}
+
+/// A base64-encoded key, as produced by
+/// [`PublicProfileData::signing_key_b64`]/[`PublicProfileData::encryption_key_b64`]
+/// and consumed by [`PublicProfileData::from_b64_keys`].
+pub type Base64 = String;
+
+/// The publicly shareable portion of a profile: what a contact learns
+/// about you.
+#[derive(Debug, Clone)]
+pub struct PublicProfileData {
+    profile_id: ProfileId,
+    name: String,
+    signing_key: sig::PublicKey,
+    encryption_key: kem::PublicKey,
+    node_id: Option<iroh::NodeId>,
+    version: u16,
+    devices: Vec<DeviceInfo>,
+    discriminant_scheme: DiscriminantScheme,
+    relays: Vec<String>,
+}
+
+/// Wire representation of [`PublicProfileData`]. The oqs key types don't
+/// implement serde themselves, so we go through raw bytes; `version` uses
+/// `#[serde(default)]` so an old blob missing it (pre-versioning) still
+/// deserializes as version 1, and unknown fields future versions add are
+/// silently ignored by serde's default struct handling. `devices` and
+/// `relays` are likewise defaulted so older blobs without them still
+/// deserialize.
+#[derive(Serialize, Deserialize)]
+struct PublicProfileDataWire {
+    #[serde(default = "default_profile_version")]
+    version: u16,
+    profile_id: String,
+    name: String,
+    signing_key: Vec<u8>,
+    encryption_key: Vec<u8>,
+    #[serde(default)]
+    devices: Vec<DeviceInfo>,
+    #[serde(default)]
+    discriminant_scheme: DiscriminantScheme,
+    /// Preferred relay URLs for reaching this profile. Signed as part of
+    /// this payload (unlike `node_id`, which is never carried on the
+    /// wire at all) so [`SignedProfile::verify`] authenticates them: a
+    /// `resolution://` URI's own relay field is otherwise just a string
+    /// an attacker could swap for a relay they monitor — see
+    /// [`PublicProfileData::relay_is_trusted`]. Relays are still
+    /// untrusted for *content* — anything routed through one is still
+    /// end-to-end encrypted to `encryption_key` — signing only prevents
+    /// a downgrade/redirection game over *which* relay a contact tries
+    /// first.
+    #[serde(default)]
+    relays: Vec<String>,
+}
+
+impl Serialize for PublicProfileData {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PublicProfileDataWire {
+            version: self.version,
+            profile_id: self.profile_id.as_str().to_string(),
+            name: self.name.clone(),
+            signing_key: self.signing_key.as_ref().to_vec(),
+            encryption_key: self.encryption_key.as_ref().to_vec(),
+            devices: self.devices.clone(),
+            discriminant_scheme: self.discriminant_scheme,
+            relays: self.relays.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PublicProfileData {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let wire = PublicProfileDataWire::deserialize(deserializer)?;
+        if wire.version > CURRENT_PROFILE_VERSION {
+            return Err(serde::de::Error::custom(
+                crate::Error::from(UserError::UnsupportedProfileVersion { got: wire.version, max_understood: CURRENT_PROFILE_VERSION }),
+            ));
+        }
+        Ok(PublicProfileData {
+            profile_id: ProfileId::new(wire.profile_id),
+            name: wire.name,
+            signing_key: signing_key_from_bytes(&wire.signing_key).map_err(serde::de::Error::custom)?,
+            encryption_key: encryption_key_from_bytes(&wire.encryption_key).map_err(serde::de::Error::custom)?,
+            node_id: None,
+            version: wire.version,
+            devices: wire.devices,
+            discriminant_scheme: wire.discriminant_scheme,
+            relays: wire.relays,
+        })
+    }
+}
+
+impl PublicProfileData {
+    pub fn profile_id(&self) -> &ProfileId {
+        &self.profile_id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn signing_key(&self) -> &sig::PublicKey {
+        &self.signing_key
+    }
+
+    pub fn encryption_key(&self) -> &kem::PublicKey {
+        &self.encryption_key
+    }
+
+    /// The iroh node id this contact is reachable at, if known.
+    pub fn node_id(&self) -> Option<iroh::NodeId> {
+        self.node_id
+    }
+
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// The other devices this identity has announced, beyond `node_id`.
+    pub fn devices(&self) -> &[DeviceInfo] {
+        &self.devices
+    }
+
+    /// This profile's preferred relay URLs, in the order they were
+    /// signed. Authenticated (see the field doc on
+    /// `PublicProfileDataWire::relays`) but not trusted for content: a
+    /// relay only ever forwards ciphertext already sealed to
+    /// [`Self::encryption_key`].
+    pub fn relays(&self) -> &[String] {
+        &self.relays
+    }
+
+    /// Whether `candidate` is one of this profile's signed relay URLs.
+    /// Used by [`decode_uri`] before trusting a `resolution://` URI's own
+    /// relay field, rejecting a redirection attempt to a relay this
+    /// profile never signed.
+    pub fn relay_is_trusted(&self, candidate: &str) -> bool {
+        self.relays.iter().any(|relay| relay == candidate)
+    }
+
+    /// Check that every announced device is genuinely vouched for by this
+    /// profile's signing key, e.g. before dialing one of them.
+    pub fn verify_devices(&self) -> crate::Result<()> {
+        for device in &self.devices {
+            device.verify(&self.signing_key)?;
+        }
+        Ok(())
+    }
+
+    /// Pick a device to dial: the profile's primary `node_id` if reachable,
+    /// otherwise the first verified announced device.
+    pub fn pick_device(&self) -> Option<iroh::NodeId> {
+        self.node_id.or_else(|| self.devices.iter().find(|d| d.verify(&self.signing_key).is_ok()).map(|d| d.node_id))
+    }
+
+    /// Check that `profile_id`'s discriminant suffix is actually derived
+    /// from this profile's key material. See
+    /// [`crate::discriminant::verify_discriminant`] for the scheme
+    /// recovery rules this delegates to.
+    pub fn validate(&self) -> crate::Result<()> {
+        verify_discriminant(self.profile_id.as_str(), &self.signing_key, &self.encryption_key)
+    }
+
+    /// Base64 encoding of [`Self::signing_key`], for text configs (TOML
+    /// files, env vars) that can't hold raw key bytes.
+    pub fn signing_key_b64(&self) -> Base64 {
+        STANDARD.encode(self.signing_key.as_ref())
+    }
+
+    /// Base64 encoding of [`Self::encryption_key`]. See [`Self::signing_key_b64`].
+    pub fn encryption_key_b64(&self) -> Base64 {
+        STANDARD.encode(self.encryption_key.as_ref())
+    }
+
+    /// Encode as a standalone canonical-msgpack blob: what [`Self::to_uri`]
+    /// wraps, for QR codes and contact files that carry the raw bytes
+    /// directly instead of a URI. See [`Self::from_blob`] for the inverse.
+    pub fn to_blob(&self) -> crate::Result<Vec<u8>> {
+        crate::encoding::to_vec(self)
+    }
+
+    /// Inverse of [`Self::to_blob`]. Rejects trailing bytes left over
+    /// after the msgpack value (see [`crate::encoding::from_slice_exact`])
+    /// and runs [`Self::validate`] before returning, so a blob with a
+    /// forged profile_id/key pairing is never handed back as if it were
+    /// trustworthy.
+    pub fn from_blob(bytes: &[u8]) -> crate::Result<Self> {
+        let profile: PublicProfileData = crate::encoding::from_slice_exact(bytes)?;
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    /// Reconstruct a minimal `PublicProfileData` from a profile id and
+    /// base64-encoded keys, the inverse of [`Self::signing_key_b64`]/
+    /// [`Self::encryption_key_b64`]. Meant for a contact pinned by hand in
+    /// a text config rather than one learned from a
+    /// [`crate::profile::SignedProfile`]: `name` comes back empty, there's
+    /// no `node_id` or announced devices, and `version` is
+    /// [`CURRENT_PROFILE_VERSION`]. Errors if either string isn't valid
+    /// base64 or doesn't decode to a well-formed key.
+    pub fn from_b64_keys(profile_id: impl Into<String>, sig_b64: &str, enc_b64: &str) -> crate::Result<Self> {
+        let signing_key_bytes = STANDARD.decode(sig_b64).map_err(anyhow::Error::from)?;
+        let encryption_key_bytes = STANDARD.decode(enc_b64).map_err(anyhow::Error::from)?;
+        Ok(Self {
+            profile_id: ProfileId::new(profile_id),
+            name: String::new(),
+            signing_key: signing_key_from_bytes(&signing_key_bytes)?,
+            encryption_key: encryption_key_from_bytes(&encryption_key_bytes)?,
+            node_id: None,
+            version: CURRENT_PROFILE_VERSION,
+            devices: Vec::new(),
+            discriminant_scheme: DiscriminantScheme::default(),
+            relays: Vec::new(),
+        })
+    }
+
+    /// Encode as a `resolution://` link: a [`Self::to_blob`] payload plus
+    /// the node id and (optionally) a suggested relay to dial it at,
+    /// base64url-encoded so the result is safe to paste as a URI without
+    /// further escaping. Errors with [`UserError::NoDialableNodeId`] if
+    /// this profile has neither its own `node_id` nor a verified announced
+    /// device — see [`Self::pick_device`]. See
+    /// [`crate::endpoint::node_addr_from_uri`] for the inverse.
+    pub fn to_uri(&self) -> crate::Result<String> {
+        let node_id = self.pick_device().ok_or(UserError::NoDialableNodeId)?;
+        let payload = UriPayload { profile: self.to_blob()?, node_id: *node_id.as_bytes(), relay: self.relays.first().cloned() };
+        let encoded = crate::encoding::to_vec(&payload)?;
+        Ok(format!("{RESOLUTION_URI_SCHEME}{}", URL_SAFE_NO_PAD.encode(encoded)))
+    }
+}
+
+/// Parse a `resolution://` link into its embedded, discriminant-validated
+/// profile, node id, and unsigned suggested relay (if any). Used by
+/// [`crate::endpoint::node_addr_from_uri`], which turns the result into an
+/// [`iroh::NodeAddr`]; split out here since it's the profile encoding, not
+/// anything iroh-transport-specific, that this crate owns the format of.
+pub(crate) fn decode_uri(uri: &str) -> crate::Result<(PublicProfileData, iroh::NodeId, Option<String>)> {
+    let encoded = uri
+        .strip_prefix(RESOLUTION_URI_SCHEME)
+        .ok_or_else(|| UserError::InvalidResolutionUri { reason: format!("missing {RESOLUTION_URI_SCHEME} scheme") })?;
+    let payload_bytes = URL_SAFE_NO_PAD
+        .decode(encoded)
+        .map_err(|_| UserError::InvalidResolutionUri { reason: "payload is not valid base64url".to_string() })?;
+    let payload: UriPayload = crate::encoding::from_slice_exact(&payload_bytes)
+        .map_err(|_| UserError::InvalidResolutionUri { reason: "payload is not a well-formed resolution URI blob".to_string() })?;
+
+    let profile = PublicProfileData::from_blob(&payload.profile)?;
+    let node_id = iroh::NodeId::from_bytes(&payload.node_id)
+        .map_err(|_| UserError::InvalidResolutionUri { reason: "embedded node id is malformed".to_string() })?;
+
+    if let Some(relay) = &payload.relay {
+        if !profile.relay_is_trusted(relay) {
+            return Err(UserError::UntrustedRelay { relay: relay.clone() }.into());
+        }
+    }
+
+    Ok((profile, node_id, payload.relay))
+}
+
+/// A local identity: display name, key material, and reachability info.
+pub struct Profile {
+    name: String,
+    context: EncryptionContext,
+    node_id: Option<iroh::NodeId>,
+    devices: Vec<DeviceInfo>,
+    relays: Vec<String>,
+}
+
+impl Profile {
+    /// The key material backing this profile.
+    pub fn context(&self) -> &EncryptionContext {
+        &self.context
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Announce another device under this identity, self-signed with this
+    /// profile's signing key so contacts can verify it later.
+    pub fn register_device(&mut self, node_id: iroh::NodeId, encryption_key: &kem::PublicKey) -> crate::Result<DeviceInfo> {
+        let device = DeviceInfo::sign(&self.context, node_id, encryption_key)?;
+        self.devices.push(device.clone());
+        Ok(device)
+    }
+
+    /// Add a preferred relay URL, to be included and signed the next time
+    /// [`Self::public`] (or a `signed_public_profile*` method) is called.
+    pub fn add_relay(&mut self, relay: impl Into<String>) {
+        self.relays.push(relay.into());
+    }
+
+    /// The publicly shareable snapshot of this profile.
+    pub fn public(&self) -> PublicProfileData {
+        let scheme = DiscriminantScheme::Sha256Base32;
+        let discriminant = discriminant(scheme, self.context.signing_public_key(), self.context.encryption_public_key());
+        PublicProfileData {
+            profile_id: ProfileId::new(format_profile_id(&self.name, &discriminant)),
+            name: self.name.clone(),
+            signing_key: self.context.signing_public_key().clone(),
+            encryption_key: self.context.encryption_public_key().clone(),
+            node_id: self.node_id,
+            version: CURRENT_PROFILE_VERSION,
+            devices: self.devices.clone(),
+            discriminant_scheme: scheme,
+            relays: self.relays.clone(),
+        }
+    }
+
+    pub fn profile_id(&self) -> ProfileId {
+        self.public().profile_id().clone()
+    }
+
+    /// Sign the current [`PublicProfileData`] snapshot with an expiry
+    /// `ttl` from now, so contacts can tell when to expect a fresher copy.
+    ///
+    /// Uses the real wall clock; see [`Self::signed_public_profile_expiring_at`]
+    /// to supply a [`Clock`] instead, e.g. a [`crate::clock::MockClock`] in
+    /// tests.
+    pub fn signed_public_profile_expiring(&self, ttl: std::time::Duration) -> crate::Result<SignedProfile> {
+        self.signed_public_profile_expiring_at(ttl, &SystemClock)
+    }
+
+    /// As [`Self::signed_public_profile_expiring`], but computing `ttl`
+    /// from `clock`'s time instead of the real wall clock.
+    pub fn signed_public_profile_expiring_at(&self, ttl: std::time::Duration, clock: &dyn Clock) -> crate::Result<SignedProfile> {
+        let valid_until = clock.now_unix() + ttl.as_secs();
+        SignedProfile::sign(&self.context, self.public(), Some(valid_until))
+    }
+
+    /// Sign the current [`PublicProfileData`] snapshot with no expiry.
+    pub fn signed_public_profile(&self) -> crate::Result<SignedProfile> {
+        SignedProfile::sign(&self.context, self.public(), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::EncryptionContext;
+
+    /// Simulates a future profile version that has added a field this
+    /// build has never heard of.
+    #[derive(Serialize)]
+    struct PublicProfileDataWireV1PlusExtra {
+        version: u16,
+        profile_id: String,
+        name: String,
+        signing_key: Vec<u8>,
+        encryption_key: Vec<u8>,
+        future_field: String,
+    }
+
+    #[test]
+    fn deserializes_v1_blob_with_unknown_extra_field() {
+        let context = EncryptionContext::generate().unwrap();
+        let wire = PublicProfileDataWireV1PlusExtra {
+            version: 1,
+            profile_id: "alice#0001".to_string(),
+            name: "alice".to_string(),
+            signing_key: context.signing_public_key().as_ref().to_vec(),
+            encryption_key: context.encryption_public_key().as_ref().to_vec(),
+            future_field: "not understood by this build".to_string(),
+        };
+        let bytes = rmp_serde::to_vec_named(&wire).unwrap();
+
+        let decoded: PublicProfileData = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(decoded.name(), "alice");
+        assert_eq!(decoded.version(), 1);
+    }
+
+    #[test]
+    fn rejects_profile_from_the_future() {
+        let context = EncryptionContext::generate().unwrap();
+        let wire = PublicProfileDataWireV1PlusExtra {
+            version: CURRENT_PROFILE_VERSION + 1,
+            profile_id: "alice#0001".to_string(),
+            name: "alice".to_string(),
+            signing_key: context.signing_public_key().as_ref().to_vec(),
+            encryption_key: context.encryption_public_key().as_ref().to_vec(),
+            future_field: String::new(),
+        };
+        let bytes = rmp_serde::to_vec_named(&wire).unwrap();
+
+        assert!(rmp_serde::from_slice::<PublicProfileData>(&bytes).is_err());
+    }
+
+    #[test]
+    fn tampered_device_fails_public_verify_devices() {
+        let mut profile = ProfileBuilder::new().name("alice").build().unwrap();
+        let node_id = iroh::SecretKey::generate(&mut rand::thread_rng()).public();
+        let device_key = EncryptionContext::generate().unwrap();
+        profile.register_device(node_id, device_key.encryption_public_key()).unwrap();
+
+        let mut public = profile.public();
+        assert!(public.verify_devices().is_ok());
+
+        // Signed by an impostor, not the profile's own signing key.
+        let impostor = EncryptionContext::generate().unwrap();
+        public.devices[0] = DeviceInfo::sign(&impostor, node_id, device_key.encryption_public_key()).unwrap();
+        assert!(public.verify_devices().is_err());
+    }
+
+    #[test]
+    fn b64_keys_round_trip_through_from_b64_keys() {
+        let profile = ProfileBuilder::new().name("alice").build().unwrap();
+        let public = profile.public();
+
+        let reconstructed =
+            PublicProfileData::from_b64_keys(public.profile_id().as_str(), &public.signing_key_b64(), &public.encryption_key_b64()).unwrap();
+
+        assert_eq!(reconstructed.profile_id(), public.profile_id());
+        assert_eq!(reconstructed.signing_key().as_ref(), public.signing_key().as_ref());
+        assert_eq!(reconstructed.encryption_key().as_ref(), public.encryption_key().as_ref());
+    }
+
+    #[test]
+    fn validate_accepts_a_freshly_built_profile() {
+        let profile = ProfileBuilder::new().name("alice").build().unwrap();
+        assert!(profile.public().validate().is_ok());
+    }
+
+    #[test]
+    fn validate_accepts_a_legacy_crc16_discriminant() {
+        use crate::discriminant::{discriminant, format_profile_id, DiscriminantScheme};
+
+        let profile = ProfileBuilder::new().name("alice").build().unwrap();
+        let mut public = profile.public();
+        let legacy = discriminant(DiscriminantScheme::Crc16, &public.signing_key, &public.encryption_key);
+        public.profile_id = ProfileId::new(format_profile_id("alice", &legacy));
+        public.discriminant_scheme = DiscriminantScheme::Crc16;
+
+        assert!(public.validate().is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_a_discriminant_for_the_wrong_keys() {
+        let profile = ProfileBuilder::new().name("alice").build().unwrap();
+        let mut public = profile.public();
+        public.profile_id = ProfileId::new("alice#00000000");
+
+        assert!(public.validate().is_err());
+    }
+
+    #[test]
+    fn from_b64_keys_rejects_malformed_base64() {
+        let profile = ProfileBuilder::new().name("alice").build().unwrap();
+        let public = profile.public();
+
+        assert!(PublicProfileData::from_b64_keys("alice#0001", "not valid base64!!", &public.encryption_key_b64()).is_err());
+    }
+
+    #[test]
+    fn blob_round_trips_a_public_profile() {
+        let profile = ProfileBuilder::new().name("alice").build().unwrap();
+        let public = profile.public();
+
+        let blob = public.to_blob().unwrap();
+        let decoded = PublicProfileData::from_blob(&blob).unwrap();
+
+        assert_eq!(decoded.profile_id(), public.profile_id());
+        assert_eq!(decoded.name(), public.name());
+        assert_eq!(decoded.signing_key().as_ref(), public.signing_key().as_ref());
+        assert_eq!(decoded.encryption_key().as_ref(), public.encryption_key().as_ref());
+    }
+
+    #[test]
+    fn from_blob_rejects_truncated_bytes() {
+        let profile = ProfileBuilder::new().name("alice").build().unwrap();
+        let blob = profile.public().to_blob().unwrap();
+
+        assert!(PublicProfileData::from_blob(&blob[..blob.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn from_blob_rejects_trailing_garbage() {
+        let profile = ProfileBuilder::new().name("alice").build().unwrap();
+        let mut blob = profile.public().to_blob().unwrap();
+        blob.push(0xff);
+
+        assert!(PublicProfileData::from_blob(&blob).is_err());
+    }
+
+    #[test]
+    fn relays_round_trip_through_a_blob_and_are_trusted_only_when_signed() {
+        let profile = ProfileBuilder::new().name("alice").relay("relay.example.com").build().unwrap();
+        let public = profile.public();
+        assert!(public.relay_is_trusted("relay.example.com"));
+        assert!(!public.relay_is_trusted("attacker-relay.example.com"));
+
+        let blob = public.to_blob().unwrap();
+        let decoded = PublicProfileData::from_blob(&blob).unwrap();
+        assert_eq!(decoded.relays(), public.relays());
+    }
+
+    #[test]
+    fn from_blob_rejects_a_discriminant_for_the_wrong_keys() {
+        let profile = ProfileBuilder::new().name("alice").build().unwrap();
+        let mut public = profile.public();
+        public.profile_id = ProfileId::new("alice#00000000");
+
+        let blob = public.to_blob().unwrap();
+        assert!(PublicProfileData::from_blob(&blob).is_err());
+    }
+
+    #[test]
+    fn to_uri_round_trips_through_decode_uri() {
+        let node_id = iroh::SecretKey::generate(&mut rand::thread_rng()).public();
+        let public = ProfileBuilder::new().name("alice").node_id(node_id).relay("https://relay.example.com").build().unwrap().public();
+
+        let uri = public.to_uri().unwrap();
+        assert!(uri.starts_with(RESOLUTION_URI_SCHEME));
+
+        let (decoded, decoded_node_id, relay) = decode_uri(&uri).unwrap();
+        assert_eq!(decoded.profile_id(), public.profile_id());
+        assert_eq!(decoded_node_id, node_id);
+        assert_eq!(relay.as_deref(), Some("https://relay.example.com"));
+    }
+
+    #[test]
+    fn to_uri_rejects_a_profile_with_no_dialable_node_id() {
+        let public = ProfileBuilder::new().name("alice").build().unwrap().public();
+        assert!(matches!(public.to_uri(), Err(crate::Error::User(UserError::NoDialableNodeId))));
+    }
+
+    #[test]
+    fn decode_uri_rejects_a_relay_spliced_in_after_signing() {
+        let node_id = iroh::SecretKey::generate(&mut rand::thread_rng()).public();
+        let public = ProfileBuilder::new().name("alice").node_id(node_id).build().unwrap().public();
+
+        let tampered = UriPayload { profile: public.to_blob().unwrap(), node_id: *node_id.as_bytes(), relay: Some("https://attacker-relay.example.com".to_string()) };
+        let encoded = crate::encoding::to_vec(&tampered).unwrap();
+        let uri = format!("{RESOLUTION_URI_SCHEME}{}", URL_SAFE_NO_PAD.encode(encoded));
+
+        assert!(matches!(decode_uri(&uri), Err(crate::Error::User(UserError::UntrustedRelay { .. }))));
+    }
+}
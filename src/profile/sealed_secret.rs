@@ -0,0 +1,176 @@
+//! Passphrase-encrypted local storage for a [`Profile`]'s secret key
+//! material, e.g. for saving it to disk between runs without keeping it
+//! in plaintext.
+
+use std::time::{Duration, Instant};
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::EncryptionContext;
+use crate::profile::device::DeviceInfo;
+use crate::profile::Profile;
+use crate::Result;
+
+/// Argon2id parameters used to derive the AES key that wraps a
+/// [`Profile`]'s secret key material. Devices vary widely in available
+/// memory and CPU, so these aren't hardcoded: [`Self::calibrate`] can
+/// pick parameters that hit a target unlock time on the device that will
+/// actually use them, and [`SealedProfile`] stores whatever parameters
+/// were used so [`SealedProfile::open`] can reproduce them without the
+/// caller needing to remember them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    /// The [`argon2`] crate's own recommended defaults.
+    fn default() -> Self {
+        let params = Params::default();
+        Self { memory_kib: params.m_cost(), iterations: params.t_cost(), parallelism: params.p_cost() }
+    }
+}
+
+impl KdfParams {
+    /// Benchmark increasingly expensive parameter sets, starting from
+    /// [`Self::default`], until one takes at least `target` to derive a
+    /// key — so a device that can afford it gets a slower, harder to
+    /// brute-force KDF instead of inheriting the one-size-fits-all
+    /// default. Scales `memory_kib` upward rather than `iterations`,
+    /// since Argon2id's memory-hardness (not raw compute) is what makes
+    /// it expensive to attack in parallel.
+    pub fn calibrate(target: Duration) -> Self {
+        let mut params = Self::default();
+        loop {
+            let started = Instant::now();
+            // Cost only depends on the parameters, not the passphrase or
+            // salt, so any fixed input benchmarks the real thing.
+            if params.derive_key(b"calibration passphrase", b"calibration-salt!").is_err() {
+                return params;
+            }
+            if started.elapsed() >= target || params.memory_kib >= 1 << 20 {
+                return params;
+            }
+            params.memory_kib = params.memory_kib.saturating_mul(2);
+        }
+    }
+
+    fn derive_key(&self, passphrase: &[u8], salt: &[u8]) -> Result<[u8; 32]> {
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, Some(32)).map_err(|e| anyhow::anyhow!(e.to_string()))?;
+        let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+        let mut key = [0u8; 32];
+        argon2.hash_password_into(passphrase, salt, &mut key).map_err(|e| anyhow::anyhow!(e.to_string()))?;
+        Ok(key)
+    }
+}
+
+/// A [`Profile`] with its secret key material encrypted under a
+/// passphrase-derived key, plus enough of its public fields to
+/// reconstruct it whole on [`Self::open`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SealedProfile {
+    name: String,
+    node_id: Option<iroh::NodeId>,
+    devices: Vec<DeviceInfo>,
+    relays: Vec<String>,
+    kdf: KdfParams,
+    salt: [u8; 16],
+    nonce: [u8; 12],
+    ciphertext: Vec<u8>,
+}
+
+impl Profile {
+    /// Encrypt this profile's secret key material under `passphrase`,
+    /// deriving the wrapping key with `kdf`. See [`SealedProfile::open`]
+    /// for the inverse.
+    pub fn seal(&self, passphrase: &str, kdf: KdfParams) -> Result<SealedProfile> {
+        let mut salt = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let key = kdf.derive_key(passphrase.as_bytes(), &salt)?;
+
+        let mut nonce = [0u8; 12];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let cipher = Aes256Gcm::new_from_slice(&key).map_err(|e| anyhow::anyhow!(e))?;
+        let secret_bytes = self.context.to_secret_bytes()?;
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), secret_bytes.as_slice())
+            .map_err(|_| anyhow::anyhow!("profile sealing failed"))?;
+
+        Ok(SealedProfile {
+            name: self.name.clone(),
+            node_id: self.node_id,
+            devices: self.devices.clone(),
+            relays: self.relays.clone(),
+            kdf,
+            salt,
+            nonce,
+            ciphertext,
+        })
+    }
+}
+
+impl SealedProfile {
+    /// Reverse [`Profile::seal`]. Fails if `passphrase` is wrong or the
+    /// sealed bytes were tampered with — either way the AEAD tag won't
+    /// verify.
+    pub fn open(&self, passphrase: &str) -> Result<Profile> {
+        let key = self.kdf.derive_key(passphrase.as_bytes(), &self.salt)?;
+        let cipher = Aes256Gcm::new_from_slice(&key).map_err(|e| anyhow::anyhow!(e))?;
+        let secret_bytes = cipher
+            .decrypt(Nonce::from_slice(&self.nonce), self.ciphertext.as_slice())
+            .map_err(|_| anyhow::anyhow!("wrong passphrase or corrupted sealed profile"))?;
+
+        let context = EncryptionContext::from_secret_bytes(&secret_bytes)?;
+        Ok(Profile { name: self.name.clone(), context, node_id: self.node_id, devices: self.devices.clone(), relays: self.relays.clone() })
+    }
+
+    /// The KDF parameters this profile was sealed with, e.g. so a caller
+    /// can display how expensive unlocking will be.
+    pub fn kdf_params(&self) -> KdfParams {
+        self.kdf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::ProfileBuilder;
+
+    #[test]
+    fn seal_then_open_round_trips_across_different_kdf_params() {
+        for kdf in [
+            KdfParams::default(),
+            KdfParams { memory_kib: 8 * 1024, iterations: 1, parallelism: 1 },
+            KdfParams { memory_kib: 19 * 1024, iterations: 3, parallelism: 2 },
+        ] {
+            let profile = ProfileBuilder::new().name("alice").build().unwrap();
+            let sealed = profile.seal("correct horse battery staple", kdf).unwrap();
+            assert_eq!(sealed.kdf_params(), kdf);
+
+            let opened = sealed.open("correct horse battery staple").unwrap();
+            assert_eq!(opened.name(), "alice");
+            assert_eq!(opened.context().signing_public_key().as_ref(), profile.context().signing_public_key().as_ref());
+            assert_eq!(opened.context().encryption_public_key().as_ref(), profile.context().encryption_public_key().as_ref());
+        }
+    }
+
+    #[test]
+    fn open_rejects_the_wrong_passphrase() {
+        let profile = ProfileBuilder::new().name("alice").build().unwrap();
+        let sealed = profile.seal("correct horse battery staple", KdfParams { memory_kib: 8 * 1024, iterations: 1, parallelism: 1 }).unwrap();
+
+        assert!(sealed.open("wrong passphrase").is_err());
+    }
+
+    #[test]
+    fn calibrate_returns_parameters_at_least_as_expensive_as_the_default() {
+        let calibrated = KdfParams::calibrate(Duration::from_millis(1));
+        assert!(calibrated.memory_kib >= KdfParams::default().memory_kib);
+    }
+}
@@ -0,0 +1,185 @@
+//! Time-bounded, signed publication of a profile.
+//!
+//! A [`PublicProfileData`] on its own is trusted forever once pinned. A
+//! [`SignedProfile`] adds an optional expiry so a compromised or rotated
+//! key only has a limited window to keep circulating before contacts are
+//! expected to fetch a fresh copy.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::clock::{Clock, SystemClock};
+use crate::crypto::{domain, verify_detached, EncryptionContext};
+use crate::error::UserError;
+use crate::profile::PublicProfileData;
+use crate::Result;
+
+/// How much clock skew between publisher and verifier we tolerate before
+/// treating a profile as expired. Generous on purpose: profiles are meant
+/// to be re-published well ahead of their `valid_until`, so this only
+/// matters for verifiers with a badly wrong clock.
+pub const CLOCK_SKEW_TOLERANCE: Duration = Duration::from_secs(300);
+
+/// A [`PublicProfileData`] snapshot signed by its own signing key, with an
+/// optional expiry. `Serialize`/`Deserialize` so it can travel as a
+/// [`crate::message::Message::ProfileAnnouncement`]; a receiver still has
+/// to call [`Self::verify`] before trusting anything in it, the same as a
+/// `SignedProfile` loaded from any other untrusted source.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedProfile {
+    profile: PublicProfileData,
+    valid_until: Option<u64>,
+    signature: Vec<u8>,
+}
+
+impl SignedProfile {
+    pub(crate) fn sign(context: &EncryptionContext, profile: PublicProfileData, valid_until: Option<u64>) -> Result<Self> {
+        let signature = context.sign_detached(domain::SIGNED_PROFILE, &signing_bytes(&profile, valid_until)?)?;
+        Ok(Self { profile, valid_until, signature })
+    }
+
+    /// Verify the signature over this profile and, unless `ignore_expiry`
+    /// is set, that it hasn't passed `valid_until` (plus
+    /// [`CLOCK_SKEW_TOLERANCE`]). Returns the verified profile.
+    ///
+    /// Uses the real wall clock; see [`Self::verify_at`] to supply a
+    /// [`Clock`] instead, e.g. a [`crate::clock::MockClock`] in tests.
+    pub fn verify(&self, ignore_expiry: bool) -> Result<&PublicProfileData> {
+        self.verify_at(ignore_expiry, &SystemClock)
+    }
+
+    /// As [`Self::verify`], but checking expiry against `clock` instead of
+    /// the real wall clock.
+    pub fn verify_at(&self, ignore_expiry: bool, clock: &dyn Clock) -> Result<&PublicProfileData> {
+        verify_detached(domain::SIGNED_PROFILE, &signing_bytes(&self.profile, self.valid_until)?, &self.signature, self.profile.signing_key())?;
+
+        if !ignore_expiry {
+            if let Some(valid_until) = self.valid_until {
+                if clock.now_unix().saturating_sub(CLOCK_SKEW_TOLERANCE.as_secs()) > valid_until {
+                    return Err(UserError::ProfileExpired { valid_until }.into());
+                }
+            }
+        }
+
+        Ok(&self.profile)
+    }
+
+    pub fn valid_until(&self) -> Option<u64> {
+        self.valid_until
+    }
+
+    /// Verify many profiles at once, e.g. a contact backup pulled in as a
+    /// roster sync. Runs the (relatively expensive) signature checks
+    /// across the `rayon` global thread pool instead of serially, one per
+    /// core. A failure on one profile doesn't stop the others: the result
+    /// at index `i` corresponds to `profiles[i]`, `Ok` or `Err`
+    /// independently.
+    ///
+    /// Beyond the signature and expiry checks [`Self::verify`] already
+    /// does, each entry also re-runs [`PublicProfileData::validate`], so a
+    /// profile whose id doesn't match its own key material is rejected
+    /// even if it was otherwise signed correctly.
+    pub fn verify_batch(profiles: &[SignedProfile]) -> Vec<Result<PublicProfileData>> {
+        profiles
+            .par_iter()
+            .map(|signed| {
+                let profile = signed.verify(false)?;
+                profile.validate()?;
+                Ok(profile.clone())
+            })
+            .collect()
+    }
+}
+
+fn signing_bytes(profile: &PublicProfileData, valid_until: Option<u64>) -> Result<Vec<u8>> {
+    let mut bytes = crate::encoding::to_vec(profile)?;
+    if let Some(valid_until) = valid_until {
+        bytes.extend_from_slice(&valid_until.to_be_bytes());
+    }
+    Ok(bytes)
+}
+
+fn now_unix() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::ProfileBuilder;
+
+    #[test]
+    fn just_expired_profile_is_rejected() {
+        let profile = ProfileBuilder::new().name("alice").build().unwrap();
+        let signed = SignedProfile::sign(profile.context(), profile.public(), Some(now_unix() - CLOCK_SKEW_TOLERANCE.as_secs() - 1)).unwrap();
+
+        assert!(signed.verify(false).is_err());
+        assert!(signed.verify(true).is_ok());
+    }
+
+    #[test]
+    fn far_future_expiry_is_accepted() {
+        let profile = ProfileBuilder::new().name("alice").build().unwrap();
+        let signed = SignedProfile::sign(profile.context(), profile.public(), Some(now_unix() + 3600)).unwrap();
+
+        assert!(signed.verify(false).is_ok());
+    }
+
+    #[test]
+    fn no_expiry_never_rejected_for_staleness() {
+        let profile = ProfileBuilder::new().name("alice").build().unwrap();
+        let signed = SignedProfile::sign(profile.context(), profile.public(), None).unwrap();
+
+        assert!(signed.verify(false).is_ok());
+    }
+
+    #[test]
+    fn mock_clock_drives_expiry_deterministically() {
+        use crate::clock::MockClock;
+
+        let profile = ProfileBuilder::new().name("alice").build().unwrap();
+        let signed = SignedProfile::sign(profile.context(), profile.public(), Some(1_000)).unwrap();
+        let clock = MockClock::at(1_000);
+
+        // Right at `valid_until`, still within the skew tolerance.
+        assert!(signed.verify_at(false, &clock).is_ok());
+
+        // Past `valid_until` by more than the tolerance: now expired, with
+        // no wall clock or sleep involved.
+        clock.advance(CLOCK_SKEW_TOLERANCE.as_secs() + 1);
+        assert!(signed.verify_at(false, &clock).is_err());
+        assert!(signed.verify_at(true, &clock).is_ok());
+    }
+
+    #[test]
+    fn verify_batch_reports_per_index_outcomes_for_a_mix_of_valid_and_tampered_profiles() {
+        let alice = ProfileBuilder::new().name("alice").build().unwrap();
+        let alice_signed = SignedProfile::sign(alice.context(), alice.public(), None).unwrap();
+
+        let bob = ProfileBuilder::new().name("bob").build().unwrap();
+        let mut bob_signed = SignedProfile::sign(bob.context(), bob.public(), None).unwrap();
+        bob_signed.profile.name = "mallory".to_string();
+
+        let carol = ProfileBuilder::new().name("carol").build().unwrap();
+        let carol_signed = SignedProfile::sign(carol.context(), carol.public(), None).unwrap();
+
+        let results = SignedProfile::verify_batch(&[alice_signed, bob_signed, carol_signed]);
+
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        assert!(results[2].is_ok());
+    }
+
+    #[test]
+    fn tampering_with_relays_after_signing_fails_verification() {
+        let profile = ProfileBuilder::new().name("alice").relay("relay.example.com").build().unwrap();
+        let mut signed = SignedProfile::sign(profile.context(), profile.public(), None).unwrap();
+
+        signed.profile.relays.push("attacker-relay.example.com".to_string());
+
+        assert!(signed.verify(false).is_err());
+    }
+}
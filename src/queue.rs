@@ -0,0 +1,100 @@
+//! Persistent outbound delivery queue.
+//!
+//! When a peer is unreachable, messages are queued and retried instead of
+//! dropped. Delivery is at-least-once: the receiver is responsible for
+//! deduplicating by [`MessageId`], since a redelivered envelope after a
+//! reconnect is indistinguishable from a fresh one on the wire.
+
+use crate::identity::{MessageId, ProfileId};
+use crate::store::{MemoryStore, Store};
+use crate::Result;
+
+/// A single queued, already-encrypted envelope waiting to be sent.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct QueuedEnvelope {
+    pub message_id: MessageId,
+    pub recipient: ProfileId,
+    pub bytes: Vec<u8>,
+}
+
+/// Queues encrypted envelopes for recipients that can't currently be
+/// reached, and hands them back out once a session reconnects. Backed by a
+/// [`Store`] rather than a bespoke storage trait, so a caller who needs
+/// queued messages to survive a restart can plug in
+/// [`crate::store::FilesystemStore`] instead of the in-memory default.
+/// Keyed by `message_id.to_string()` rather than [`MessageId`] itself,
+/// since [`crate::store::FilesystemStore`] only implements `Store<String, _>`.
+pub struct OutboundQueue<S: Store<String, QueuedEnvelope> = MemoryStore<String, QueuedEnvelope>> {
+    storage: S,
+}
+
+impl OutboundQueue<MemoryStore<String, QueuedEnvelope>> {
+    pub fn new() -> Self {
+        Self { storage: MemoryStore::default() }
+    }
+}
+
+impl Default for OutboundQueue<MemoryStore<String, QueuedEnvelope>> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Store<String, QueuedEnvelope>> OutboundQueue<S> {
+    /// Use a specific [`Store`] backend instead of the in-memory default.
+    pub fn with_store(storage: S) -> Self {
+        Self { storage }
+    }
+
+    /// Persist an envelope for later delivery.
+    pub fn enqueue(&self, recipient: ProfileId, message_id: MessageId, bytes: Vec<u8>) -> Result<()> {
+        self.storage.put(message_id.to_string(), QueuedEnvelope { message_id, recipient, bytes })
+    }
+
+    /// Return everything currently queued for `recipient`, e.g. to send on
+    /// reconnect. Does not remove anything; call [`OutboundQueue::ack`] once
+    /// the peer has confirmed receipt.
+    pub fn drain_ready(&self, recipient: &ProfileId) -> Result<Vec<QueuedEnvelope>> {
+        Ok(self.storage.list()?.into_iter().map(|(_, envelope)| envelope).filter(|e| &e.recipient == recipient).collect())
+    }
+
+    /// Remove a delivered envelope from the queue.
+    pub fn ack(&self, message_id: MessageId) -> Result<()> {
+        self.storage.delete(&message_id.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drain_returns_enqueued_messages_and_ack_removes_them() {
+        let queue = OutboundQueue::new();
+        let recipient = ProfileId::from("alice#1234");
+        let id = MessageId([1u8; 16]);
+
+        queue.enqueue(recipient.clone(), id, b"hello".to_vec()).unwrap();
+        let pending = queue.drain_ready(&recipient).unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].bytes, b"hello");
+
+        queue.ack(id).unwrap();
+        assert!(queue.drain_ready(&recipient).unwrap().is_empty());
+    }
+
+    #[test]
+    fn outbound_queue_works_against_a_filesystem_backed_store() {
+        use crate::store::FilesystemStore;
+
+        let dir = std::env::temp_dir().join(format!("resolution-protocol-queue-test-{}", uuid::Uuid::new_v4()));
+        let queue = OutboundQueue::with_store(FilesystemStore::new(&dir).unwrap());
+        let recipient = ProfileId::from("alice#1234");
+        let id = MessageId([2u8; 16]);
+
+        queue.enqueue(recipient.clone(), id, b"hello".to_vec()).unwrap();
+        assert_eq!(queue.drain_ready(&recipient).unwrap().len(), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
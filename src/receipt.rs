@@ -0,0 +1,180 @@
+//! Signed delivery receipts, for applications that need non-repudiable
+//! proof a specific peer received a specific message — as opposed to a
+//! transport-level delivery ack, which only tells the sender bytes
+//! arrived and disappears once the connection that carried it is gone. A
+//! `Receipt` is signed by the receiver over the message id and a
+//! timestamp, so the sender can persist it and later present it to a
+//! third party without that party having to trust the sender's word for
+//! it.
+
+use serde::{Deserialize, Serialize};
+
+use crate::clock::{Clock, SystemClock};
+use crate::crypto::{EncryptionContext, Signed};
+use crate::error::Error;
+use crate::identity::MessageId;
+use crate::profile::PublicProfileData;
+use crate::Result;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ReceiptBody {
+    message_id: MessageId,
+    received_at: u64,
+}
+
+/// Proof that a specific peer received a specific message at a specific
+/// time, signed by the receiver's signing key. Built with
+/// [`Self::new`]/[`Self::new_at`] by the receiver, then handed to the
+/// sender to keep; the sender verifies it later with [`Self::verify`]
+/// against the receiver's [`PublicProfileData`] and the message id it's
+/// checking for.
+#[derive(Debug, Clone)]
+pub struct Receipt {
+    signed: Signed<ReceiptBody>,
+}
+
+impl Receipt {
+    /// Sign a receipt for `message_id`, stamped with the current time.
+    pub fn new(context: &EncryptionContext, message_id: MessageId) -> Result<Self> {
+        Self::new_at(context, message_id, &SystemClock)
+    }
+
+    /// As [`Self::new`], but with the timestamp taken from `clock` instead
+    /// of the real wall clock — e.g. a [`crate::clock::MockClock`] in
+    /// tests.
+    pub fn new_at(context: &EncryptionContext, message_id: MessageId, clock: &dyn Clock) -> Result<Self> {
+        let signed = Signed::new(context, &ReceiptBody { message_id, received_at: clock.now_unix() })?;
+        Ok(Self { signed })
+    }
+
+    /// Verify this receipt was signed by `receiver` and covers
+    /// `message_id`, returning the unix time the receiver claimed to have
+    /// received it at. A receipt that verifies but names a different
+    /// message id is rejected with [`Error::ReceiptMessageMismatch`]
+    /// rather than treated as valid for the one being checked.
+    pub fn verify(&self, receiver: &PublicProfileData, message_id: MessageId) -> Result<u64> {
+        let body: ReceiptBody = self.signed.verify(receiver.signing_key())?;
+        if body.message_id != message_id {
+            return Err(Error::ReceiptMessageMismatch { expected: message_id, got: body.message_id });
+        }
+        Ok(body.received_at)
+    }
+
+    /// Encode as bytes suitable for transmission back to the sender or for
+    /// storage in a [`ReceiptLedger`].
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        self.signed.to_bytes()
+    }
+
+    /// Inverse of [`Self::to_bytes`]. Doesn't verify the signature yet —
+    /// call [`Self::verify`] for that once you have the receiver's public
+    /// profile.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Ok(Self { signed: Signed::from_bytes(bytes)? })
+    }
+}
+
+/// Storage backend for [`ReceiptLedger`]. Implementors decide durability;
+/// the ledger itself only knows how to record and look receipts up by the
+/// message they cover.
+pub trait ReceiptStorage: Send + Sync {
+    fn put(&self, message_id: MessageId, bytes: Vec<u8>) -> Result<()>;
+    fn get(&self, message_id: &MessageId) -> Result<Option<Vec<u8>>>;
+}
+
+/// A [`ReceiptStorage`] that keeps everything in memory. Useful for tests
+/// and for applications that don't need receipts to survive a restart.
+#[derive(Default)]
+pub struct MemoryReceiptStorage {
+    inner: std::sync::Mutex<std::collections::HashMap<MessageId, Vec<u8>>>,
+}
+
+impl ReceiptStorage for MemoryReceiptStorage {
+    fn put(&self, message_id: MessageId, bytes: Vec<u8>) -> Result<()> {
+        self.inner.lock().unwrap().insert(message_id, bytes);
+        Ok(())
+    }
+
+    fn get(&self, message_id: &MessageId) -> Result<Option<Vec<u8>>> {
+        Ok(self.inner.lock().unwrap().get(message_id).cloned())
+    }
+}
+
+/// Persists [`Receipt`]s so a sender can present proof of delivery long
+/// after the session that carried it has closed.
+pub struct ReceiptLedger<S: ReceiptStorage = MemoryReceiptStorage> {
+    storage: S,
+}
+
+impl<S: ReceiptStorage> ReceiptLedger<S> {
+    pub fn new(storage: S) -> Self {
+        Self { storage }
+    }
+
+    /// Persist `receipt` under the message id it covers.
+    pub fn record(&self, message_id: MessageId, receipt: &Receipt) -> Result<()> {
+        self.storage.put(message_id, receipt.to_bytes()?)
+    }
+
+    /// Look up the receipt stored for `message_id`, if any. Doesn't verify
+    /// it — call [`Receipt::verify`] once you have the receiver's public
+    /// profile.
+    pub fn get(&self, message_id: &MessageId) -> Result<Option<Receipt>> {
+        self.storage.get(message_id)?.map(|bytes| Receipt::from_bytes(&bytes)).transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::profile::ProfileBuilder;
+
+    fn sample_message_id() -> MessageId {
+        MessageId([7u8; 16])
+    }
+
+    #[test]
+    fn a_receipt_verifies_against_the_signers_public_profile_and_message_id() {
+        let receiver = ProfileBuilder::new().name("bob").build().unwrap();
+        let message_id = sample_message_id();
+
+        let receipt = Receipt::new_at(receiver.context(), message_id, &MockClock::at(1_000)).unwrap();
+        let received_at = receipt.verify(&receiver.public(), message_id).unwrap();
+
+        assert_eq!(received_at, 1_000);
+    }
+
+    #[test]
+    fn a_receipt_for_a_different_message_id_is_rejected() {
+        let receiver = ProfileBuilder::new().name("bob").build().unwrap();
+        let receipt = Receipt::new_at(receiver.context(), sample_message_id(), &MockClock::at(1_000)).unwrap();
+
+        let err = receipt.verify(&receiver.public(), MessageId([9u8; 16])).unwrap_err();
+        assert!(matches!(err, Error::ReceiptMessageMismatch { .. }));
+    }
+
+    #[test]
+    fn a_receipt_from_an_impostor_fails_verification() {
+        let receiver = ProfileBuilder::new().name("bob").build().unwrap();
+        let impostor = ProfileBuilder::new().name("mallory").build().unwrap();
+        let message_id = sample_message_id();
+
+        let receipt = Receipt::new_at(impostor.context(), message_id, &MockClock::at(1_000)).unwrap();
+        assert!(receipt.verify(&receiver.public(), message_id).is_err());
+    }
+
+    #[test]
+    fn a_stored_receipt_round_trips_through_the_ledger_and_still_verifies() {
+        let receiver = ProfileBuilder::new().name("bob").build().unwrap();
+        let message_id = sample_message_id();
+
+        let receipt = Receipt::new_at(receiver.context(), message_id, &MockClock::at(1_000)).unwrap();
+        let ledger = ReceiptLedger::new(MemoryReceiptStorage::default());
+        ledger.record(message_id, &receipt).unwrap();
+
+        let stored = ledger.get(&message_id).unwrap().expect("receipt should be stored");
+        let received_at = stored.verify(&receiver.public(), message_id).unwrap();
+        assert_eq!(received_at, 1_000);
+    }
+}
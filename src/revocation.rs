@@ -0,0 +1,115 @@
+//! Tracking which profiles have been revoked, e.g. after a reported key
+//! compromise or a device being retired.
+//!
+//! Checking a revocation before trusting a peer is left to the caller
+//! (nothing in this crate consults [`RevocationStore`] yet) — this only
+//! provides the record itself. A caller that rejects a revoked peer's
+//! message should emit [`crate::audit::SecurityEvent::RevocationHit`].
+
+use serde::{Deserialize, Serialize};
+
+use crate::identity::ProfileId;
+use crate::store::{MemoryStore, Store};
+use crate::Result;
+
+/// Why and when a profile was revoked.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevocationRecord {
+    pub reason: String,
+    /// Unix seconds the revocation was recorded.
+    pub revoked_at: u64,
+}
+
+/// The set of profiles this device no longer trusts. Backed by a [`Store`]
+/// rather than a bare `HashMap`, like [`crate::pinning::KeyPinStore`], so a
+/// caller who needs revocations to survive a restart can plug in
+/// [`crate::store::FilesystemStore`] instead of the in-memory default.
+pub struct RevocationStore<S: Store<String, RevocationRecord> = MemoryStore<String, RevocationRecord>> {
+    store: S,
+}
+
+impl RevocationStore<MemoryStore<String, RevocationRecord>> {
+    pub fn new() -> Self {
+        Self { store: MemoryStore::default() }
+    }
+}
+
+impl Default for RevocationStore<MemoryStore<String, RevocationRecord>> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Store<String, RevocationRecord>> RevocationStore<S> {
+    /// Use a specific [`Store`] backend instead of the in-memory default.
+    pub fn with_store(store: S) -> Self {
+        Self { store }
+    }
+
+    /// Record `profile_id` as revoked. Overwrites any existing record for
+    /// the same profile.
+    pub fn revoke(&self, profile_id: &ProfileId, reason: impl Into<String>, revoked_at: u64) -> Result<()> {
+        self.store.put(profile_id.to_string(), RevocationRecord { reason: reason.into(), revoked_at })
+    }
+
+    /// Undo a revocation, e.g. because it turned out to be a false alarm.
+    pub fn unrevoke(&self, profile_id: &ProfileId) -> Result<()> {
+        self.store.delete(&profile_id.to_string())
+    }
+
+    pub fn is_revoked(&self, profile_id: &ProfileId) -> bool {
+        matches!(self.store.get(&profile_id.to_string()), Ok(Some(_)))
+    }
+
+    pub fn record(&self, profile_id: &ProfileId) -> Result<Option<RevocationRecord>> {
+        self.store.get(&profile_id.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile_id() -> ProfileId {
+        ProfileId::new("alice#0001")
+    }
+
+    #[test]
+    fn a_profile_is_not_revoked_until_revoke_is_called() {
+        let revocations = RevocationStore::new();
+        assert!(!revocations.is_revoked(&profile_id()));
+
+        revocations.revoke(&profile_id(), "reported key compromise", 1_000).unwrap();
+        assert!(revocations.is_revoked(&profile_id()));
+    }
+
+    #[test]
+    fn unrevoke_reverses_a_revocation() {
+        let revocations = RevocationStore::new();
+        revocations.revoke(&profile_id(), "reported key compromise", 1_000).unwrap();
+        revocations.unrevoke(&profile_id()).unwrap();
+        assert!(!revocations.is_revoked(&profile_id()));
+    }
+
+    #[test]
+    fn record_returns_the_reason_and_timestamp() {
+        let revocations = RevocationStore::new();
+        revocations.revoke(&profile_id(), "reported key compromise", 1_000).unwrap();
+        let record = revocations.record(&profile_id()).unwrap().unwrap();
+        assert_eq!(record.reason, "reported key compromise");
+        assert_eq!(record.revoked_at, 1_000);
+    }
+
+    #[test]
+    fn revocation_store_works_against_a_filesystem_backed_store() {
+        use crate::store::FilesystemStore;
+
+        let dir = std::env::temp_dir().join(format!("resolution-protocol-revocation-test-{}", uuid::Uuid::new_v4()));
+        let revocations = RevocationStore::with_store(FilesystemStore::new(&dir).unwrap());
+
+        revocations.revoke(&profile_id(), "reported key compromise", 1_000).unwrap();
+        assert!(revocations.is_revoked(&profile_id()));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
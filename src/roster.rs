@@ -0,0 +1,283 @@
+//! Roster of known contacts.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::clock::{Clock, SystemClock};
+use crate::identity::ProfileId;
+use crate::profile::PublicProfileData;
+
+/// The set of contacts a profile knows about.
+///
+/// `last_seen` is tracked separately from the contacts themselves: it's
+/// local, advisory bookkeeping (updated when a session connects or a
+/// presence beacon is observed) and must never be taken from data a peer
+/// sends us.
+#[derive(Debug, Default)]
+pub struct Roster {
+    contacts: HashMap<ProfileId, PublicProfileData>,
+    last_seen: Mutex<HashMap<ProfileId, u64>>,
+}
+
+/// Wire representation of [`Roster`] for local persistence.
+#[derive(Serialize, Deserialize)]
+struct RosterWire {
+    contacts: Vec<PublicProfileData>,
+    last_seen: HashMap<ProfileId, u64>,
+}
+
+impl Serialize for Roster {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        RosterWire {
+            contacts: self.contacts.values().cloned().collect(),
+            last_seen: self.last_seen.lock().unwrap().clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Roster {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let wire = RosterWire::deserialize(deserializer)?;
+        Ok(Roster {
+            contacts: wire.contacts.into_iter().map(|c| (c.profile_id().clone(), c)).collect(),
+            last_seen: Mutex::new(wire.last_seen),
+        })
+    }
+}
+
+impl Roster {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, contact: PublicProfileData) {
+        self.contacts.insert(contact.profile_id().clone(), contact);
+    }
+
+    pub fn get(&self, profile_id: &ProfileId) -> Option<&PublicProfileData> {
+        self.contacts.get(profile_id)
+    }
+
+    pub fn profile_ids(&self) -> impl Iterator<Item = ProfileId> + '_ {
+        self.contacts.keys().cloned()
+    }
+
+    /// Record that `profile_id` was just observed, e.g. by a session
+    /// connecting or a presence beacon. A no-op for unknown contacts.
+    ///
+    /// Uses the real wall clock; see [`Self::touch_at`] to supply a
+    /// [`Clock`] instead, e.g. a [`crate::clock::MockClock`] in tests.
+    pub fn touch(&self, profile_id: &ProfileId) {
+        self.touch_at(profile_id, &SystemClock);
+    }
+
+    /// As [`Self::touch`], but recording `clock`'s time instead of the
+    /// real wall clock.
+    pub fn touch_at(&self, profile_id: &ProfileId, clock: &dyn Clock) {
+        if !self.contacts.contains_key(profile_id) {
+            return;
+        }
+        self.last_seen.lock().unwrap().insert(profile_id.clone(), clock.now_unix());
+    }
+
+    /// When `profile_id` was last observed, if ever.
+    pub fn last_seen(&self, profile_id: &ProfileId) -> Option<SystemTime> {
+        let secs = *self.last_seen.lock().unwrap().get(profile_id)?;
+        Some(UNIX_EPOCH + std::time::Duration::from_secs(secs))
+    }
+
+    /// Merge `other`'s contacts and last-seen bookkeeping into `self`,
+    /// last-writer-wins by `last_seen`: reconciling the same identity
+    /// restored on two devices that have each accumulated local roster
+    /// state independently. A contact present only in `other` is adopted
+    /// outright. A contact present in both, with identical data, is a
+    /// no-op beyond reconciling `last_seen`. A contact present in both
+    /// with *different* data (e.g. `other` observed a name change or key
+    /// rotation `self` hasn't seen yet) keeps whichever side's
+    /// `last_seen` is more recent; a tie, or a missing timestamp on
+    /// either side, favors keeping `self`'s existing entry over churning
+    /// on no real evidence either way.
+    ///
+    /// Unlike [`crate::pinning::KeyPinStore::merge`], this never reports
+    /// a conflict: cached contact metadata isn't itself a trust boundary
+    /// (pinning is), so silently picking a side here is an acceptable
+    /// outcome.
+    pub fn merge(&mut self, other: &Roster) {
+        let other_last_seen = other.last_seen.lock().unwrap().clone();
+
+        for (profile_id, contact) in &other.contacts {
+            let other_seen = other_last_seen.get(profile_id).copied();
+            let self_seen = self.last_seen.lock().unwrap().get(profile_id).copied();
+
+            let adopt_other = match self.contacts.get(profile_id) {
+                None => true,
+                Some(existing) => {
+                    existing.to_blob().ok() != contact.to_blob().ok()
+                        && match (self_seen, other_seen) {
+                            (Some(self_seen), Some(other_seen)) => other_seen > self_seen,
+                            _ => false,
+                        }
+                }
+            };
+            if adopt_other {
+                self.contacts.insert(profile_id.clone(), contact.clone());
+            }
+
+            if let Some(seen) = other_seen {
+                let mut mine = self.last_seen.lock().unwrap();
+                if mine.get(profile_id).is_none_or(|&existing| seen > existing) {
+                    mine.insert(profile_id.clone(), seen);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::ProfileBuilder;
+
+    fn sample_contact() -> PublicProfileData {
+        ProfileBuilder::new().name("alice").build().unwrap().public()
+    }
+
+    #[test]
+    fn touch_sets_last_seen_for_known_contact() {
+        let mut roster = Roster::new();
+        let contact = sample_contact();
+        let profile_id = contact.profile_id().clone();
+        roster.insert(contact);
+
+        assert!(roster.last_seen(&profile_id).is_none());
+        roster.touch(&profile_id);
+        assert!(roster.last_seen(&profile_id).is_some());
+    }
+
+    #[test]
+    fn touch_ignores_unknown_contact() {
+        let roster = Roster::new();
+        let unknown = ProfileId::from("ghost#0000");
+        roster.touch(&unknown);
+        assert!(roster.last_seen(&unknown).is_none());
+    }
+
+    #[test]
+    fn touch_at_records_the_given_clocks_time() {
+        use crate::clock::MockClock;
+
+        let mut roster = Roster::new();
+        let contact = sample_contact();
+        let profile_id = contact.profile_id().clone();
+        roster.insert(contact);
+
+        let clock = MockClock::at(1_000);
+        roster.touch_at(&profile_id, &clock);
+        assert_eq!(roster.last_seen(&profile_id), Some(UNIX_EPOCH + std::time::Duration::from_secs(1_000)));
+
+        clock.advance(60);
+        roster.touch_at(&profile_id, &clock);
+        assert_eq!(roster.last_seen(&profile_id), Some(UNIX_EPOCH + std::time::Duration::from_secs(1_060)));
+    }
+
+    #[test]
+    fn merge_adopts_a_contact_only_present_in_the_other_roster() {
+        use crate::clock::MockClock;
+
+        let mut local = Roster::new();
+        let mut remote = Roster::new();
+        let contact = sample_contact();
+        let profile_id = contact.profile_id().clone();
+        remote.insert(contact);
+        remote.touch_at(&profile_id, &MockClock::at(1_000));
+
+        local.merge(&remote);
+        assert!(local.get(&profile_id).is_some());
+        assert_eq!(local.last_seen(&profile_id), Some(UNIX_EPOCH + std::time::Duration::from_secs(1_000)));
+    }
+
+    /// Two [`PublicProfileData`] sharing a profile_id (same name and
+    /// keys) but disagreeing on `relays`, for exercising [`Roster::merge`]'s
+    /// conflicting-data path.
+    fn same_identity_disagreeing_on_relays() -> (PublicProfileData, PublicProfileData) {
+        use crate::crypto::EncryptionContext;
+        use crate::profile::ProfileBuilder;
+
+        let secret_bytes = EncryptionContext::generate().unwrap().to_secret_bytes().unwrap();
+        let without_relay = ProfileBuilder::new()
+            .identity("alice", EncryptionContext::from_secret_bytes(&secret_bytes).unwrap())
+            .build()
+            .unwrap()
+            .public();
+        let with_relay = ProfileBuilder::new()
+            .identity("alice", EncryptionContext::from_secret_bytes(&secret_bytes).unwrap())
+            .relay("relay://fresh.example")
+            .build()
+            .unwrap()
+            .public();
+        (without_relay, with_relay)
+    }
+
+    #[test]
+    fn merge_keeps_the_more_recently_seen_sides_data_on_disagreement() {
+        use crate::clock::MockClock;
+
+        let (stale, fresh) = same_identity_disagreeing_on_relays();
+        let profile_id = stale.profile_id().clone();
+        assert_eq!(&profile_id, fresh.profile_id());
+
+        let mut local = Roster::new();
+        local.insert(stale);
+        local.touch_at(&profile_id, &MockClock::at(1_000));
+
+        let mut remote = Roster::new();
+        remote.insert(fresh.clone());
+        remote.touch_at(&profile_id, &MockClock::at(2_000));
+
+        local.merge(&remote);
+        assert_eq!(local.get(&profile_id).unwrap().to_blob().unwrap(), fresh.to_blob().unwrap());
+        assert_eq!(local.last_seen(&profile_id), Some(UNIX_EPOCH + std::time::Duration::from_secs(2_000)));
+    }
+
+    #[test]
+    fn merge_keeps_self_when_self_has_no_last_seen_but_other_does() {
+        use crate::clock::MockClock;
+
+        let (mine, theirs) = same_identity_disagreeing_on_relays();
+        let profile_id = mine.profile_id().clone();
+
+        let mut local = Roster::new();
+        local.insert(mine.clone());
+        // local never touches this contact, so self_seen is None.
+
+        let mut remote = Roster::new();
+        remote.insert(theirs);
+        remote.touch_at(&profile_id, &MockClock::at(1_000));
+
+        local.merge(&remote);
+        assert_eq!(local.get(&profile_id).unwrap().to_blob().unwrap(), mine.to_blob().unwrap());
+    }
+
+    #[test]
+    fn merge_keeps_self_on_a_last_seen_tie() {
+        use crate::clock::MockClock;
+
+        let (mine, theirs) = same_identity_disagreeing_on_relays();
+        let profile_id = mine.profile_id().clone();
+
+        let mut local = Roster::new();
+        local.insert(mine.clone());
+        local.touch_at(&profile_id, &MockClock::at(1_000));
+
+        let mut remote = Roster::new();
+        remote.insert(theirs);
+        remote.touch_at(&profile_id, &MockClock::at(1_000));
+
+        local.merge(&remote);
+        assert_eq!(local.get(&profile_id).unwrap().to_blob().unwrap(), mine.to_blob().unwrap());
+    }
+}
@@ -0,0 +1,145 @@
+//! Executor-specific primitives (task spawning, timers) this crate needs,
+//! kept behind a small [`Runtime`] trait instead of calling
+//! `tokio::spawn`/`tokio::time::sleep` directly at every use site. This
+//! module's [`spawn`] and [`sleep`] free functions are what the rest of
+//! the crate calls; which [`Runtime`] they dispatch to is picked at
+//! compile time by the `smol-runtime` feature.
+//!
+//! **This does not make the crate tokio-free.** [`crate::endpoint`]'s
+//! transport is built on `iroh`, whose connection and stream types run on
+//! tokio's IO reactor internally — there's no supported way to drive them
+//! from another executor, `smol-runtime` or not. This crate's own
+//! `tokio::sync::{Mutex, mpsc}` channels ([`crate::session::stream`]) and
+//! `tokio_util::sync::CancellationToken` ([`crate::session::handshake`],
+//! [`crate::session::pool`]) are unaffected by this module too — swapping
+//! those out would mean giving up tokio's channel/lock primitives
+//! entirely, a much larger change than the two things named in the
+//! request this module addresses (spawning, timers). What `smol-runtime`
+//! actually buys an application built around `smol`: its own background
+//! work and this crate's handshake-timeout, stream-dispatch, and
+//! presence-polling tasks all run on the `smol` executor instead of
+//! silently requiring a second, separate tokio runtime just for those
+//! call sites — as long as
+//! *something* in the process still drives a tokio reactor for `iroh`'s
+//! sake (e.g. `smol`'s own tokio-compat shims, or a small dedicated
+//! tokio runtime).
+//!
+//! Enable with `--features smol-runtime` (mutually exclusive with the
+//! default tokio-backed [`Runtime`]).
+
+use std::future::Future;
+use std::time::Duration;
+
+/// The subset of an async executor this crate needs. Implement this to
+/// plug in an executor other than the two this crate ships
+/// ([`TokioRuntime`], or [`SmolRuntime`] behind the `smol-runtime`
+/// feature) — nothing else in this module assumes one of those two.
+pub trait Runtime {
+    /// Run `future` in the background, detached: nothing in this crate
+    /// waits on its result, matching how [`crate::session::stream`]'s
+    /// stream-dispatch loop and would-be future callers use it.
+    fn spawn<F>(future: F)
+    where
+        F: Future<Output = ()> + Send + 'static;
+
+    /// Complete after `duration` has elapsed.
+    fn sleep(duration: Duration) -> impl Future<Output = ()> + Send;
+}
+
+/// The default [`Runtime`], backed by `tokio::spawn`/`tokio::time::sleep`.
+pub struct TokioRuntime;
+
+impl Runtime for TokioRuntime {
+    fn spawn<F>(future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        tokio::spawn(future);
+    }
+
+    fn sleep(duration: Duration) -> impl Future<Output = ()> + Send {
+        tokio::time::sleep(duration)
+    }
+}
+
+/// An alternative [`Runtime`] backed by `smol`, for applications that
+/// don't otherwise run a tokio executor. See this module's docs for what
+/// this does and doesn't decouple from tokio.
+#[cfg(feature = "smol-runtime")]
+pub struct SmolRuntime;
+
+#[cfg(feature = "smol-runtime")]
+impl Runtime for SmolRuntime {
+    fn spawn<F>(future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        smol::spawn(future).detach();
+    }
+
+    fn sleep(duration: Duration) -> impl Future<Output = ()> + Send {
+        async move {
+            smol::Timer::after(duration).await;
+        }
+    }
+}
+
+#[cfg(not(feature = "smol-runtime"))]
+type ActiveRuntime = TokioRuntime;
+
+#[cfg(feature = "smol-runtime")]
+type ActiveRuntime = SmolRuntime;
+
+/// Run `future` in the background on whichever [`Runtime`] this build was
+/// compiled with. See [`Runtime::spawn`].
+pub fn spawn<F>(future: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    ActiveRuntime::spawn(future);
+}
+
+/// Complete after `duration` has elapsed, on whichever [`Runtime`] this
+/// build was compiled with. See [`Runtime::sleep`].
+pub fn sleep(duration: Duration) -> impl Future<Output = ()> + Send {
+    ActiveRuntime::sleep(duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn spawn_actually_runs_the_future() {
+        let ran = Arc::new(AtomicBool::new(false));
+        let flag = ran.clone();
+        spawn(async move {
+            flag.store(true, Ordering::SeqCst);
+        });
+
+        sleep(Duration::from_millis(20)).await;
+        assert!(ran.load(Ordering::SeqCst));
+    }
+
+    // Compile-only check for the `smol-runtime` feature: `cargo test
+    // --no-default-features --features smol-runtime` must build this
+    // module and this test, proving `SmolRuntime` actually satisfies
+    // `Runtime` and that `spawn`/`sleep` resolve to it. Run under
+    // `smol::block_on` rather than `#[tokio::test]` since the whole point
+    // is not depending on a tokio executor being present.
+    #[cfg(feature = "smol-runtime")]
+    #[test]
+    fn smol_runtime_spawn_actually_runs_the_future() {
+        let ran = Arc::new(AtomicBool::new(false));
+        let flag = ran.clone();
+        smol::block_on(async {
+            spawn(async move {
+                flag.store(true, Ordering::SeqCst);
+            });
+            sleep(Duration::from_millis(20)).await;
+        });
+        assert!(ran.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,76 @@
+//! Events surfaced to the application while a session is running.
+
+use uuid::Uuid;
+
+use crate::crypto::GroupKey;
+use crate::identity::ProfileId;
+use crate::profile::PublicProfileData;
+
+/// Something the application should react to on a [`super::Session`].
+#[derive(Debug)]
+pub enum SessionEvent {
+    /// A plain chat payload.
+    Chat(Vec<u8>),
+
+    /// The peer invited us into a group. The inviter's authorization is
+    /// already checked (the sealed key only decrypts if it was genuinely
+    /// signed by the peer's key); the app may still want to confirm with
+    /// the user before joining.
+    GroupInviteReceived {
+        conversation_id: Uuid,
+        group_key: GroupKey,
+    },
+
+    /// The peer presented different keys than what's pinned for them from
+    /// a previous session. Surfaced under [`crate::session::handshake::KeyChangePolicy::Warn`];
+    /// the app should ask the user to re-verify before trusting the peer.
+    KeyChanged { profile_id: ProfileId },
+
+    /// A [`crate::message::Message::ProfileAnnouncement`] from the peer
+    /// verified successfully. `key_changed` is the same trust-on-first-use
+    /// check [`crate::session::handshake::handshake`] runs, so a mid-session
+    /// key rotation is reported the same way a rotation discovered at the
+    /// next handshake would be.
+    ProfileUpdated {
+        profile: PublicProfileData,
+        key_changed: bool,
+    },
+
+    /// A [`crate::message::Message::Unknown`] arrived: a message tag this
+    /// build doesn't recognize, most likely a newer variant from a peer
+    /// running ahead of us. The app can log or ignore it; the session
+    /// itself stays open and keeps receiving.
+    UnknownMessage { tag: u8, bytes: Vec<u8> },
+}
+
+/// Whether [`super::Session::accept_invite`] decrypted a group key the
+/// caller hadn't seen before, or recognized a retransmitted invite for one
+/// it already knows about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InviteOutcome {
+    /// The invite's key id wasn't in the caller-supplied known-keys set.
+    /// `0` is the newly decrypted key — the caller should add it to that
+    /// set before the next call.
+    New(GroupKey),
+
+    /// The invite's key id was already in the caller-supplied known-keys
+    /// set, most likely a retransmitted invite arriving twice. `0` is the
+    /// existing entry, not a fresh decryption of it, so accepting the same
+    /// invite any number of times produces the same key every time rather
+    /// than an error.
+    Idempotent(GroupKey),
+}
+
+impl InviteOutcome {
+    /// The group key either way — call this when you only care about
+    /// having the key, not about which case produced it.
+    pub fn key(&self) -> &GroupKey {
+        match self {
+            InviteOutcome::New(key) | InviteOutcome::Idempotent(key) => key,
+        }
+    }
+
+    pub fn is_idempotent(&self) -> bool {
+        matches!(self, InviteOutcome::Idempotent(_))
+    }
+}
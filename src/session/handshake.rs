@@ -0,0 +1,390 @@
+//! Exchanging profiles and checking the peer's keys before a [`Session`]
+//! is handed to the app.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_util::sync::CancellationToken;
+
+use crate::crypto::{domain, verify_detached};
+use crate::pinning::KeyPinStore;
+use crate::error::UserError;
+use crate::profile::{Profile, PublicProfileData};
+use crate::roster::Roster;
+use crate::session::{NegotiatedParams, Session, SessionEvent};
+use crate::{Error, Result};
+
+/// How long [`handshake`] waits for the peer to complete the profile
+/// exchange before giving up with [`Error::HandshakeTimeout`].
+pub const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What to do when a peer's keys don't match what we previously pinned for
+/// them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyChangePolicy {
+    /// Surface a [`SessionEvent::KeyChanged`] but let the session proceed.
+    /// The default: most apps want to warn the user rather than silently
+    /// break chat.
+    Warn,
+    /// Refuse to establish the session at all.
+    Reject,
+}
+
+impl Default for KeyChangePolicy {
+    fn default() -> Self {
+        KeyChangePolicy::Warn
+    }
+}
+
+/// Complete the handshake on an already-connected transport: exchange
+/// profiles, then check the peer's presented keys against `pins`. If
+/// `roster` is given and already knows the peer, their `last_seen` is
+/// updated.
+///
+/// Bounded by `timeout` and by `cancel`, so a peer that opens the stream
+/// and then never sends its profile can't pin the caller's task forever:
+/// the connection is closed and [`Error::HandshakeTimeout`] is returned
+/// either once `timeout` elapses or as soon as `cancel` is triggered.
+pub async fn handshake(
+    connection: iroh::endpoint::Connection,
+    local: Arc<Profile>,
+    pins: Arc<KeyPinStore>,
+    policy: KeyChangePolicy,
+    roster: Option<&Roster>,
+    timeout: Duration,
+    cancel: &CancellationToken,
+) -> Result<(Session, Option<SessionEvent>)> {
+    let attempt = attempt_handshake(connection.clone(), local, pins, policy, roster);
+    tokio::pin!(attempt);
+
+    tokio::select! {
+        result = &mut attempt => result,
+        _ = crate::runtime::sleep(timeout) => {
+            connection.close(0u32.into(), b"handshake timed out");
+            Err(Error::HandshakeTimeout)
+        }
+        _ = cancel.cancelled() => {
+            connection.close(0u32.into(), b"handshake cancelled");
+            Err(Error::HandshakeTimeout)
+        }
+    }
+}
+
+async fn attempt_handshake(
+    connection: iroh::endpoint::Connection,
+    local: Arc<Profile>,
+    pins: Arc<KeyPinStore>,
+    policy: KeyChangePolicy,
+    roster: Option<&Roster>,
+) -> Result<(Session, Option<SessionEvent>)> {
+    let (mut send, mut recv) = connection.open_bi().await.map_err(anyhow::Error::from)?;
+    let mut transcript = Vec::new();
+    let local_public = local.public();
+    send_profile(&mut send, &local_public, &mut transcript).await?;
+    let peer = recv_profile(&mut recv, &mut transcript).await?;
+    peer.validate()?;
+
+    let changed = pins.observe(peer.profile_id(), peer.signing_key(), peer.encryption_key());
+    if changed && policy == KeyChangePolicy::Reject {
+        return Err(anyhow::anyhow!(
+            "peer {} presented different keys than previously pinned",
+            peer.profile_id()
+        )
+        .into());
+    }
+
+    confirm_capabilities(&mut send, &mut recv, local.context(), &peer, &mut transcript).await?;
+    confirm_transcript(&mut send, &mut recv, local.context(), &peer, &transcript).await?;
+
+    if let Some(roster) = roster {
+        roster.touch(peer.profile_id());
+    }
+
+    let event = changed.then(|| SessionEvent::KeyChanged { profile_id: peer.profile_id().clone() });
+    Ok((Session::new(connection, local, peer, pins), event))
+}
+
+/// Guard against an active attacker tampering with the (otherwise
+/// unauthenticated) capability exchange to force both sides onto a weaker
+/// common suite. Each side advertises its own [`NegotiatedParams`] in the
+/// clear, then signs a hash of exactly what it advertised and sends that
+/// too; the peer's signature is checked against what was actually received
+/// during the plain exchange, using the signing key already trusted from
+/// the profile exchange above. A mismatch means the advertised
+/// capabilities were altered in transit — [`Error::DowngradeDetected`]
+/// rather than the more generic [`Error::SignatureInvalid`], since the
+/// cause here is specifically a tampered negotiation, not an untrusted
+/// signer.
+///
+/// This is a tamper check, not a negotiation: neither side's own
+/// [`NegotiatedParams`] (see [`Session::negotiated`]) changes based on what
+/// the peer advertised.
+async fn confirm_capabilities(
+    send: &mut iroh::endpoint::SendStream,
+    recv: &mut iroh::endpoint::RecvStream,
+    local_context: &crate::crypto::EncryptionContext,
+    peer: &PublicProfileData,
+    transcript: &mut Vec<u8>,
+) -> Result<()> {
+    let local_caps = NegotiatedParams::for_local(local_context);
+    let local_caps_bytes = crate::encoding::to_vec(&local_caps)?;
+    send_framed(send, &local_caps_bytes, transcript).await?;
+    let peer_caps_bytes = recv_framed(recv, transcript).await?;
+
+    let confirmation = local_context.sign_detached(domain::CAPABILITY_CONFIRM, &local_caps_bytes)?;
+    send_framed(send, &confirmation, transcript).await?;
+    let peer_confirmation = recv_framed(recv, transcript).await?;
+
+    verify_detached(domain::CAPABILITY_CONFIRM, &peer_caps_bytes, &peer_confirmation, peer.signing_key())
+        .map_err(|_| Error::DowngradeDetected)
+}
+
+/// Bind the entire handshake to a signature, closing the gap
+/// [`confirm_capabilities`] leaves open: that check only covers the
+/// capability advertisement, so a tampered profile exchange (or any other
+/// message earlier in the handshake) would otherwise go undetected. Each
+/// side signs a hash of every byte exchanged so far, in order, and checks
+/// the peer's signature against the same bytes as seen on this side — a
+/// mismatch means something in the transcript was altered in transit.
+async fn confirm_transcript(
+    send: &mut iroh::endpoint::SendStream,
+    recv: &mut iroh::endpoint::RecvStream,
+    local_context: &crate::crypto::EncryptionContext,
+    peer: &PublicProfileData,
+    transcript: &[u8],
+) -> Result<()> {
+    let transcript_hash = Sha256::digest(transcript);
+    let confirmation = local_context.sign_detached(domain::HANDSHAKE_TRANSCRIPT, &transcript_hash)?;
+    let mut unused = Vec::new();
+    send_framed(send, &confirmation, &mut unused).await?;
+    let peer_confirmation = recv_framed(recv, &mut unused).await?;
+
+    verify_detached(domain::HANDSHAKE_TRANSCRIPT, &transcript_hash, &peer_confirmation, peer.signing_key())
+        .map_err(|_| Error::HandshakeTampered)
+}
+
+async fn send_profile(stream: &mut iroh::endpoint::SendStream, profile: &PublicProfileData, transcript: &mut Vec<u8>) -> Result<()> {
+    send_framed(stream, &crate::encoding::to_vec(profile)?, transcript).await
+}
+
+async fn recv_profile(stream: &mut iroh::endpoint::RecvStream, transcript: &mut Vec<u8>) -> Result<PublicProfileData> {
+    crate::encoding::from_slice_with_limits(&recv_framed(stream, transcript).await?, &crate::encoding::DecodeLimits::DEFAULT)
+}
+
+async fn send_framed(stream: &mut iroh::endpoint::SendStream, bytes: &[u8], transcript: &mut Vec<u8>) -> Result<()> {
+    stream
+        .write_all(&(bytes.len() as u32).to_be_bytes())
+        .await
+        .map_err(anyhow::Error::from)?;
+    stream.write_all(bytes).await.map_err(anyhow::Error::from)?;
+    transcript.extend_from_slice(bytes);
+    Ok(())
+}
+
+async fn recv_framed(stream: &mut iroh::endpoint::RecvStream, transcript: &mut Vec<u8>) -> Result<Vec<u8>> {
+    let mut len_buf = [0u8; 4];
+    stream.read_exact(&mut len_buf).await.map_err(anyhow::Error::from)?;
+    let len = u32::from_be_bytes(len_buf) as usize;
+    if len > crate::framing::MAX_ENVELOPE_LEN {
+        return Err(UserError::EnvelopeTooLarge { len, max: crate::framing::MAX_ENVELOPE_LEN }.into());
+    }
+    let mut buf = vec![0u8; len];
+    stream.read_exact(&mut buf).await.map_err(anyhow::Error::from)?;
+    transcript.extend_from_slice(&buf);
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endpoint::connect;
+    use crate::profile::ProfileBuilder;
+
+    #[tokio::test]
+    async fn a_peer_that_never_sends_its_profile_times_out() {
+        let alice = ProfileBuilder::new().name("alice").build().unwrap();
+        let bob = ProfileBuilder::new().name("bob").build().unwrap();
+
+        let alice_endpoint = alice.make_endpoint_with_alpn(b"resolution/handshake-test").await.unwrap();
+        let bob_endpoint = bob.make_endpoint_with_alpn(b"resolution/handshake-test").await.unwrap();
+        let bob_addr = bob_endpoint.node_addr().await.unwrap();
+
+        // Accept the connection, then stall forever instead of exchanging
+        // profiles — the peer this test is guarding against.
+        let bob_task = tokio::spawn(async move {
+            let incoming = bob_endpoint.accept().await.unwrap();
+            let _connection = incoming.await.unwrap();
+            std::future::pending::<()>().await
+        });
+
+        let connection = connect(&alice_endpoint, bob_addr, b"resolution/handshake-test").await.unwrap();
+        let pins = Arc::new(KeyPinStore::new());
+        let cancel = CancellationToken::new();
+
+        let result = handshake(
+            connection,
+            Arc::new(alice),
+            pins,
+            KeyChangePolicy::Warn,
+            None,
+            Duration::from_millis(200),
+            &cancel,
+        )
+        .await;
+
+        assert!(matches!(result, Err(Error::HandshakeTimeout)));
+        bob_task.abort();
+    }
+
+    #[tokio::test]
+    async fn a_cancelled_token_aborts_the_handshake_before_the_timeout() {
+        let alice = ProfileBuilder::new().name("alice").build().unwrap();
+        let bob = ProfileBuilder::new().name("bob").build().unwrap();
+
+        let alice_endpoint = alice.make_endpoint_with_alpn(b"resolution/handshake-cancel-test").await.unwrap();
+        let bob_endpoint = bob.make_endpoint_with_alpn(b"resolution/handshake-cancel-test").await.unwrap();
+        let bob_addr = bob_endpoint.node_addr().await.unwrap();
+
+        let bob_task = tokio::spawn(async move {
+            let incoming = bob_endpoint.accept().await.unwrap();
+            let _connection = incoming.await.unwrap();
+            std::future::pending::<()>().await
+        });
+
+        let connection = connect(&alice_endpoint, bob_addr, b"resolution/handshake-cancel-test").await.unwrap();
+        let pins = Arc::new(KeyPinStore::new());
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let result = handshake(connection, Arc::new(alice), pins, KeyChangePolicy::Warn, None, Duration::from_secs(30), &cancel).await;
+
+        assert!(matches!(result, Err(Error::HandshakeTimeout)));
+        bob_task.abort();
+    }
+
+    #[tokio::test]
+    async fn negotiated_params_match_on_both_sides_of_a_successful_handshake() {
+        let alice = Arc::new(ProfileBuilder::new().name("alice").build().unwrap());
+        let bob = Arc::new(ProfileBuilder::new().name("bob").build().unwrap());
+
+        let alice_endpoint = alice.make_endpoint_with_alpn(b"resolution/negotiated-test").await.unwrap();
+        let bob_endpoint = bob.make_endpoint_with_alpn(b"resolution/negotiated-test").await.unwrap();
+        let bob_addr = bob_endpoint.node_addr().await.unwrap();
+
+        let bob_profile = bob.clone();
+        let bob_task = tokio::spawn(async move {
+            let incoming = bob_endpoint.accept().await.unwrap();
+            let connection = incoming.await.unwrap();
+            let pins = Arc::new(KeyPinStore::new());
+            let (session, _event) =
+                handshake(connection, bob_profile, pins, KeyChangePolicy::default(), None, DEFAULT_HANDSHAKE_TIMEOUT, &CancellationToken::new())
+                    .await
+                    .unwrap();
+            session.negotiated()
+        });
+
+        let connection = connect(&alice_endpoint, bob_addr, b"resolution/negotiated-test").await.unwrap();
+        let pins = Arc::new(KeyPinStore::new());
+        let (session, _event) =
+            handshake(connection, alice.clone(), pins, KeyChangePolicy::default(), None, DEFAULT_HANDSHAKE_TIMEOUT, &CancellationToken::new())
+                .await
+                .unwrap();
+
+        let alice_params = session.negotiated();
+        let bob_params = bob_task.await.unwrap();
+
+        assert_eq!(alice_params, bob_params);
+        assert_eq!(alice_params.protocol_version, crate::constants::PROTOCOL_VERSION);
+        assert_eq!(alice_params.max_message_size, crate::framing::MAX_ENVELOPE_LEN);
+    }
+
+    #[tokio::test]
+    async fn a_capability_confirmation_that_doesnt_match_what_was_advertised_is_a_downgrade() {
+        let alice = Arc::new(ProfileBuilder::new().name("alice").build().unwrap());
+        let bob = Arc::new(ProfileBuilder::new().name("bob").build().unwrap());
+
+        let alice_endpoint = alice.make_endpoint_with_alpn(b"resolution/downgrade-test").await.unwrap();
+        let bob_endpoint = bob.make_endpoint_with_alpn(b"resolution/downgrade-test").await.unwrap();
+        let bob_addr = bob_endpoint.node_addr().await.unwrap();
+
+        // Plays bob's side of the protocol by hand instead of calling
+        // `handshake`, so it can advertise one set of capabilities in the
+        // clear and then sign a confirmation over a *different* set — the
+        // same thing alice would see if an attacker altered the
+        // advertisement in transit.
+        let bob_profile = bob.clone();
+        let bob_task = tokio::spawn(async move {
+            let incoming = bob_endpoint.accept().await.unwrap();
+            let connection = incoming.await.unwrap();
+            let (mut send, mut recv) = connection.open_bi().await.unwrap();
+            let mut transcript = Vec::new();
+
+            send_profile(&mut send, &bob_profile.public(), &mut transcript).await.unwrap();
+            let _alice_profile = recv_profile(&mut recv, &mut transcript).await.unwrap();
+
+            let advertised = NegotiatedParams::for_local(bob_profile.context());
+            send_framed(&mut send, &crate::encoding::to_vec(&advertised).unwrap(), &mut transcript).await.unwrap();
+            let _alice_caps = recv_framed(&mut recv, &mut transcript).await.unwrap();
+
+            let mut tampered = advertised.clone();
+            tampered.security_level = 0;
+            let confirmation = bob_profile
+                .context()
+                .sign_detached(domain::CAPABILITY_CONFIRM, &crate::encoding::to_vec(&tampered).unwrap())
+                .unwrap();
+            send_framed(&mut send, &confirmation, &mut transcript).await.unwrap();
+            let _alice_confirmation = recv_framed(&mut recv, &mut transcript).await.unwrap();
+        });
+
+        let connection = connect(&alice_endpoint, bob_addr, b"resolution/downgrade-test").await.unwrap();
+        let pins = Arc::new(KeyPinStore::new());
+        let result = handshake(connection, alice.clone(), pins, KeyChangePolicy::default(), None, DEFAULT_HANDSHAKE_TIMEOUT, &CancellationToken::new()).await;
+
+        assert!(matches!(result, Err(Error::DowngradeDetected)));
+        bob_task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_tampered_handshake_message_is_caught_by_the_transcript_signature() {
+        let alice = Arc::new(ProfileBuilder::new().name("alice").build().unwrap());
+        let bob = Arc::new(ProfileBuilder::new().name("bob").build().unwrap());
+
+        let alice_endpoint = alice.make_endpoint_with_alpn(b"resolution/transcript-test").await.unwrap();
+        let bob_endpoint = bob.make_endpoint_with_alpn(b"resolution/transcript-test").await.unwrap();
+        let bob_addr = bob_endpoint.node_addr().await.unwrap();
+
+        // Plays bob's side of the protocol by hand, completing the
+        // capability exchange honestly, then signing its transcript
+        // confirmation over a hash of a transcript with one byte flipped
+        // — the same mismatch alice would see if any earlier handshake
+        // message (profile exchange included) had been altered in transit.
+        let bob_profile = bob.clone();
+        let bob_task = tokio::spawn(async move {
+            let incoming = bob_endpoint.accept().await.unwrap();
+            let connection = incoming.await.unwrap();
+            let (mut send, mut recv) = connection.open_bi().await.unwrap();
+            let mut transcript = Vec::new();
+
+            send_profile(&mut send, &bob_profile.public(), &mut transcript).await.unwrap();
+            let _alice_profile = recv_profile(&mut recv, &mut transcript).await.unwrap();
+            confirm_capabilities(&mut send, &mut recv, bob_profile.context(), &_alice_profile, &mut transcript).await.unwrap();
+
+            let mut tampered_transcript = transcript.clone();
+            tampered_transcript[0] ^= 0xff;
+            let tampered_hash = Sha256::digest(&tampered_transcript);
+            let confirmation = bob_profile.context().sign_detached(domain::HANDSHAKE_TRANSCRIPT, &tampered_hash).unwrap();
+            let mut unused = Vec::new();
+            send_framed(&mut send, &confirmation, &mut unused).await.unwrap();
+            let _alice_confirmation = recv_framed(&mut recv, &mut unused).await.unwrap();
+        });
+
+        let connection = connect(&alice_endpoint, bob_addr, b"resolution/transcript-test").await.unwrap();
+        let pins = Arc::new(KeyPinStore::new());
+        let result = handshake(connection, alice.clone(), pins, KeyChangePolicy::default(), None, DEFAULT_HANDSHAKE_TIMEOUT, &CancellationToken::new()).await;
+
+        assert!(matches!(result, Err(Error::HandshakeTampered)));
+        bob_task.await.unwrap();
+    }
+}
@@ -0,0 +1,78 @@
+//! Per-session traffic counters.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Bandwidth and message counters for a single [`super::Session`].
+///
+/// All fields use relaxed atomics: these are diagnostic counters, not a
+/// synchronization primitive, so exact ordering across counters doesn't
+/// matter.
+#[derive(Debug, Default)]
+pub struct SessionMetrics {
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    messages_sent: AtomicU64,
+    messages_received: AtomicU64,
+    encryption_failures: AtomicU64,
+    decryption_failures: AtomicU64,
+}
+
+/// A point-in-time copy of [`SessionMetrics`]'s counters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub messages_sent: u64,
+    pub messages_received: u64,
+    pub encryption_failures: u64,
+    pub decryption_failures: u64,
+}
+
+impl SessionMetrics {
+    pub fn record_sent(&self, bytes: usize) {
+        self.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.messages_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_received(&self, bytes: usize) {
+        self.bytes_received.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.messages_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_encryption_failure(&self) {
+        self.encryption_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_decryption_failure(&self) {
+        self.decryption_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            messages_sent: self.messages_sent.load(Ordering::Relaxed),
+            messages_received: self.messages_received.load(Ordering::Relaxed),
+            encryption_failures: self.encryption_failures.load(Ordering::Relaxed),
+            decryption_failures: self.decryption_failures.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counters_increment_on_send_and_receive() {
+        let metrics = SessionMetrics::default();
+        metrics.record_sent(10);
+        metrics.record_received(4);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.bytes_sent, 10);
+        assert_eq!(snapshot.bytes_received, 4);
+        assert_eq!(snapshot.messages_sent, 1);
+        assert_eq!(snapshot.messages_received, 1);
+    }
+}
@@ -0,0 +1,465 @@
+//! An established, authenticated connection to a peer.
+
+mod events;
+pub mod handshake;
+mod metrics;
+mod negotiated;
+pub(crate) mod observer;
+mod pool;
+mod stream;
+
+pub use events::{InviteOutcome, SessionEvent};
+pub use handshake::{handshake as connect_handshake, KeyChangePolicy};
+pub use metrics::{MetricsSnapshot, SessionMetrics};
+pub use negotiated::NegotiatedParams;
+pub use observer::{LifecycleEvent, SessionObserver};
+pub use pool::SessionPool;
+pub use stream::{Stream, StreamKind};
+
+use self::stream::StreamRouter;
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use uuid::Uuid;
+
+use crate::crypto::{GroupKey, SingleEncryption};
+use crate::error::UserError;
+use crate::message::Message;
+use crate::pinning::KeyPinStore;
+use crate::profile::{Profile, PublicProfileData, SignedProfile};
+use crate::{Error, Result};
+
+/// A live connection to a peer, established after the handshake completes.
+pub struct Session {
+    connection: iroh::endpoint::Connection,
+    local: Arc<Profile>,
+    peer: Mutex<PublicProfileData>,
+    pins: Arc<KeyPinStore>,
+    metrics: SessionMetrics,
+    streams: StreamRouter,
+}
+
+impl Session {
+    pub(crate) fn new(connection: iroh::endpoint::Connection, local: Arc<Profile>, peer: PublicProfileData, pins: Arc<KeyPinStore>) -> Self {
+        let streams = StreamRouter::new(connection.clone());
+        Self {
+            connection,
+            local,
+            peer: Mutex::new(peer),
+            pins,
+            metrics: SessionMetrics::default(),
+            streams,
+        }
+    }
+
+    pub fn peer(&self) -> PublicProfileData {
+        self.peer.lock().unwrap().clone()
+    }
+
+    /// The parameters this session is running under — protocol version,
+    /// crypto suite, and message size limit — for an app that wants to
+    /// adjust its UI or behavior accordingly.
+    pub fn negotiated(&self) -> NegotiatedParams {
+        NegotiatedParams::for_local(self.local.context())
+    }
+
+    /// Whether the underlying connection is still usable. `false` once the
+    /// peer or transport has closed it, e.g. after an idle timeout — the
+    /// signal [`SessionPool`] uses to decide whether a cached session can
+    /// be reused or needs to be evicted and reconnected.
+    pub fn is_healthy(&self) -> bool {
+        self.connection.close_reason().is_none()
+    }
+
+    /// Close the underlying connection. Further sends/receives on this
+    /// session will fail.
+    pub fn close(&self) {
+        self.connection.close(0u32.into(), b"session closed");
+    }
+
+    /// Bandwidth and message counters accumulated over this session's
+    /// lifetime.
+    pub fn metrics(&self) -> &SessionMetrics {
+        &self.metrics
+    }
+
+    /// Send an already-encrypted envelope on a fresh unidirectional stream.
+    pub async fn send_raw(&self, bytes: &[u8]) -> Result<()> {
+        let mut stream = self.connection.open_uni().await.map_err(anyhow::Error::from)?;
+        stream
+            .write_all(&(bytes.len() as u32).to_be_bytes())
+            .await
+            .map_err(anyhow::Error::from)?;
+        stream.write_all(bytes).await.map_err(anyhow::Error::from)?;
+        stream.finish().map_err(anyhow::Error::from)?;
+        self.metrics.record_sent(bytes.len());
+        Ok(())
+    }
+
+    /// Receive one envelope from the next inbound unidirectional stream.
+    pub async fn recv_raw(&self) -> Result<Vec<u8>> {
+        let mut stream = self.connection.accept_uni().await.map_err(anyhow::Error::from)?;
+        let mut len_buf = [0u8; 4];
+        stream.read_exact(&mut len_buf).await.map_err(anyhow::Error::from)?;
+        let len = u32::from_be_bytes(len_buf) as usize;
+        if len > crate::framing::MAX_ENVELOPE_LEN {
+            return Err(UserError::EnvelopeTooLarge { len, max: crate::framing::MAX_ENVELOPE_LEN }.into());
+        }
+        let mut buf = vec![0u8; len];
+        stream.read_exact(&mut buf).await.map_err(anyhow::Error::from)?;
+        self.metrics.record_received(buf.len());
+        Ok(buf)
+    }
+
+    /// Open a new multiplexed [`Stream`] of `kind`, independent of chat
+    /// traffic and any other open stream — e.g. a large file transfer on
+    /// [`StreamKind::Bulk`] won't head-of-line-block a chat message sent
+    /// concurrently on [`StreamKind::Control`].
+    pub async fn open_stream(&self, kind: StreamKind) -> Result<Stream> {
+        stream::open(&self.connection, kind).await
+    }
+
+    /// Wait for the next inbound stream of `kind`. Streams of other kinds
+    /// arriving in the meantime are queued separately and don't block this
+    /// call.
+    pub async fn accept_stream(&self, kind: StreamKind) -> Result<Stream> {
+        self.streams.recv(kind).await
+    }
+
+    async fn send_message(&self, message: &Message) -> Result<()> {
+        self.send_raw(&crate::encoding::to_vec(message)?).await
+    }
+
+    async fn recv_message(&self) -> Result<Message> {
+        crate::encoding::from_slice_with_limits(&self.recv_raw().await?, &crate::encoding::DecodeLimits::DEFAULT)
+    }
+
+    /// Seal `group`'s key to the peer's encryption key and send it as a
+    /// `Message::GroupInvite`, so it flows through the same authenticated
+    /// path as chat.
+    pub async fn invite_to_group(&self, conversation_id: Uuid, group: &GroupKey) -> Result<()> {
+        let target = self.peer.lock().unwrap().encryption_key().clone();
+        let sealed_key = self.local.context().encrypt_group_key_to(target, group)?;
+        self.send_message(&Message::GroupInvite { conversation_id, sealed_key }).await
+    }
+
+    /// Decrypt a `Message::GroupInvite`'s `sealed_key` idempotently: a
+    /// retransmitted invite (the same envelope arriving twice, which
+    /// at-least-once delivery makes routine — see [`crate::queue`]) should
+    /// join the group once, not create confusing duplicate state or fail
+    /// the second time around. `known_keys` is the caller's own record of
+    /// group keys it's already accepted; if the decrypted key's id is
+    /// already in it, the existing entry is returned as
+    /// [`InviteOutcome::Idempotent`] instead of the freshly decrypted one,
+    /// so accepting the same invite any number of times is always safe and
+    /// always yields the same stable key.
+    pub fn accept_invite(&self, sealed_key: &SingleEncryption, known_keys: &HashSet<GroupKey>) -> Result<InviteOutcome> {
+        let signer = self.peer.lock().unwrap().signing_key().clone();
+        let key = self.local.context().decrypt_group_key_from(sealed_key, signer)?;
+        match known_keys.get(&key) {
+            Some(existing) => Ok(InviteOutcome::Idempotent(existing.clone())),
+            None => Ok(InviteOutcome::New(key)),
+        }
+    }
+
+    /// Send this session's current signed profile unprompted, so the peer's
+    /// roster can pick up a display-name change or key rotation without
+    /// waiting for the next handshake.
+    pub async fn announce_profile(&self) -> Result<()> {
+        let signed = self.local.signed_public_profile()?;
+        self.send_message(&Message::ProfileAnnouncement(signed)).await
+    }
+
+    /// Receive the next message. A `GroupInvite` is only decryptable if it
+    /// was genuinely sealed and signed by this session's peer, which is
+    /// the authorization check before it's surfaced to the app. A
+    /// `ProfileAnnouncement` is only accepted from the profile this session
+    /// was already established with — it can update that profile's name,
+    /// devices, or keys, but can't impersonate a different profile
+    /// entirely over an existing session.
+    pub async fn receive(&self) -> Result<SessionEvent> {
+        match self.recv_message().await? {
+            Message::Chat { body } => Ok(SessionEvent::Chat(body)),
+            Message::GroupInvite { conversation_id, sealed_key } => {
+                let signer = self.peer.lock().unwrap().signing_key().clone();
+                let group_key = self.local.context().decrypt_group_key_from(&sealed_key, signer)?;
+                Ok(SessionEvent::GroupInviteReceived { conversation_id, group_key })
+            }
+            Message::ProfileAnnouncement(signed) => {
+                let profile = self.verify_announcement(&signed)?;
+                let key_changed = self.pins.observe(profile.profile_id(), profile.signing_key(), profile.encryption_key());
+                Ok(SessionEvent::ProfileUpdated { profile: profile.clone(), key_changed })
+            }
+            Message::Unknown { tag, bytes } => Ok(SessionEvent::UnknownMessage { tag, bytes }),
+        }
+    }
+
+    /// Post-compromise recovery for a peer who presented different keys
+    /// than what was pinned for them (see [`SessionEvent::KeyChanged`] and
+    /// [`SessionEvent::ProfileUpdated`]'s `key_changed`): encodes the "did
+    /// you verify the new safety number?" prompt as a typed API instead of
+    /// leaving it to each app to remember to gate on. On
+    /// `user_confirmed`, `new_profile`'s keys are pinned and the session
+    /// resumes under them, exactly as if they'd been presented at a fresh
+    /// handshake with [`crate::session::handshake::KeyChangePolicy::Warn`].
+    /// Otherwise the session is closed and [`Error::ReverificationDeclined`]
+    /// is returned rather than continuing to trust unconfirmed keys.
+    pub fn reverify(&self, new_profile: &SignedProfile, user_confirmed: bool) -> Result<()> {
+        if !user_confirmed {
+            self.close();
+            return Err(Error::ReverificationDeclined);
+        }
+
+        let profile = self.verify_announcement(new_profile)?;
+        self.pins.observe(profile.profile_id(), profile.signing_key(), profile.encryption_key());
+        *self.peer.lock().unwrap() = profile.clone();
+        Ok(())
+    }
+
+    fn verify_announcement<'a>(&self, signed: &'a SignedProfile) -> Result<&'a PublicProfileData> {
+        let profile = signed.verify(false)?;
+        let peer = self.peer.lock().unwrap();
+        if profile.profile_id() != peer.profile_id() {
+            return Err(anyhow::anyhow!("profile announcement for {} doesn't match this session's peer {}", profile.profile_id(), peer.profile_id()).into());
+        }
+        Ok(profile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine as _;
+    use tokio_util::sync::CancellationToken;
+
+    use crate::crypto::EncryptionContext;
+    use crate::endpoint::connect;
+    use crate::profile::ProfileBuilder;
+    use crate::session::handshake::{handshake, KeyChangePolicy, DEFAULT_HANDSHAKE_TIMEOUT};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn a_peer_that_rotates_its_keys_is_observed_as_a_profile_update_with_key_changed() {
+        let alice = Arc::new(ProfileBuilder::new().name("alice").build().unwrap());
+        let bob = Arc::new(ProfileBuilder::new().name("bob").build().unwrap());
+
+        let alice_endpoint = alice.make_endpoint_with_alpn(b"resolution/announce-test").await.unwrap();
+        let bob_endpoint = bob.make_endpoint_with_alpn(b"resolution/announce-test").await.unwrap();
+        let bob_addr = bob_endpoint.node_addr().await.unwrap();
+
+        let bob_profile = bob.clone();
+        let bob_task = tokio::spawn(async move {
+            let incoming = bob_endpoint.accept().await.unwrap();
+            let connection = incoming.await.unwrap();
+            let pins = Arc::new(KeyPinStore::new());
+            let (session, _event) =
+                handshake(connection, bob_profile, pins, KeyChangePolicy::default(), None, DEFAULT_HANDSHAKE_TIMEOUT, &CancellationToken::new())
+                    .await
+                    .unwrap();
+
+            // Simulate bob rotating his keys: sign a fresh `PublicProfileData`
+            // under the same `profile_id` but a brand new key pair, then send
+            // it as an announcement over the already-established session.
+            let rotated_context = EncryptionContext::generate().unwrap();
+            let rotated_public = PublicProfileData::from_b64_keys(
+                bob.profile_id().to_string(),
+                &STANDARD.encode(rotated_context.signing_public_key()),
+                &STANDARD.encode(rotated_context.encryption_public_key()),
+            )
+            .unwrap();
+            let rotated_signed = SignedProfile::sign(&rotated_context, rotated_public, None).unwrap();
+            let bytes = crate::encoding::to_vec(&Message::ProfileAnnouncement(rotated_signed)).unwrap();
+            session.send_raw(&bytes).await.unwrap();
+        });
+
+        let connection = connect(&alice_endpoint, bob_addr, b"resolution/announce-test").await.unwrap();
+        let pins = Arc::new(KeyPinStore::new());
+        let (alice_session, _event) =
+            handshake(connection, alice.clone(), pins, KeyChangePolicy::default(), None, DEFAULT_HANDSHAKE_TIMEOUT, &CancellationToken::new())
+                .await
+                .unwrap();
+
+        let event = alice_session.receive().await.unwrap();
+        match event {
+            SessionEvent::ProfileUpdated { profile, key_changed } => {
+                assert_eq!(profile.profile_id(), &bob.profile_id());
+                assert!(key_changed);
+            }
+            other => panic!("expected ProfileUpdated, got {other:?}"),
+        }
+
+        bob_task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn an_announcement_for_a_different_profile_id_than_the_session_peer_is_rejected() {
+        let alice = Arc::new(ProfileBuilder::new().name("alice").build().unwrap());
+        let bob = Arc::new(ProfileBuilder::new().name("bob").build().unwrap());
+        let mallory = ProfileBuilder::new().name("mallory").build().unwrap();
+
+        let alice_endpoint = alice.make_endpoint_with_alpn(b"resolution/announce-mismatch-test").await.unwrap();
+        let bob_endpoint = bob.make_endpoint_with_alpn(b"resolution/announce-mismatch-test").await.unwrap();
+        let bob_addr = bob_endpoint.node_addr().await.unwrap();
+
+        let bob_profile = bob.clone();
+        let bob_task = tokio::spawn(async move {
+            let incoming = bob_endpoint.accept().await.unwrap();
+            let connection = incoming.await.unwrap();
+            let pins = Arc::new(KeyPinStore::new());
+            let (session, _event) =
+                handshake(connection, bob_profile, pins, KeyChangePolicy::default(), None, DEFAULT_HANDSHAKE_TIMEOUT, &CancellationToken::new())
+                    .await
+                    .unwrap();
+
+            let mallory_signed = mallory.signed_public_profile().unwrap();
+            let bytes = crate::encoding::to_vec(&Message::ProfileAnnouncement(mallory_signed)).unwrap();
+            session.send_raw(&bytes).await.unwrap();
+        });
+
+        let connection = connect(&alice_endpoint, bob_addr, b"resolution/announce-mismatch-test").await.unwrap();
+        let pins = Arc::new(KeyPinStore::new());
+        let (alice_session, _event) =
+            handshake(connection, alice.clone(), pins, KeyChangePolicy::default(), None, DEFAULT_HANDSHAKE_TIMEOUT, &CancellationToken::new())
+                .await
+                .unwrap();
+
+        assert!(alice_session.receive().await.is_err());
+
+        bob_task.await.unwrap();
+    }
+
+    /// A signed [`PublicProfileData`] for `bob`'s `profile_id`, under a
+    /// freshly generated key pair instead of `bob`'s original one — the
+    /// same shape of announcement a compromised or rotated peer would
+    /// send, without needing a live connection to produce it.
+    fn rotated_signed_profile(bob: &Profile) -> SignedProfile {
+        let rotated_context = EncryptionContext::generate().unwrap();
+        let rotated_public = PublicProfileData::from_b64_keys(
+            bob.profile_id().to_string(),
+            &STANDARD.encode(rotated_context.signing_public_key()),
+            &STANDARD.encode(rotated_context.encryption_public_key()),
+        )
+        .unwrap();
+        SignedProfile::sign(&rotated_context, rotated_public, None).unwrap()
+    }
+
+    #[tokio::test]
+    async fn reverify_with_user_confirmed_pins_the_new_keys_and_keeps_the_session_open() {
+        let alice = Arc::new(ProfileBuilder::new().name("alice").build().unwrap());
+        let bob = Arc::new(ProfileBuilder::new().name("bob").build().unwrap());
+
+        let alice_endpoint = alice.make_endpoint_with_alpn(b"resolution/reverify-confirmed-test").await.unwrap();
+        let bob_endpoint = bob.make_endpoint_with_alpn(b"resolution/reverify-confirmed-test").await.unwrap();
+        let bob_addr = bob_endpoint.node_addr().await.unwrap();
+
+        let bob_profile = bob.clone();
+        let bob_task = tokio::spawn(async move {
+            let incoming = bob_endpoint.accept().await.unwrap();
+            let connection = incoming.await.unwrap();
+            let pins = Arc::new(KeyPinStore::new());
+            handshake(connection, bob_profile, pins, KeyChangePolicy::default(), None, DEFAULT_HANDSHAKE_TIMEOUT, &CancellationToken::new())
+                .await
+                .unwrap();
+        });
+
+        let connection = connect(&alice_endpoint, bob_addr, b"resolution/reverify-confirmed-test").await.unwrap();
+        let pins = Arc::new(KeyPinStore::new());
+        let (alice_session, _event) =
+            handshake(connection, alice.clone(), pins, KeyChangePolicy::default(), None, DEFAULT_HANDSHAKE_TIMEOUT, &CancellationToken::new())
+                .await
+                .unwrap();
+
+        let rotated = rotated_signed_profile(&bob);
+        let rotated_encryption_key = rotated.verify(false).unwrap().encryption_key().clone();
+
+        alice_session.reverify(&rotated, true).unwrap();
+
+        assert_eq!(alice_session.peer().encryption_key(), &rotated_encryption_key);
+        assert!(alice_session.is_healthy());
+
+        bob_task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn reverify_without_user_confirmation_closes_the_session_and_leaves_the_pinned_peer_untouched() {
+        let alice = Arc::new(ProfileBuilder::new().name("alice").build().unwrap());
+        let bob = Arc::new(ProfileBuilder::new().name("bob").build().unwrap());
+
+        let alice_endpoint = alice.make_endpoint_with_alpn(b"resolution/reverify-rejected-test").await.unwrap();
+        let bob_endpoint = bob.make_endpoint_with_alpn(b"resolution/reverify-rejected-test").await.unwrap();
+        let bob_addr = bob_endpoint.node_addr().await.unwrap();
+
+        let bob_profile = bob.clone();
+        let bob_task = tokio::spawn(async move {
+            let incoming = bob_endpoint.accept().await.unwrap();
+            let connection = incoming.await.unwrap();
+            let pins = Arc::new(KeyPinStore::new());
+            handshake(connection, bob_profile, pins, KeyChangePolicy::default(), None, DEFAULT_HANDSHAKE_TIMEOUT, &CancellationToken::new())
+                .await
+                .unwrap();
+        });
+
+        let connection = connect(&alice_endpoint, bob_addr, b"resolution/reverify-rejected-test").await.unwrap();
+        let pins = Arc::new(KeyPinStore::new());
+        let (alice_session, _event) =
+            handshake(connection, alice.clone(), pins, KeyChangePolicy::default(), None, DEFAULT_HANDSHAKE_TIMEOUT, &CancellationToken::new())
+                .await
+                .unwrap();
+
+        let original_encryption_key = alice_session.peer().encryption_key().clone();
+        let rotated = rotated_signed_profile(&bob);
+
+        let result = alice_session.reverify(&rotated, false);
+
+        assert!(matches!(result, Err(Error::ReverificationDeclined)));
+        assert_eq!(alice_session.peer().encryption_key(), &original_encryption_key);
+        assert!(!alice_session.is_healthy());
+
+        bob_task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn accepting_the_same_group_invite_twice_yields_the_same_stable_key() {
+        let alice = Arc::new(ProfileBuilder::new().name("alice").build().unwrap());
+        let bob = Arc::new(ProfileBuilder::new().name("bob").build().unwrap());
+
+        let alice_endpoint = alice.make_endpoint_with_alpn(b"resolution/accept-invite-test").await.unwrap();
+        let bob_endpoint = bob.make_endpoint_with_alpn(b"resolution/accept-invite-test").await.unwrap();
+        let bob_addr = bob_endpoint.node_addr().await.unwrap();
+
+        let bob_profile = bob.clone();
+        let bob_task = tokio::spawn(async move {
+            let incoming = bob_endpoint.accept().await.unwrap();
+            let connection = incoming.await.unwrap();
+            let pins = Arc::new(KeyPinStore::new());
+            handshake(connection, bob_profile, pins, KeyChangePolicy::default(), None, DEFAULT_HANDSHAKE_TIMEOUT, &CancellationToken::new())
+                .await
+                .unwrap();
+        });
+
+        let connection = connect(&alice_endpoint, bob_addr, b"resolution/accept-invite-test").await.unwrap();
+        let pins = Arc::new(KeyPinStore::new());
+        let (alice_session, _event) =
+            handshake(connection, alice.clone(), pins, KeyChangePolicy::default(), None, DEFAULT_HANDSHAKE_TIMEOUT, &CancellationToken::new())
+                .await
+                .unwrap();
+        bob_task.await.unwrap();
+
+        let group = GroupKey::generate();
+        let sealed_key = bob.context().encrypt_group_key_to(alice.context().encryption_public_key().clone(), &group).unwrap();
+
+        let mut known_keys = HashSet::new();
+        let first = alice_session.accept_invite(&sealed_key, &known_keys).unwrap();
+        assert!(matches!(first, InviteOutcome::New(_)));
+        assert_eq!(first.key().id(), group.id());
+        known_keys.insert(first.key().clone());
+
+        let second = alice_session.accept_invite(&sealed_key, &known_keys).unwrap();
+        assert!(second.is_idempotent());
+        assert_eq!(second.key().id(), group.id());
+        assert_eq!(known_keys.len(), 1);
+    }
+}
@@ -0,0 +1,62 @@
+//! What a [`super::Session`]'s two sides ended up agreeing on.
+
+use serde::{Deserialize, Serialize};
+
+/// A snapshot of the parameters in effect for a [`super::Session`], so an
+/// app can adjust its behavior — e.g. disable large-attachment UI when
+/// `max_message_size` is small — without hardcoding assumptions the
+/// protocol might change later.
+///
+/// [`Self::for_local`] always reads these off the local side rather than
+/// trusting a peer-advertised value directly — a [`super::Session`] only
+/// exists once the handshake's profile exchange has already succeeded,
+/// which itself proves both sides are running a compatible ALPN (hence
+/// `protocol_version`) and can decode each other's key material (hence
+/// `kem_algorithm`/`sig_algorithm`/`security_level`).
+///
+/// `Serialize`/`Deserialize` because the handshake also advertises this
+/// value to the peer and, once the connection is authenticated,
+/// cross-checks it against a signed hash — see
+/// [`super::handshake::handshake`]'s downgrade-detection step — but that
+/// exchange is a tamper check, not a negotiation: nothing here changes
+/// based on what the peer reports.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NegotiatedParams {
+    pub protocol_version: String,
+    pub kem_algorithm: String,
+    pub sig_algorithm: String,
+    pub security_level: u8,
+    pub max_message_size: usize,
+    pub compression_supported: bool,
+}
+
+impl NegotiatedParams {
+    pub(crate) fn for_local(local: &crate::crypto::EncryptionContext) -> Self {
+        let (kem_algorithm, sig_algorithm) = local.algorithms();
+        Self {
+            protocol_version: crate::constants::PROTOCOL_VERSION.to_string(),
+            kem_algorithm,
+            sig_algorithm,
+            security_level: local.security_level(),
+            max_message_size: crate::framing::MAX_ENVELOPE_LEN,
+            compression_supported: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::EncryptionContext;
+
+    #[test]
+    fn reflects_the_local_context_algorithms_and_security_level() {
+        let context = EncryptionContext::generate().unwrap();
+        let params = NegotiatedParams::for_local(&context);
+
+        assert_eq!(params.protocol_version, crate::constants::PROTOCOL_VERSION);
+        assert_eq!((params.kem_algorithm.as_str(), params.sig_algorithm.as_str()), (context.algorithms().0.as_str(), context.algorithms().1.as_str()));
+        assert_eq!(params.security_level, context.security_level());
+        assert_eq!(params.max_message_size, crate::framing::MAX_ENVELOPE_LEN);
+    }
+}
@@ -0,0 +1,51 @@
+//! Centralized hooks for a session's connection lifecycle, so keepalive,
+//! failover, and key-pinning logic can all react to the same events
+//! instead of each polling [`super::Session::is_healthy`] or
+//! [`super::Session::receive`] on their own.
+
+use std::sync::Arc;
+
+use crate::identity::ProfileId;
+
+/// A lifecycle event for a peer tracked by a [`super::SessionPool`],
+/// reported to whatever [`SessionObserver`] was registered for it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LifecycleEvent {
+    /// A session with `profile_id` was just established by a fresh
+    /// handshake. Handing back an already cached session reports nothing.
+    Connected { profile_id: ProfileId },
+
+    /// A previously live session is no longer usable.
+    Disconnected { profile_id: ProfileId, reason: String },
+
+    /// A new connection attempt for `profile_id` is starting after a
+    /// `Disconnected`. Nothing in this tree drives this yet — there's no
+    /// keepalive or failover loop that retries on its own — but it's part
+    /// of the enum now so adding one later doesn't also mean touching
+    /// every existing `SessionObserver` implementation.
+    Reconnecting { profile_id: ProfileId },
+
+    /// The peer presented different keys than previously pinned. Mirrors
+    /// [`super::SessionEvent::KeyChanged`], surfaced here too so an
+    /// observer watching lifecycle events doesn't also have to poll
+    /// `Session::receive` just to catch it.
+    KeyChanged { profile_id: ProfileId },
+
+    /// A connection or handshake attempt for `profile_id` failed outright,
+    /// as opposed to a session that was live and then dropped (that's
+    /// `Disconnected`).
+    Error { profile_id: ProfileId, message: String },
+}
+
+/// Implemented by anything that wants to react to a [`super::SessionPool`]'s
+/// connection lifecycle. Every method defaults to a no-op, so an observer
+/// only needs to override the events it actually cares about.
+pub trait SessionObserver: Send + Sync {
+    fn on_event(&self, event: LifecycleEvent) {
+        let _ = event;
+    }
+}
+
+pub(crate) fn notify(observer: &Arc<dyn SessionObserver>, event: LifecycleEvent) {
+    observer.on_event(event);
+}
@@ -0,0 +1,260 @@
+//! Caching and reusing [`Session`]s per peer, so chatty applications don't
+//! pay the handshake and KEM cost on every message.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use tokio_util::sync::CancellationToken;
+
+use crate::identity::ProfileId;
+use crate::pinning::KeyPinStore;
+use crate::profile::Profile;
+use crate::session::handshake::{handshake, KeyChangePolicy, DEFAULT_HANDSHAKE_TIMEOUT};
+use crate::session::observer::{LifecycleEvent, SessionObserver};
+use crate::session::{Session, SessionEvent};
+use crate::Result;
+
+/// An LRU cache of live [`Session`]s, keyed by peer `profile_id`.
+///
+/// `get_or_connect` returns a cached session if one is healthy, or dials
+/// and hands shakes a new one otherwise. The cache is bounded by
+/// `capacity`: inserting past it evicts (and closes) the least recently
+/// used session first.
+pub struct SessionPool {
+    local: Arc<Profile>,
+    alpn: Vec<u8>,
+    pins: Arc<KeyPinStore>,
+    capacity: usize,
+    sessions: Mutex<HashMap<ProfileId, Arc<Session>>>,
+    lru: Mutex<VecDeque<ProfileId>>,
+    observer: Option<Arc<dyn SessionObserver>>,
+}
+
+impl SessionPool {
+    /// `capacity` is the maximum number of sessions kept alive at once.
+    pub fn new(local: Arc<Profile>, alpn: impl Into<Vec<u8>>, capacity: usize) -> Self {
+        Self {
+            local,
+            alpn: alpn.into(),
+            pins: Arc::new(KeyPinStore::new()),
+            capacity,
+            sessions: Mutex::new(HashMap::new()),
+            lru: Mutex::new(VecDeque::new()),
+            observer: None,
+        }
+    }
+
+    /// Report connect/disconnect/key-change events on `observer` instead of
+    /// dropping them, so callers like keepalive or failover logic don't
+    /// have to poll [`Session::is_healthy`] themselves.
+    pub fn with_observer(mut self, observer: Arc<dyn SessionObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    fn notify(&self, event: LifecycleEvent) {
+        if let Some(observer) = &self.observer {
+            crate::session::observer::notify(observer, event);
+        }
+    }
+
+    /// Return the healthy cached session for `profile_id` if there is one,
+    /// otherwise dial `addr` over `endpoint`, complete the handshake, cache
+    /// the result, and return it.
+    pub async fn get_or_connect(&self, endpoint: &iroh::Endpoint, profile_id: &ProfileId, addr: impl Into<iroh::NodeAddr>) -> Result<Arc<Session>> {
+        if let Some(session) = self.healthy_session(profile_id) {
+            return Ok(session);
+        }
+
+        let connection = match crate::endpoint::connect(endpoint, addr, &self.alpn).await {
+            Ok(connection) => connection,
+            Err(err) => {
+                self.notify(LifecycleEvent::Error { profile_id: profile_id.clone(), message: err.to_string() });
+                return Err(err);
+            }
+        };
+        let (session, event) = match handshake(
+            connection,
+            self.local.clone(),
+            self.pins.clone(),
+            KeyChangePolicy::default(),
+            None,
+            DEFAULT_HANDSHAKE_TIMEOUT,
+            &CancellationToken::new(),
+        )
+        .await
+        {
+            Ok(result) => result,
+            Err(err) => {
+                self.notify(LifecycleEvent::Error { profile_id: profile_id.clone(), message: err.to_string() });
+                return Err(err);
+            }
+        };
+
+        if matches!(event, Some(SessionEvent::KeyChanged { .. })) {
+            self.notify(LifecycleEvent::KeyChanged { profile_id: profile_id.clone() });
+        }
+
+        let session = Arc::new(session);
+        self.insert(profile_id.clone(), session.clone());
+        self.notify(LifecycleEvent::Connected { profile_id: profile_id.clone() });
+        Ok(session)
+    }
+
+    /// Number of sessions currently cached, healthy or not.
+    pub fn len(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Close and drop every cached session.
+    pub fn close_all(&self) {
+        for (profile_id, session) in self.sessions.lock().unwrap().drain() {
+            session.close();
+            self.notify(LifecycleEvent::Disconnected { profile_id, reason: "pool closed".to_string() });
+        }
+        self.lru.lock().unwrap().clear();
+    }
+
+    fn healthy_session(&self, profile_id: &ProfileId) -> Option<Arc<Session>> {
+        let dead = {
+            let sessions = self.sessions.lock().unwrap();
+            match sessions.get(profile_id) {
+                Some(session) if session.is_healthy() => {
+                    let session = session.clone();
+                    drop(sessions);
+                    self.touch(profile_id);
+                    return Some(session);
+                }
+                Some(_) => true,
+                None => false,
+            }
+        };
+        if dead {
+            self.sessions.lock().unwrap().remove(profile_id);
+            self.lru.lock().unwrap().retain(|id| id != profile_id);
+            self.notify(LifecycleEvent::Disconnected { profile_id: profile_id.clone(), reason: "connection no longer healthy".to_string() });
+        }
+        None
+    }
+
+    fn touch(&self, profile_id: &ProfileId) {
+        let mut lru = self.lru.lock().unwrap();
+        lru.retain(|id| id != profile_id);
+        lru.push_back(profile_id.clone());
+    }
+
+    fn insert(&self, profile_id: ProfileId, session: Arc<Session>) {
+        self.touch(&profile_id);
+        self.sessions.lock().unwrap().insert(profile_id, session);
+        self.evict_over_capacity();
+    }
+
+    fn evict_over_capacity(&self) {
+        let mut evicted_ids = Vec::new();
+        {
+            let mut sessions = self.sessions.lock().unwrap();
+            let mut lru = self.lru.lock().unwrap();
+            while sessions.len() > self.capacity {
+                let Some(evicted) = lru.pop_front() else { break };
+                if let Some(session) = sessions.remove(&evicted) {
+                    session.close();
+                    evicted_ids.push(evicted);
+                }
+            }
+        }
+        for profile_id in evicted_ids {
+            self.notify(LifecycleEvent::Disconnected { profile_id, reason: "evicted from pool".to_string() });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::ProfileBuilder;
+    use crate::session::handshake::handshake as accept_handshake;
+
+    #[derive(Default)]
+    struct RecordingObserver {
+        events: Mutex<Vec<LifecycleEvent>>,
+    }
+
+    impl SessionObserver for RecordingObserver {
+        fn on_event(&self, event: LifecycleEvent) {
+            self.events.lock().unwrap().push(event);
+        }
+    }
+
+    async fn spawn_echo_peer(name: &str, alpn: &'static [u8]) -> (iroh::NodeAddr, tokio::task::JoinHandle<()>) {
+        let profile = Arc::new(ProfileBuilder::new().name(name).build().unwrap());
+        let endpoint = profile.make_endpoint_with_alpn(alpn).await.unwrap();
+        let addr = endpoint.node_addr().await.unwrap();
+
+        let handle = tokio::spawn(async move {
+            loop {
+                let Ok(incoming) = endpoint.accept().await else { break };
+                let Ok(connection) = incoming.await else { continue };
+                let pins = Arc::new(KeyPinStore::new());
+                let _ = accept_handshake(
+                    connection,
+                    profile.clone(),
+                    pins,
+                    KeyChangePolicy::default(),
+                    None,
+                    DEFAULT_HANDSHAKE_TIMEOUT,
+                    &CancellationToken::new(),
+                )
+                .await;
+            }
+        });
+
+        (addr, handle)
+    }
+
+    #[tokio::test]
+    async fn a_second_get_or_connect_reuses_the_cached_session() {
+        let alpn: &'static [u8] = b"resolution/pool-test";
+        let (peer_addr, peer_task) = spawn_echo_peer("bob", alpn).await;
+
+        let alice = Arc::new(ProfileBuilder::new().name("alice").build().unwrap());
+        let alice_endpoint = alice.make_endpoint_with_alpn(alpn).await.unwrap();
+        let pool = SessionPool::new(alice.clone(), alpn, 4);
+        let peer_id = ProfileId::new("bob#0000");
+
+        let first = pool.get_or_connect(&alice_endpoint, &peer_id, peer_addr.clone()).await.unwrap();
+        let second = pool.get_or_connect(&alice_endpoint, &peer_id, peer_addr).await.unwrap();
+
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(pool.len(), 1);
+
+        pool.close_all();
+        assert!(pool.is_empty());
+        peer_task.abort();
+    }
+
+    #[tokio::test]
+    async fn an_observer_sees_connected_then_disconnected_across_a_connect_close_cycle() {
+        let alpn: &'static [u8] = b"resolution/pool-observer-test";
+        let (peer_addr, peer_task) = spawn_echo_peer("bob", alpn).await;
+
+        let alice = Arc::new(ProfileBuilder::new().name("alice").build().unwrap());
+        let alice_endpoint = alice.make_endpoint_with_alpn(alpn).await.unwrap();
+        let observer = Arc::new(RecordingObserver::default());
+        let pool = SessionPool::new(alice.clone(), alpn, 4).with_observer(observer.clone());
+        let peer_id = ProfileId::new("bob#0000");
+
+        pool.get_or_connect(&alice_endpoint, &peer_id, peer_addr).await.unwrap();
+        pool.close_all();
+
+        let events = observer.events.lock().unwrap();
+        assert_eq!(*events, vec![
+            LifecycleEvent::Connected { profile_id: peer_id.clone() },
+            LifecycleEvent::Disconnected { profile_id: peer_id, reason: "pool closed".to_string() },
+        ]);
+        peer_task.abort();
+    }
+}
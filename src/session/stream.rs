@@ -0,0 +1,217 @@
+//! Multiple independently-flowing logical streams over one [`Session`],
+//! so a large file transfer doesn't head-of-line-block chat traffic (or
+//! vice versa) the way a single shared stream would.
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::{mpsc, Mutex};
+
+use crate::error::UserError;
+use crate::Result;
+
+/// Which logical channel a [`Stream`] carries. Sent as a one-byte tag at
+/// the start of the underlying QUIC stream so the receiving side can route
+/// it without a side-channel negotiation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamKind {
+    /// Chat and other latency-sensitive control traffic.
+    Control,
+    /// File transfers and other large, throughput-bound payloads.
+    Bulk,
+}
+
+impl StreamKind {
+    fn tag(self) -> u8 {
+        match self {
+            StreamKind::Control => 0,
+            StreamKind::Bulk => 1,
+        }
+    }
+
+    fn from_tag(tag: u8) -> Result<Self> {
+        match tag {
+            0 => Ok(StreamKind::Control),
+            1 => Ok(StreamKind::Bulk),
+            other => Err(anyhow::anyhow!("unknown stream kind tag {other}").into()),
+        }
+    }
+}
+
+/// One multiplexed, length-framed logical stream. Opened locally with
+/// [`crate::session::Session::open_stream`] or received with
+/// [`crate::session::Session::accept_stream`].
+pub struct Stream {
+    kind: StreamKind,
+    send: iroh::endpoint::SendStream,
+    recv: iroh::endpoint::RecvStream,
+}
+
+impl Stream {
+    pub fn kind(&self) -> StreamKind {
+        self.kind
+    }
+
+    /// Write one length-prefixed frame.
+    pub async fn write_frame(&mut self, bytes: &[u8]) -> Result<()> {
+        self.send
+            .write_all(&(bytes.len() as u32).to_be_bytes())
+            .await
+            .map_err(anyhow::Error::from)?;
+        self.send.write_all(bytes).await.map_err(anyhow::Error::from)?;
+        Ok(())
+    }
+
+    /// Read the next length-prefixed frame.
+    pub async fn read_frame(&mut self) -> Result<Vec<u8>> {
+        let mut len_buf = [0u8; 4];
+        self.recv.read_exact(&mut len_buf).await.map_err(anyhow::Error::from)?;
+        let len = u32::from_be_bytes(len_buf) as usize;
+        if len > crate::framing::MAX_ENVELOPE_LEN {
+            return Err(UserError::EnvelopeTooLarge { len, max: crate::framing::MAX_ENVELOPE_LEN }.into());
+        }
+        let mut buf = vec![0u8; len];
+        self.recv.read_exact(&mut buf).await.map_err(anyhow::Error::from)?;
+        Ok(buf)
+    }
+
+    /// Signal that no more frames will be written.
+    pub fn finish(&mut self) -> Result<()> {
+        self.send.finish().map_err(anyhow::Error::from)?;
+        Ok(())
+    }
+}
+
+/// Open a new bidirectional stream and tag it with `kind`, for
+/// [`crate::session::Session::open_stream`].
+pub(crate) async fn open(connection: &iroh::endpoint::Connection, kind: StreamKind) -> Result<Stream> {
+    let (mut send, recv) = connection.open_bi().await.map_err(anyhow::Error::from)?;
+    send.write_all(&[kind.tag()]).await.map_err(anyhow::Error::from)?;
+    Ok(Stream { kind, send, recv })
+}
+
+/// Accepts inbound bidirectional streams on a connection and sorts them
+/// into a queue per [`StreamKind`], so a caller waiting on
+/// [`StreamRouter::recv`] for one kind isn't stuck behind whatever kind
+/// happens to arrive first on the wire. Only reads the one-byte kind tag
+/// per stream — never a whole frame — so demuxing never blocks on a large
+/// payload elsewhere on the connection.
+pub(crate) struct StreamRouter {
+    control_rx: Mutex<mpsc::UnboundedReceiver<Stream>>,
+    bulk_rx: Mutex<mpsc::UnboundedReceiver<Stream>>,
+}
+
+impl StreamRouter {
+    pub(crate) fn new(connection: iroh::endpoint::Connection) -> Self {
+        let (control_tx, control_rx) = mpsc::unbounded_channel();
+        let (bulk_tx, bulk_rx) = mpsc::unbounded_channel();
+
+        crate::runtime::spawn(async move {
+            loop {
+                let (send, mut recv) = match connection.accept_bi().await {
+                    Ok(streams) => streams,
+                    Err(_) => break,
+                };
+                let mut tag = [0u8; 1];
+                if recv.read_exact(&mut tag).await.is_err() {
+                    continue;
+                }
+                let Ok(kind) = StreamKind::from_tag(tag[0]) else { continue };
+                let stream = Stream { kind, send, recv };
+                let dispatched = match kind {
+                    StreamKind::Control => control_tx.send(stream).is_ok(),
+                    StreamKind::Bulk => bulk_tx.send(stream).is_ok(),
+                };
+                if !dispatched {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            control_rx: Mutex::new(control_rx),
+            bulk_rx: Mutex::new(bulk_rx),
+        }
+    }
+
+    pub(crate) async fn recv(&self, kind: StreamKind) -> Result<Stream> {
+        let mut rx = match kind {
+            StreamKind::Control => self.control_rx.lock().await,
+            StreamKind::Bulk => self.bulk_rx.lock().await,
+        };
+        rx.recv().await.ok_or_else(|| anyhow::anyhow!("session closed while waiting for a {kind:?} stream").into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio_util::sync::CancellationToken;
+
+    use super::*;
+    use crate::endpoint::connect;
+    use crate::pinning::KeyPinStore;
+    use crate::profile::ProfileBuilder;
+    use crate::session::handshake::{handshake, KeyChangePolicy, DEFAULT_HANDSHAKE_TIMEOUT};
+
+    #[tokio::test]
+    async fn a_large_bulk_transfer_does_not_block_control_messages() {
+        let alpn: &'static [u8] = b"resolution/streams-test";
+        let alice = Arc::new(ProfileBuilder::new().name("alice").build().unwrap());
+        let bob = Arc::new(ProfileBuilder::new().name("bob").build().unwrap());
+
+        let alice_endpoint = alice.make_endpoint_with_alpn(alpn).await.unwrap();
+        let bob_endpoint = bob.make_endpoint_with_alpn(alpn).await.unwrap();
+        let bob_addr = bob_endpoint.node_addr().await.unwrap();
+
+        let bob_profile = bob.clone();
+        let bob_task = tokio::spawn(async move {
+            let incoming = bob_endpoint.accept().await.unwrap();
+            let connection = incoming.await.unwrap();
+            let pins = Arc::new(KeyPinStore::new());
+            let (session, _event) = handshake(
+                connection,
+                bob_profile,
+                pins.clone(),
+                KeyChangePolicy::default(),
+                None,
+                DEFAULT_HANDSHAKE_TIMEOUT,
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+
+            let mut control = session.accept_stream(StreamKind::Control).await.unwrap();
+            let mut bulk = session.accept_stream(StreamKind::Bulk).await.unwrap();
+            let chat = control.read_frame().await.unwrap();
+            let payload = bulk.read_frame().await.unwrap();
+            (chat, payload)
+        });
+
+        let connection = connect(&alice_endpoint, bob_addr, alpn).await.unwrap();
+        let pins = Arc::new(KeyPinStore::new());
+        let (session, _event) = handshake(
+            connection,
+            alice.clone(),
+            pins.clone(),
+            KeyChangePolicy::default(),
+            None,
+            DEFAULT_HANDSHAKE_TIMEOUT,
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        let mut bulk = session.open_stream(StreamKind::Bulk).await.unwrap();
+        let mut control = session.open_stream(StreamKind::Control).await.unwrap();
+
+        let large_payload = vec![7u8; 4 * 1024 * 1024];
+        let (bulk_result, control_result) =
+            tokio::join!(bulk.write_frame(&large_payload), control.write_frame(b"hi bob"));
+        bulk_result.unwrap();
+        control_result.unwrap();
+
+        let (chat, payload) = bob_task.await.unwrap();
+        assert_eq!(chat, b"hi bob");
+        assert_eq!(payload, large_payload);
+    }
+}
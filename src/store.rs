@@ -0,0 +1,175 @@
+//! Pluggable key-value storage backing the crate's persistent state.
+//!
+//! Generic get/put/delete/list access for state that's just a plain map,
+//! like [`crate::pinning::KeyPinStore`] and [`crate::queue::OutboundQueue`].
+//! Bring your own backend (SQLite, sled, ...) by implementing this trait
+//! instead of the crate depending on one.
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::Result;
+
+/// Typed get/put/delete/list operations over a key-value backend.
+pub trait Store<K, V>: Send + Sync {
+    fn get(&self, key: &K) -> Result<Option<V>>;
+    fn put(&self, key: K, value: V) -> Result<()>;
+    fn delete(&self, key: &K) -> Result<()>;
+    fn list(&self) -> Result<Vec<(K, V)>>;
+}
+
+/// A [`Store`] that keeps everything in memory. The default for tests and
+/// for applications that don't need this state to survive a restart.
+pub struct MemoryStore<K, V> {
+    inner: Mutex<HashMap<K, V>>,
+}
+
+impl<K, V> Default for MemoryStore<K, V> {
+    fn default() -> Self {
+        Self { inner: Mutex::new(HashMap::new()) }
+    }
+}
+
+impl<K: Eq + Hash + Clone + Send + Sync, V: Clone + Send + Sync> Store<K, V> for MemoryStore<K, V> {
+    fn get(&self, key: &K) -> Result<Option<V>> {
+        Ok(self.inner.lock().unwrap().get(key).cloned())
+    }
+
+    fn put(&self, key: K, value: V) -> Result<()> {
+        self.inner.lock().unwrap().insert(key, value);
+        Ok(())
+    }
+
+    fn delete(&self, key: &K) -> Result<()> {
+        self.inner.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<(K, V)>> {
+        Ok(self.inner.lock().unwrap().iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+    }
+}
+
+/// A [`Store`] that persists each value as its own file in a directory,
+/// keyed by string. Simple rather than efficient: fine for a handful of
+/// pinned keys or roster entries, not meant to replace a real embedded
+/// database for high-volume state.
+pub struct FilesystemStore<V> {
+    dir: PathBuf,
+    _value: PhantomData<V>,
+}
+
+impl<V> FilesystemStore<V> {
+    /// Use (creating if necessary) `dir` to store one file per key.
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir).map_err(anyhow::Error::from)?;
+        Ok(Self { dir, _value: PhantomData })
+    }
+
+    /// Reject anything that isn't safe to join onto `self.dir` as a single
+    /// path component. Keys reach a store straight from wire input (e.g. a
+    /// [`crate::identity::ProfileId`]'s string form is entirely attacker
+    /// chosen — see [`crate::discriminant::verify_discriminant`], which
+    /// constrains the discriminant suffix but not the name before it), so
+    /// this can't assume a key is already a bare filename.
+    fn path_for(&self, key: &str) -> Result<PathBuf> {
+        if key.is_empty() || key == "." || key == ".." || key.contains(['/', '\\']) {
+            return Err(crate::error::UserError::InvalidStoreKey { key: key.to_string() }.into());
+        }
+        Ok(self.dir.join(key))
+    }
+}
+
+impl<V: Serialize + DeserializeOwned + Send + Sync> Store<String, V> for FilesystemStore<V> {
+    fn get(&self, key: &String) -> Result<Option<V>> {
+        let path = self.path_for(key)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        let bytes = std::fs::read(path).map_err(anyhow::Error::from)?;
+        Ok(Some(crate::encoding::from_slice(&bytes)?))
+    }
+
+    fn put(&self, key: String, value: V) -> Result<()> {
+        let bytes = crate::encoding::to_vec(&value)?;
+        std::fs::write(self.path_for(&key)?, bytes).map_err(anyhow::Error::from)?;
+        Ok(())
+    }
+
+    fn delete(&self, key: &String) -> Result<()> {
+        let path = self.path_for(key)?;
+        if path.exists() {
+            std::fs::remove_file(path).map_err(anyhow::Error::from)?;
+        }
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<(String, V)>> {
+        let mut out = Vec::new();
+        for entry in std::fs::read_dir(&self.dir).map_err(anyhow::Error::from)? {
+            let entry = entry.map_err(anyhow::Error::from)?;
+            let key = entry.file_name().to_string_lossy().into_owned();
+            if let Some(value) = self.get(&key)? {
+                out.push((key, value));
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the same assertions against any `Store<String, String>`, so
+    /// [`MemoryStore`] and [`FilesystemStore`] are checked against one
+    /// contract instead of duplicating the scenario per backend.
+    fn exercise_store<S: Store<String, String>>(store: S) {
+        assert_eq!(store.get(&"a".to_string()).unwrap(), None);
+
+        store.put("a".to_string(), "1".to_string()).unwrap();
+        store.put("b".to_string(), "2".to_string()).unwrap();
+        assert_eq!(store.get(&"a".to_string()).unwrap(), Some("1".to_string()));
+
+        let mut listed = store.list().unwrap();
+        listed.sort();
+        assert_eq!(listed, vec![("a".to_string(), "1".to_string()), ("b".to_string(), "2".to_string())]);
+
+        store.delete(&"a".to_string()).unwrap();
+        assert_eq!(store.get(&"a".to_string()).unwrap(), None);
+        assert_eq!(store.list().unwrap(), vec![("b".to_string(), "2".to_string())]);
+    }
+
+    #[test]
+    fn memory_store_satisfies_the_store_contract() {
+        exercise_store(MemoryStore::<String, String>::default());
+    }
+
+    #[test]
+    fn filesystem_store_satisfies_the_store_contract() {
+        let dir = std::env::temp_dir().join(format!("resolution-protocol-store-test-{}", uuid::Uuid::new_v4()));
+        let store = FilesystemStore::<String>::new(&dir).unwrap();
+
+        exercise_store(store);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn filesystem_store_rejects_keys_that_would_escape_its_directory() {
+        let dir = std::env::temp_dir().join(format!("resolution-protocol-store-test-{}", uuid::Uuid::new_v4()));
+        let store = FilesystemStore::<String>::new(&dir).unwrap();
+
+        for key in ["../../../../etc/passwd", "..", ".", "a/b", "a\\b", ""] {
+            assert!(store.put(key.to_string(), "x".to_string()).is_err(), "expected {key:?} to be rejected");
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,66 @@
+//! Chunk-size arithmetic for sending a file over a session in pieces.
+//!
+//! There's no stateful file-transfer protocol type in this crate yet —
+//! callers already chunk a body by hand and seal each chunk with
+//! [`crate::crypto::EncryptionContext::encrypt_direct`] or the group
+//! streaming API. [`FileTransfer`] is just a namespace for the sizing
+//! helper that spares them from hand-deriving it from
+//! [`crate::crypto::EnvelopeOverhead`] and [`crate::session::NegotiatedParams`]
+//! themselves.
+
+use crate::constants::{MAX_FILE_TRANSFER_CHUNK_LEN, MIN_FILE_TRANSFER_CHUNK_LEN};
+use crate::crypto::EncryptionContext;
+use crate::session::NegotiatedParams;
+
+/// Namespace for file-transfer chunk-size helpers.
+pub struct FileTransfer;
+
+impl FileTransfer {
+    /// The largest plaintext chunk size that, once sealed with `context`,
+    /// still fits inside `negotiated.max_message_size` — the transport's
+    /// negotiated frame limit — leaving no more waste than necessary.
+    /// Clamped to [`MIN_FILE_TRANSFER_CHUNK_LEN`]..=[`MAX_FILE_TRANSFER_CHUNK_LEN`]
+    /// so a pathologically small or generous negotiated frame size doesn't
+    /// produce an impractical chunk size at either end.
+    ///
+    /// Takes `context` (rather than deriving overhead from `negotiated`
+    /// alone) because the per-message overhead — KEM ciphertext length,
+    /// signature bound, and the like — depends on the crypto suite
+    /// actually in use, which [`crate::crypto::EncryptionContext::overhead_bytes`]
+    /// already knows how to compute; `negotiated` only carries that
+    /// suite's *name*, not enough to re-derive its sizes without a second,
+    /// parallel algorithm-name lookup.
+    pub fn recommended_chunk_size(context: &EncryptionContext, negotiated: &NegotiatedParams) -> usize {
+        let overhead = context.overhead_bytes().direct_bytes();
+        let available = negotiated.max_message_size.saturating_sub(overhead);
+        available.clamp(MIN_FILE_TRANSFER_CHUNK_LEN, MAX_FILE_TRANSFER_CHUNK_LEN)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recommended_chunk_plus_overhead_stays_under_the_negotiated_frame_size() {
+        let context = EncryptionContext::generate().unwrap();
+        let negotiated = NegotiatedParams::for_local(&context);
+
+        let chunk_size = FileTransfer::recommended_chunk_size(&context, &negotiated);
+        let overhead = context.overhead_bytes().direct_bytes();
+
+        assert!(chunk_size + overhead <= negotiated.max_message_size);
+    }
+
+    #[test]
+    fn recommended_chunk_size_is_clamped_to_the_sane_bounds() {
+        let context = EncryptionContext::generate().unwrap();
+        let mut negotiated = NegotiatedParams::for_local(&context);
+
+        negotiated.max_message_size = 1;
+        assert_eq!(FileTransfer::recommended_chunk_size(&context, &negotiated), MIN_FILE_TRANSFER_CHUNK_LEN);
+
+        negotiated.max_message_size = usize::MAX;
+        assert_eq!(FileTransfer::recommended_chunk_size(&context, &negotiated), MAX_FILE_TRANSFER_CHUNK_LEN);
+    }
+}